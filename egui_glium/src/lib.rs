@@ -0,0 +1,90 @@
+// egui integration for glium on winit 0.30, the upstream crate is still on
+// winit 0.29 so this one is kept in the workspace
+// only the root viewport is supported
+
+use egui::epaint::ClippedShape;
+use egui::{Context, TexturesDelta, ViewportId};
+pub use egui_winit::{self, EventResponse};
+use glium::glutin::surface::WindowSurface;
+use glium::{Display, Surface};
+pub use painter::Painter;
+use winit::event::WindowEvent;
+use winit::raw_window_handle::HasDisplayHandle;
+use winit::window::Window;
+
+mod painter;
+
+pub struct EguiGlium {
+  pub egui_ctx: Context,
+  pub egui_winit: egui_winit::State,
+  pub painter: Painter,
+
+  shapes: Vec<ClippedShape>,
+  textures_delta: TexturesDelta,
+}
+
+impl EguiGlium {
+  pub fn new(
+    viewport_id: ViewportId,
+    display: &Display<WindowSurface>,
+    window: &Window,
+    display_target: &dyn HasDisplayHandle,
+  ) -> Self {
+    let painter = Painter::new(display);
+
+    let egui_ctx = Context::default();
+    let egui_winit = egui_winit::State::new(
+      egui_ctx.clone(),
+      viewport_id,
+      display_target,
+      Some(window.scale_factor() as f32),
+      Some(painter.max_texture_side()),
+    );
+
+    Self {
+      egui_ctx,
+      egui_winit,
+      painter,
+      shapes: Vec::new(),
+      textures_delta: TexturesDelta::default(),
+    }
+  }
+
+  pub fn on_event(&mut self, window: &Window, event: &WindowEvent) -> EventResponse {
+    self.egui_winit.on_window_event(window, event)
+  }
+
+  /// Run the ui, the output is drawn by the next [`EguiGlium::paint`]
+  pub fn run(&mut self, window: &Window, run_ui: impl FnMut(&Context)) {
+    let raw_input = self.egui_winit.take_egui_input(window);
+
+    let egui::FullOutput {
+      platform_output,
+      textures_delta,
+      shapes,
+      viewport_output,
+      ..
+    } = self.egui_ctx.run(raw_input, run_ui);
+
+    if viewport_output.len() > 1 {
+      log::warn!("egui_glium only supports the root viewport");
+    }
+
+    self.egui_winit.handle_platform_output(window, platform_output);
+
+    self.shapes = shapes;
+    self.textures_delta.append(textures_delta);
+  }
+
+  pub fn paint<T: Surface>(&mut self, display: &Display<WindowSurface>, target: &mut T) {
+    let shapes = std::mem::take(&mut self.shapes);
+    let textures_delta = std::mem::take(&mut self.textures_delta);
+
+    let pixels_per_point = self.egui_ctx.pixels_per_point();
+    let clipped_primitives = self.egui_ctx.tessellate(shapes, pixels_per_point);
+
+    self
+      .painter
+      .paint_and_update_textures(display, target, pixels_per_point, &clipped_primitives, &textures_delta);
+  }
+}
@@ -0,0 +1,238 @@
+// draws tessellated egui meshes with glium
+// textures are kept as srgb textures keyed by their egui id
+
+use std::borrow::Cow;
+
+use egui::epaint::{ImageDelta, Mesh, Primitive};
+use egui::{ClippedPrimitive, ImageData, Rect, TextureFilter, TextureId, TextureOptions, TexturesDelta};
+use glium::backend::Facade;
+use glium::index::PrimitiveType;
+use glium::program::ProgramCreationInput;
+use glium::texture::srgb_texture2d::SrgbTexture2d;
+use glium::texture::{ClientFormat, MipmapsOption, RawImage2d, SrgbFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::{
+  implement_vertex, uniform, Blend, BlendingFunction, CapabilitiesSource, DrawParameters, IndexBuffer,
+  LinearBlendingFactor, Program, Surface, VertexBuffer,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+  a_pos: [f32; 2],
+  a_tc: [f32; 2],
+  a_srgba: [u8; 4],
+}
+
+implement_vertex!(Vertex, a_pos, a_tc, a_srgba);
+
+struct Texture {
+  texture: SrgbTexture2d,
+  options: TextureOptions,
+}
+
+pub struct Painter {
+  max_texture_side: usize,
+  program: Program,
+  textures: ahash::HashMap<TextureId, Texture>,
+}
+
+impl Painter {
+  pub fn new(facade: &dyn Facade) -> Painter {
+    let max_texture_side = facade.get_context().get_capabilities().max_texture_size as usize;
+
+    // egui blends in gamma space, keep glium from converting the output
+    let program = Program::new(
+      facade,
+      ProgramCreationInput::SourceCode {
+        vertex_shader: include_str!("shader/vertex.glsl"),
+        tessellation_control_shader: None,
+        tessellation_evaluation_shader: None,
+        geometry_shader: None,
+        fragment_shader: include_str!("shader/fragment.glsl"),
+        transform_feedback_varyings: None,
+        outputs_srgb: true,
+        uses_point_size: false,
+      },
+    )
+    .expect("failed to compile the egui shader");
+
+    Painter {
+      max_texture_side,
+      program,
+      textures: Default::default(),
+    }
+  }
+
+  #[inline]
+  pub fn max_texture_side(&self) -> usize {
+    self.max_texture_side
+  }
+
+  /// Upload new textures, draw and free the textures egui is done with
+  pub fn paint_and_update_textures<T: Surface>(
+    &mut self,
+    facade: &dyn Facade,
+    target: &mut T,
+    pixels_per_point: f32,
+    clipped_primitives: &[ClippedPrimitive],
+    textures_delta: &TexturesDelta,
+  ) {
+    for (id, delta) in &textures_delta.set {
+      self.set_texture(facade, *id, delta);
+    }
+
+    self.paint_primitives(facade, target, pixels_per_point, clipped_primitives);
+
+    for id in &textures_delta.free {
+      self.textures.remove(id);
+    }
+  }
+
+  pub fn paint_primitives<T: Surface>(
+    &mut self,
+    facade: &dyn Facade,
+    target: &mut T,
+    pixels_per_point: f32,
+    clipped_primitives: &[ClippedPrimitive],
+  ) {
+    for ClippedPrimitive { clip_rect, primitive } in clipped_primitives {
+      match primitive {
+        Primitive::Mesh(mesh) => self.paint_mesh(facade, target, pixels_per_point, clip_rect, mesh),
+        Primitive::Callback(_) => log::warn!("egui paint callbacks aren't supported"),
+      }
+    }
+  }
+
+  fn paint_mesh<T: Surface>(
+    &self,
+    facade: &dyn Facade,
+    target: &mut T,
+    pixels_per_point: f32,
+    clip_rect: &Rect,
+    mesh: &Mesh,
+  ) {
+    debug_assert!(mesh.is_valid());
+
+    let Some(texture) = self.textures.get(&mesh.texture_id) else {
+      return;
+    };
+
+    let (width, height) = target.get_dimensions();
+
+    // clip rect in pixels, gl counts rows from the bottom
+    let clip_min_x = (pixels_per_point * clip_rect.min.x).round().clamp(0.0, width as f32) as u32;
+    let clip_min_y = (pixels_per_point * clip_rect.min.y).round().clamp(0.0, height as f32) as u32;
+    let clip_max_x = (pixels_per_point * clip_rect.max.x)
+      .round()
+      .clamp(clip_min_x as f32, width as f32) as u32;
+    let clip_max_y = (pixels_per_point * clip_rect.max.y)
+      .round()
+      .clamp(clip_min_y as f32, height as f32) as u32;
+
+    if clip_max_x == clip_min_x || clip_max_y == clip_min_y {
+      return;
+    }
+
+    let vertices: Vec<Vertex> = mesh
+      .vertices
+      .iter()
+      .map(|vertex| Vertex {
+        a_pos: [vertex.pos.x, vertex.pos.y],
+        a_tc: [vertex.uv.x, vertex.uv.y],
+        a_srgba: vertex.color.to_array(),
+      })
+      .collect();
+
+    let vertex_buffer = VertexBuffer::new(facade, &vertices).unwrap();
+    let index_buffer = IndexBuffer::new(facade, PrimitiveType::TrianglesList, &mesh.indices).unwrap();
+
+    let (magnify, minify) = match texture.options.magnification {
+      TextureFilter::Linear => (MagnifySamplerFilter::Linear, MinifySamplerFilter::Linear),
+      TextureFilter::Nearest => (MagnifySamplerFilter::Nearest, MinifySamplerFilter::Nearest),
+    };
+
+    let uniforms = uniform! {
+      u_screen_size: [width as f32 / pixels_per_point, height as f32 / pixels_per_point],
+      u_sampler: texture
+        .texture
+        .sampled()
+        .magnify_filter(magnify)
+        .minify_filter(minify)
+        .wrap_function(SamplerWrapFunction::Clamp),
+    };
+
+    // premultiplied alpha
+    let blend = Blend {
+      color: BlendingFunction::Addition {
+        source: LinearBlendingFactor::One,
+        destination: LinearBlendingFactor::OneMinusSourceAlpha,
+      },
+      alpha: BlendingFunction::Addition {
+        source: LinearBlendingFactor::OneMinusDestinationAlpha,
+        destination: LinearBlendingFactor::One,
+      },
+      ..Default::default()
+    };
+
+    let params = DrawParameters {
+      blend,
+      scissor: Some(glium::Rect {
+        left: clip_min_x,
+        bottom: height - clip_max_y,
+        width: clip_max_x - clip_min_x,
+        height: clip_max_y - clip_min_y,
+      }),
+      ..Default::default()
+    };
+
+    if let Err(e) = target.draw(&vertex_buffer, &index_buffer, &self.program, &uniforms, &params) {
+      log::error!("failed to draw egui mesh: {e}");
+    }
+  }
+
+  fn set_texture(&mut self, facade: &dyn Facade, id: TextureId, delta: &ImageDelta) {
+    let pixels: Vec<(u8, u8, u8, u8)> = match &delta.image {
+      ImageData::Color(image) => image.pixels.iter().map(|color| color.to_tuple()).collect(),
+      ImageData::Font(image) => image.srgba_pixels(None).map(|color| color.to_tuple()).collect(),
+    };
+
+    let image = RawImage2d {
+      data: Cow::Owned(pixels),
+      width: delta.image.width() as u32,
+      height: delta.image.height() as u32,
+      format: ClientFormat::U8U8U8U8,
+    };
+
+    match delta.pos {
+      // partial update of a texture egui already sent
+      Some([x, y]) => {
+        let Some(texture) = self.textures.get_mut(&id) else {
+          log::warn!("egui updated unknown texture {id:?}");
+          return;
+        };
+
+        let rect = glium::Rect {
+          left: x as u32,
+          bottom: y as u32,
+          width: image.width,
+          height: image.height,
+        };
+
+        texture.texture.main_level().write(rect, image);
+        texture.options = delta.options;
+      }
+      None => {
+        let texture = SrgbTexture2d::with_format(facade, image, SrgbFormat::U8U8U8U8, MipmapsOption::NoMipmap).unwrap();
+
+        self.textures.insert(
+          id,
+          Texture {
+            texture,
+            options: delta.options,
+          },
+        );
+      }
+    }
+  }
+}
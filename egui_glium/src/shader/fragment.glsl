@@ -0,0 +1,24 @@
+#version 140
+
+uniform sampler2D u_sampler;
+
+in vec4 v_rgba_in_gamma;
+in vec2 v_tc;
+
+out vec4 f_color;
+
+// 0-1 srgb gamma from 0-1 linear
+vec3 srgb_gamma_from_linear(vec3 rgb) {
+  bvec3 cutoff = lessThan(rgb, vec3(0.0031308));
+  vec3 lower = rgb * vec3(12.92);
+  vec3 higher = vec3(1.055) * pow(rgb, vec3(1.0 / 2.4)) - vec3(0.055);
+  return mix(higher, lower, vec3(cutoff));
+}
+
+void main() {
+  // textures are srgb so sampling linearizes them, egui blends in gamma space
+  vec4 texture_in_gamma = texture(u_sampler, v_tc);
+  texture_in_gamma.rgb = srgb_gamma_from_linear(texture_in_gamma.rgb);
+
+  f_color = v_rgba_in_gamma * texture_in_gamma;
+}
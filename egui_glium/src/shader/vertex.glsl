@@ -0,0 +1,17 @@
+#version 140
+
+uniform vec2 u_screen_size;
+
+in vec2 a_pos;
+// 0-255 srgb
+in vec4 a_srgba;
+in vec2 a_tc;
+
+out vec4 v_rgba_in_gamma;
+out vec2 v_tc;
+
+void main() {
+  gl_Position = vec4(2.0 * a_pos.x / u_screen_size.x - 1.0, 1.0 - 2.0 * a_pos.y / u_screen_size.y, 0.0, 1.0);
+  v_rgba_in_gamma = a_srgba / 255.0;
+  v_tc = a_tc;
+}
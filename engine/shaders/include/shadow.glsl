@@ -0,0 +1,28 @@
+// variance shadow mapping, shadowMap holds the depth and depth squared
+
+uniform sampler2D shadowMap;
+
+// ----------------------------------------------------------------------------
+// Piecewise linear interpolation
+float linstep(const float low, const float high, const float value) {
+    return clamp((value - low) / (high - low), 0.0, 1.0);
+}
+
+// ----------------------------------------------------------------------------
+// Variance shadow mapping
+float ComputeShadow(const vec4 fragPosLightSpace) {
+    // Perspective divide
+    vec2 screenCoords = fragPosLightSpace.xy / fragPosLightSpace.w;
+    screenCoords = screenCoords * 0.5 + 0.5; // [0, 1]
+
+    const float distance = fragPosLightSpace.z; // Use raw distance instead of linear junk
+    const vec2 moments = texture(shadowMap, screenCoords.xy).rg;
+
+    const float p = step(distance, moments.x);
+    const float variance = max(moments.y - (moments.x * moments.x), 0.00002);
+    const float d = distance - moments.x;
+    const float pMax = linstep(0.2, 1.0, variance / (variance + d*d)); // Solve light bleeding
+
+   return min(max(p, pMax), 1.0);
+}
+
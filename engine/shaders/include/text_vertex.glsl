@@ -0,0 +1,6 @@
+// passed from text.vert to text.frag
+
+struct VertexOutput {
+	vec4 color;
+	vec2 tex_coords;
+};
@@ -8,11 +8,6 @@ in FragData {
     noperspective vec3 wireframeDist;
 } fragData;
 
-// IBL
-uniform samplerCube irradianceMap;
-uniform samplerCube prefilterMap;
-uniform sampler2D brdfLUT;
-
 // PBR material parameters
 uniform sampler2D albedoMap;
 uniform sampler2D normalMap;
@@ -20,9 +15,6 @@ uniform sampler2D metallicMap;
 uniform sampler2D roughnessMap;
 //uniform sampler2D aoMap;
 
-// Shadow map
-uniform sampler2D shadowMap;
-
 uniform float bloomThreshold;
 
 // lights
@@ -38,29 +30,8 @@ layout (location = 1) out vec4 BrightColor;
 
 const float PI = 3.14159265359;
 
-// ----------------------------------------------------------------------------
-// Piecewise linear interpolation
-float linstep(const float low, const float high, const float value) {
-    return clamp((value - low) / (high - low), 0.0, 1.0);
-}
-
-// ----------------------------------------------------------------------------
-// Variance shadow mapping
-float ComputeShadow(const vec4 fragPosLightSpace) {
-    // Perspective divide
-    vec2 screenCoords = fragPosLightSpace.xy / fragPosLightSpace.w;
-    screenCoords = screenCoords * 0.5 + 0.5; // [0, 1]
-
-    const float distance = fragPosLightSpace.z; // Use raw distance instead of linear junk
-    const vec2 moments = texture(shadowMap, screenCoords.xy).rg;
-
-    const float p = step(distance, moments.x);
-    const float variance = max(moments.y - (moments.x * moments.x), 0.00002);
-    const float d = distance - moments.x;
-    const float pMax = linstep(0.2, 1.0, variance / (variance + d*d)); // Solve light bleeding
-
-   return min(max(p, pMax), 1.0);
-}
+#include "include/shadow.glsl"
+#include "include/ibl.glsl"
 
 // ----------------------------------------------------------------------------
 float DistributionGGX(vec3 N, vec3 H, float roughness) {
@@ -99,10 +70,6 @@ vec3 fresnelSchlick(float cosTheta, vec3 F0) {
     return F0 + (1.0 - F0) * pow(1.0 - cosTheta, 5.0);
 }
 // ----------------------------------------------------------------------------
-vec3 fresnelSchlickRoughness(float cosTheta, vec3 F0, float roughness) {
-    return F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(1.0 - cosTheta, 5.0);
-}   
-// ----------------------------------------------------------------------------
 void main() {       
     // material properties
     const vec3 albedo = pow(texture(albedoMap, fragData.TexCoords).rgb, vec3(2.2));
@@ -160,22 +127,7 @@ void main() {
 }
     
     // ambient lighting (we now use IBL as the ambient term)
-    const vec3 F = fresnelSchlickRoughness(max(dot(N, V), 0.0), F0, roughness);
-    
-    vec3 kS = F;
-    vec3 kD = 1.0 - kS;
-    kD *= 1.0 - metallic;     
-    
-    vec3 irradiance = texture(irradianceMap, N).rgb;
-    vec3 diffuse    = irradiance * albedo;
-    
-    // sample both the pre-filter map and the BRDF lut and combine them together as per the Split-Sum approximation to get the IBL specular part.
-    const float MAX_REFLECTION_LOD = 4.0;
-    vec3 prefilteredColor = textureLod(prefilterMap, R,  roughness * MAX_REFLECTION_LOD).rgb;    
-    vec2 brdf  = texture(brdfLUT, vec2(max(dot(N, V), 0.0), roughness)).rg;
-    vec3 specular = prefilteredColor * (F * brdf.x + brdf.y);
-    
-    const vec3 ambient = kD * diffuse + specular; // * ao;
+    const vec3 ambient = ComputeAmbient(N, V, R, F0, albedo, metallic, roughness); // * ao;
     
     vec3 color = ambient * 0.5 + Lo;
 
@@ -2,10 +2,7 @@
 
 layout (location = 0) out vec4 o_color;
 
-struct VertexOutput {
-	vec4 color;
-	vec2 tex_coords;
-};
+#include "include/text_vertex.glsl"
 
 layout (location = 0) in VertexOutput Input;
 
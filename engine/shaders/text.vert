@@ -6,10 +6,7 @@ layout (location = 2) in vec2 tex_coords;
 
 uniform mat4 u_view_projection;
 
-struct VertexOutput {
-	vec4 color;
-	vec2 tex_coords;
-};
+#include "include/text_vertex.glsl"
 
 layout (location = 0) out VertexOutput Output;
 
@@ -1,30 +1,63 @@
 use std::num::NonZeroU32;
+use std::time::Instant;
 
+use egui::ViewportId;
 use glium::Display;
 use glutin::config::ConfigTemplateBuilder;
 use glutin::context::{ContextApi, ContextAttributesBuilder, Version};
 use glutin::display::GetGlDisplay;
 use glutin::prelude::*;
-use glutin::surface::{SurfaceAttributesBuilder, SwapInterval, WindowSurface};
+use glutin::surface::{SurfaceAttributesBuilder, WindowSurface};
+use glutin_winit::event_loop::GlutinEventLoop;
 use glutin_winit::DisplayBuilder;
 use raw_window_handle::HasWindowHandle;
-use glutin_winit::event_loop::GlutinEventLoop;
-use winit::window::{Window, WindowAttributes};
+use winit::dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::event::{DeviceEvent, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
+use winit::keyboard::PhysicalKey;
+use winit::window::{CursorGrabMode, Fullscreen, Window, WindowAttributes, WindowId};
 
-use crate::window::set_viewport;
-use crate::VSYNC;
+use crate::debug::Egui;
+use crate::engine::config::{CursorMode, EngineConfig, WindowCommand};
+use crate::engine::input::record::InputEvent;
+use crate::engine::input::{normalize_position, LogicalKey};
+use crate::engine::layer::{Layer, LayerStack};
+use crate::engine::{create_context, EngineContext};
+use crate::math::vec2;
+use crate::render::target::Target;
+use crate::window::Viewport;
 
 pub struct AppState {
   pub display: Display<WindowSurface>,
   pub window: Window,
+  gl_config: glutin::config::Config,
+  ime_allowed: bool,
 }
 
 impl AppState {
-  pub fn new(event_loop: &impl GlutinEventLoop, window_title: &'static str, visible: bool) -> AppState {
-    let window_attributes = WindowAttributes::default()
-      .with_title(window_title)
-      .with_visible(visible);
-    let config_template_builder = ConfigTemplateBuilder::new().with_multisampling(4);
+  pub fn new(event_loop: &impl GlutinEventLoop, config: &EngineConfig, visible: bool) -> AppState {
+    let mut window_attributes = WindowAttributes::default()
+      .with_title(config.title.clone())
+      .with_visible(visible)
+      .with_resizable(config.resizable)
+      .with_decorations(config.decorations)
+      .with_fullscreen(fullscreen(config.fullscreen))
+      .with_cursor(config.cursor_icon);
+
+    if let Some((width, height)) = config.size {
+      window_attributes = window_attributes.with_inner_size(LogicalSize::new(width, height));
+    }
+
+    if let Some((x, y)) = config.position {
+      window_attributes = window_attributes.with_position(LogicalPosition::new(x, y));
+    }
+
+    let mut config_template_builder = ConfigTemplateBuilder::new();
+
+    if config.msaa > 0 {
+      config_template_builder = config_template_builder.with_multisampling(config.msaa);
+    }
+
     let display_builder = DisplayBuilder::new().with_window_attributes(Some(window_attributes));
 
     let (window, gl_config) = display_builder
@@ -34,53 +67,582 @@ impl AppState {
       .unwrap();
     let window = window.unwrap();
 
-    let raw_window_handle = window.window_handle().unwrap().as_raw();
-    let context_attributes = ContextAttributesBuilder::new()
-      .with_context_api(ContextApi::OpenGl(Some(Version::new(4, 0))))
-      .build(Some(raw_window_handle));
-
-    let not_current_gl_context = unsafe {
-      gl_config
-        .display()
-        .create_context(&gl_config, &context_attributes)
-        .unwrap()
-    };
-
     // Determine our framebuffer size based on the window size, or default to
-    // 800x600 if it's invisible
-    let (width, height): (u32, u32) = if visible {
+    // the configured size or 800x600 if it's invisible
+    let size = if visible {
       window.inner_size().into()
     } else {
-      (800, 600)
+      config.size.unwrap_or((800, 600))
     };
 
-    // set engine viewport size used for camera and stuff
-    set_viewport((width, height));
+    let display = create_display(&window, &gl_config, config, size);
+
+    // neither can be set before the window exists
+    window.set_cursor_visible(config.cursor_visible);
+    set_cursor_mode(&window, config.cursor_mode);
+
+    AppState {
+      display,
+      window,
+      gl_config,
+      ime_allowed: false,
+    }
+  }
+
+  /// Drops the gl context and creates a new one on the same window, glium
+  /// owns the surface so this is the only way to change the swap interval
+  pub fn rebuild(self, config: &EngineConfig) -> AppState {
+    let AppState {
+      display,
+      window,
+      gl_config,
+      ime_allowed,
+    } = self;
+
+    // the window can only have one surface at a time
+    drop(display);
+    let display = create_display(&window, &gl_config, config, window.inner_size().into());
+
+    AppState {
+      display,
+      window,
+      gl_config,
+      ime_allowed,
+    }
+  }
 
-    let attrs = SurfaceAttributesBuilder::<WindowSurface>::new().build(
+  pub fn apply(&self, command: WindowCommand) {
+    match command {
+      WindowCommand::Title(title) => self.window.set_title(&title),
+      WindowCommand::Size((width, height)) => {
+        // the resize event that follows updates the display and viewport
+        let _ = self.window.request_inner_size(LogicalSize::new(width, height));
+      }
+      WindowCommand::Position((x, y)) => self.window.set_outer_position(LogicalPosition::new(x, y)),
+      WindowCommand::Resizable(resizable) => self.window.set_resizable(resizable),
+      WindowCommand::Decorations(decorations) => self.window.set_decorations(decorations),
+      WindowCommand::Fullscreen(enabled) => self.window.set_fullscreen(fullscreen(enabled)),
+      // applied from the config once the window is focused again
+      WindowCommand::CursorMode(_) if !self.window.has_focus() => {}
+      WindowCommand::CursorMode(mode) => set_cursor_mode(&self.window, mode),
+      WindowCommand::CursorVisible(visible) => self.window.set_cursor_visible(visible),
+      WindowCommand::CursorIcon(icon) => self.window.set_cursor(icon),
+      // needs a new context, handled by the app
+      WindowCommand::Vsync(_) => {}
+      WindowCommand::ImeCursorArea { position, size } => self.window.set_ime_cursor_area(
+        PhysicalPosition::new(position.0, position.1),
+        PhysicalSize::new(size.0, size.1),
+      ),
+    }
+  }
+
+  /// Only touches the window when the value changes, platforms reset the
+  /// composition when ime is enabled again
+  pub fn set_ime_allowed(&mut self, allowed: bool) {
+    if self.ime_allowed != allowed {
+      self.ime_allowed = allowed;
+      self.window.set_ime_allowed(allowed);
+    }
+  }
+}
+
+fn create_display(
+  window: &Window,
+  gl_config: &glutin::config::Config,
+  config: &EngineConfig,
+  (width, height): (u32, u32),
+) -> Display<WindowSurface> {
+  let (major, minor) = config.gl_version;
+
+  let raw_window_handle = window.window_handle().unwrap().as_raw();
+  let mut context_attributes =
+    ContextAttributesBuilder::new().with_context_api(ContextApi::OpenGl(Some(Version::new(major, minor))));
+
+  if let Some(profile) = config.gl_profile {
+    context_attributes = context_attributes.with_profile(profile);
+  }
+
+  let context_attributes = context_attributes.build(Some(raw_window_handle));
+
+  let not_current_gl_context = unsafe {
+    gl_config
+      .display()
+      .create_context(gl_config, &context_attributes)
+      .unwrap()
+  };
+
+  // a minimized window can report a zero size, the next resize fixes it up
+  let attrs = SurfaceAttributesBuilder::<WindowSurface>::new()
+    .with_srgb(config.srgb)
+    .build(
       raw_window_handle,
-      NonZeroU32::new(width).unwrap(),
-      NonZeroU32::new(height).unwrap(),
+      NonZeroU32::new(width).unwrap_or(NonZeroU32::MIN),
+      NonZeroU32::new(height).unwrap_or(NonZeroU32::MIN),
     );
-    // Now we can create our surface, use it to make our context current and finally
-    // create our display
-    let surface = unsafe { gl_config.display().create_window_surface(&gl_config, &attrs).unwrap() };
-    let current_context = not_current_gl_context.make_current(&surface).unwrap();
+  // Now we can create our surface, use it to make our context current and finally
+  // create our display
+  let surface = unsafe { gl_config.display().create_window_surface(gl_config, &attrs).unwrap() };
+  let current_context = not_current_gl_context.make_current(&surface).unwrap();
 
-    let swap_interval = if VSYNC {
-      SwapInterval::Wait(NonZeroU32::new(1).unwrap())
-    } else {
-      SwapInterval::DontWait
-    };
+  // some drivers don't support every interval, run with their default
+  // rather than not at all
+  if let Err(e) = surface.set_swap_interval(&current_context, config.vsync.into()) {
+    warn!(
+      "failed to set vsync to {:?}, keeping the driver's default: {e}",
+      config.vsync
+    );
+  }
 
-    surface.set_swap_interval(&current_context, swap_interval).unwrap();
-    let display = Display::from_context_surface(current_context, surface).unwrap();
+  Display::from_context_surface(current_context, surface).unwrap()
+}
 
-    AppState { display, window }
+// window events turned into an InputEvent, focus changes still reach the
+// layers during playback
+fn is_input(event: &WindowEvent) -> bool {
+  matches!(
+    event,
+    WindowEvent::CursorMoved { .. }
+      | WindowEvent::CursorEntered { .. }
+      | WindowEvent::CursorLeft { .. }
+      | WindowEvent::MouseInput { .. }
+      | WindowEvent::MouseWheel { .. }
+      | WindowEvent::KeyboardInput { .. }
+      | WindowEvent::ModifiersChanged(_)
+      | WindowEvent::Ime(_)
+      | WindowEvent::Touch(_)
+      | WindowEvent::PinchGesture { .. }
+      | WindowEvent::RotationGesture { .. }
+  )
+}
+
+fn fullscreen(enabled: bool) -> Option<Fullscreen> {
+  enabled.then_some(Fullscreen::Borderless(None))
+}
+
+// platforms support either or both of confining and locking, use the other
+// one when the requested mode isn't available
+fn set_cursor_mode(window: &Window, mode: CursorMode) {
+  let result = match mode {
+    CursorMode::Free => window.set_cursor_grab(CursorGrabMode::None),
+    CursorMode::Confined => window
+      .set_cursor_grab(CursorGrabMode::Confined)
+      .or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked)),
+    CursorMode::Locked => window
+      .set_cursor_grab(CursorGrabMode::Locked)
+      .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined)),
+  };
+
+  if let Err(e) = result {
+    warn!("failed to set cursor mode to {mode:?}: {e}");
   }
 }
 
-pub struct App {
-  pub window_title: &'static str,
+/// One os window with its own gl context, egui, layers and engine context
+pub struct App<T: 'static = ()> {
+  /// Used to create the window again on resume, kept in sync with runtime
+  /// changes
+  pub config: EngineConfig,
+  pub layers: LayerStack<T>,
   pub state: Option<AppState>,
+  pub egui: Option<Egui>,
+  pub context: Option<EngineContext<T>>,
+  /// A vsync change that waits for the window to have a size again
+  rebuild_pending: bool,
+}
+
+impl<T: 'static> App<T> {
+  /// The window is created on [`App::resume`]
+  pub fn new(config: EngineConfig, layers: Vec<Box<dyn Layer<T>>>) -> Self {
+    Self {
+      config,
+      layers: LayerStack::new(layers),
+      state: None,
+      egui: None,
+      context: None,
+      rebuild_pending: false,
+    }
+  }
+
+  /// `None` while suspended
+  #[inline]
+  pub fn id(&self) -> Option<WindowId> {
+    self.state.as_ref().map(|state| state.window.id())
+  }
+
+  pub(crate) fn resume(&mut self, event_loop: &ActiveEventLoop, event_proxy: &EventLoopProxy<T>) {
+    let state = AppState::new(event_loop, &self.config, true);
+
+    match self.context.as_mut() {
+      // coming back from a suspend, everything but the gpu objects survived
+      Some(context) => {
+        context.restore_gpu(&state.display);
+        // don't simulate the time spent suspended
+        context.last_time = Instant::now();
+
+        for layer in &mut self.layers {
+          layer.on_context_restored(context);
+        }
+      }
+      None => {
+        let mut context = create_context(&state.display, self.config.clone());
+        context.event_proxy = Some(event_proxy.clone());
+
+        self.context = Some(context);
+      }
+    }
+
+    let context = self.context.as_mut().unwrap();
+    context.window_id = Some(state.window.id());
+    context.viewport = state.window.inner_size().into();
+
+    // attaches the initial layers
+    self.layers.apply_commands(context);
+    self.egui = Some(Egui::new(ViewportId::ROOT, &state.display, &state.window, event_loop));
+    self.state = Some(state);
+  }
+
+  // the gl context goes away with the window, keep the engine context and
+  // layers but drop every gpu object so the context is actually released
+  pub(crate) fn suspend(&mut self) {
+    if let Some(context) = self.context.as_mut() {
+      for layer in &mut self.layers {
+        layer.on_context_lost(context);
+      }
+
+      context.release_gpu();
+      context.window_id = None;
+    }
+
+    self.egui = None;
+    self.state = None;
+    // the new context is created with the current config anyway
+    self.rebuild_pending = false;
+  }
+
+  /// Detach every layer, the window closes when this is dropped
+  pub(crate) fn close(&mut self) {
+    if let Some(context) = self.context.as_mut() {
+      self.layers.clear(context);
+    }
+  }
+
+  #[inline]
+  pub(crate) fn close_requested(&self) -> bool {
+    self.context.as_ref().is_some_and(|context| context.close_requested)
+  }
+
+  /// # Safety
+  ///
+  /// `self.context` must be `Some` before calling this function
+  pub(crate) fn update(&mut self) {
+    let context = self.context.as_mut().unwrap();
+    let now = Instant::now();
+    let delta_time = now.duration_since(context.last_time).as_secs_f32();
+    context.last_time = now;
+
+    let ime_allowed = self.layers.update_frame(context, delta_time);
+
+    if let Some(state) = self.state.as_mut() {
+      state.set_ime_allowed(ime_allowed);
+    }
+
+    self.apply_window_commands();
+  }
+
+  /// # Safety
+  ///
+  /// `self.context` must be `Some` before calling this function
+  fn apply_window_commands(&mut self) {
+    let context = self.context.as_mut().unwrap();
+
+    if context.window_commands.is_empty() {
+      return;
+    }
+
+    let mut rebuild = false;
+
+    // while suspended the window picks the changes up from the config once it
+    // is recreated
+    match self.state.as_ref() {
+      Some(state) => context.window_commands.drain(..).for_each(|command| match command {
+        WindowCommand::Vsync(_) => rebuild = true,
+        command => state.apply(command),
+      }),
+      None => context.window_commands.clear(),
+    }
+
+    // keep runtime changes when the window is recreated
+    self.config.clone_from(context.config());
+
+    if rebuild {
+      self.rebuild_pending = true;
+      self.rebuild_display();
+    }
+  }
+
+  // like a suspend and resume that keeps the window, egui starts over
+  // waits for the next resize while minimized, a surface can't be zero sized
+  fn rebuild_display(&mut self) {
+    let Some(state) = self.state.as_ref() else {
+      return;
+    };
+
+    let size = state.window.inner_size();
+
+    if !self.rebuild_pending || size.width == 0 || size.height == 0 {
+      return;
+    }
+
+    self.rebuild_pending = false;
+
+    let context = self.context.as_mut().unwrap();
+
+    for layer in &mut self.layers {
+      layer.on_context_lost(context);
+    }
+
+    context.release_gpu();
+    self.egui = None;
+
+    let state = self.state.take().unwrap().rebuild(&self.config);
+    context.restore_gpu(&state.display);
+
+    for layer in &mut self.layers {
+      layer.on_context_restored(context);
+    }
+
+    self.egui = Some(Egui::new(
+      ViewportId::ROOT,
+      &state.display,
+      &state.window,
+      &state.window,
+    ));
+    self.state = Some(state);
+  }
+
+  /// # Safety
+  ///
+  /// `self.context`, `self.state` and `self.egui` must be `Some` before calling
+  /// this function
+  pub(crate) fn draw(&mut self) {
+    let state = self.state.as_ref().unwrap();
+
+    let mut frame = Target::Frame(state.display.draw());
+
+    let context = self.context.as_mut().unwrap();
+    let profiler = context.profiler.clone();
+
+    self.layers.draw_frame(context, &mut frame, &state.display);
+
+    let egui_scope = profiler.scope("egui");
+    let egui = self.egui.as_mut().unwrap();
+
+    egui.run(&state.window, |ctx| {
+      for layer in &mut self.layers {
+        let _scope = profiler.scope(layer.name());
+        layer.egui(context, ctx.clone());
+      }
+    });
+
+    // input for the next frame is filtered by what egui wants after this one,
+    // recorded like any other input and replaced by the recording on playback
+    let (keyboard, pointer) = (egui.wants_keyboard_input(), egui.wants_pointer_input());
+
+    if (keyboard, pointer) != (context.input.egui_wants_keyboard(), context.input.egui_wants_pointer()) {
+      let event = InputEvent::EguiFocus { keyboard, pointer };
+      context.input_replay.live_event(&mut context.input, event);
+    }
+
+    egui.paint(&state.display, &mut frame);
+    context.capture.capture(&frame, &state.display, true);
+
+    drop(egui_scope);
+
+    {
+      let _scope = profiler.scope("present");
+      frame.finish().unwrap();
+    }
+
+    profiler.end_frame();
+  }
+
+  /// # Safety
+  ///
+  /// `self.context` and `self.state` must be `Some` before calling this
+  /// function
+  pub(crate) fn resize(&mut self, size: PhysicalSize<u32>) {
+    // minimized, the surface keeps its old size until the window is back
+    if size.width == 0 || size.height == 0 {
+      return;
+    }
+
+    let state = self.state.as_ref().unwrap();
+    state.display.resize(size.into());
+
+    self.context.as_mut().unwrap().viewport = Viewport::from(size);
+
+    self.rebuild_display();
+  }
+
+  /// # Safety
+  ///
+  /// `self.context`, `self.state` and `self.egui` must be `Some` before calling
+  /// this function
+  pub(crate) fn handle_window_event(&mut self, event: WindowEvent) {
+    let state = self.state.as_ref().unwrap();
+
+    // the grab is released while unfocused, some platforms drop it anyway
+    match event {
+      WindowEvent::Focused(true) => set_cursor_mode(&state.window, self.config.cursor_mode),
+      WindowEvent::Focused(false) => set_cursor_mode(&state.window, CursorMode::Free),
+      _ => (),
+    }
+
+    // a recording replaces live input, egui and the layers only see what it
+    // plays back through Input
+    if is_input(&event) && self.context.as_ref().unwrap().input_replay.is_playing() {
+      return;
+    }
+
+    // send events to egui first, input still sees everything it consumes so
+    // releases aren't lost, queries are filtered while egui wants the input
+    let egui_consumed = self.egui.as_mut().unwrap().handle_window_event(&event, &state.window);
+
+    let context = self.context.as_mut().unwrap();
+
+    let mut input_events = Vec::new();
+
+    match &event {
+      WindowEvent::CursorMoved { position, .. } => {
+        let (width, height): (f32, f32) = state.window.inner_size().into();
+        let (x, y) = (position.x as f32, position.y as f32);
+        let normalized = normalize_position(vec2(x, y), vec2(width, height));
+
+        input_events.push(InputEvent::MousePosition {
+          x: normalized.x,
+          y: normalized.y,
+        });
+        input_events.push(InputEvent::MousePixelPosition { x, y });
+      }
+      WindowEvent::CursorEntered { .. } => input_events.push(InputEvent::CursorEntered),
+      WindowEvent::CursorLeft { .. } => input_events.push(InputEvent::CursorLeft),
+      WindowEvent::MouseInput { state, button, .. } => input_events.push(InputEvent::MouseButton {
+        button: *button,
+        state: *state,
+      }),
+      WindowEvent::MouseWheel { delta, .. } => input_events.push(match *delta {
+        MouseScrollDelta::LineDelta(x, y) => InputEvent::Scroll { x, y },
+        MouseScrollDelta::PixelDelta(delta) => InputEvent::PixelScroll {
+          x: delta.x as f32,
+          y: delta.y as f32,
+        },
+      }),
+      WindowEvent::KeyboardInput { event, .. } => {
+        input_events.push(InputEvent::Key {
+          key: event.physical_key.into(),
+          state: event.state,
+        });
+
+        let logical_key = LogicalKey::from_winit(&event.logical_key);
+
+        if let Some(logical_key) = context
+          .input
+          .logical_key_for(event.physical_key, logical_key, event.state)
+        {
+          input_events.push(InputEvent::Key {
+            key: logical_key.into(),
+            state: event.state,
+          });
+        }
+
+        if let Some(text) = event.text.as_ref().filter(|_| event.state.is_pressed()) {
+          input_events.push(InputEvent::Text(text.to_string()));
+        }
+      }
+      WindowEvent::ModifiersChanged(modifiers) => input_events.push(InputEvent::Modifiers(modifiers.state())),
+      WindowEvent::Ime(ime) => input_events.push(InputEvent::Ime(ime.clone().into())),
+      WindowEvent::Touch(touch) => {
+        let (width, height): (f32, f32) = state.window.inner_size().into();
+        let (x, y) = (touch.location.x as f32, touch.location.y as f32);
+        let normalized = normalize_position(vec2(x, y), vec2(width, height));
+
+        input_events.push(InputEvent::Touch {
+          id: touch.id,
+          phase: touch.phase,
+          x,
+          y,
+          normalized_x: normalized.x,
+          normalized_y: normalized.y,
+        });
+      }
+      WindowEvent::PinchGesture { delta, .. } => input_events.push(InputEvent::PinchGesture(*delta as f32)),
+      // winit turns counterclockwise in degrees
+      WindowEvent::RotationGesture { delta, .. } => input_events.push(InputEvent::RotationGesture(-delta.to_radians())),
+      WindowEvent::Focused(false) => input_events.push(InputEvent::FocusLost),
+      _ => (),
+    }
+
+    for input_event in input_events {
+      context.input_replay.live_event(&mut context.input, input_event);
+    }
+
+    if egui_consumed {
+      return;
+    }
+
+    if let WindowEvent::KeyboardInput { event, .. } = &event {
+      if let PhysicalKey::Code(code) = event.physical_key {
+        if event.state.is_pressed() && !event.repeat && context.capture.handle_hotkey(code) {
+          return;
+        }
+      }
+    }
+
+    for layer in self.layers.iter_mut().rev() {
+      if layer.handle_window_event(context, &event, &state.window) {
+        break; // event was consumed
+      }
+    }
+
+    self.layers.apply_commands(context);
+    self.apply_window_commands();
+  }
+
+  /// Raw mouse motion while the window is focused
+  pub(crate) fn handle_device_event(&mut self, event: &DeviceEvent) {
+    let (Some(state), Some(context)) = (self.state.as_ref(), self.context.as_mut()) else {
+      return;
+    };
+
+    if !state.window.has_focus() {
+      return;
+    }
+
+    if let DeviceEvent::MouseMotion { delta: (x, y) } = *event {
+      let event = InputEvent::RawMouseMotion {
+        x: x as f32,
+        y: y as f32,
+      };
+
+      context.input_replay.live_event(&mut context.input, event);
+    }
+  }
+
+  /// Returns true if a layer consumed the event
+  pub(crate) fn handle_user_event(&mut self, event: &T) -> bool {
+    let Some(context) = self.context.as_mut() else {
+      return false;
+    };
+
+    let consumed = self
+      .layers
+      .iter_mut()
+      .rev()
+      .any(|layer| layer.handle_user_event(context, event));
+
+    self.layers.apply_commands(context);
+    self.apply_window_commands();
+
+    consumed
+  }
 }
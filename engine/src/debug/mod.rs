@@ -1,9 +1,10 @@
+pub mod profiler;
 pub mod widgets;
 
 use egui::{Context, ViewportId};
 use egui_glium::EguiGlium;
 use glium::glutin::surface::WindowSurface;
-use glium::Display;
+use glium::{Display, Surface};
 use raw_window_handle::HasDisplayHandle;
 use winit::event::WindowEvent;
 use winit::window::Window;
@@ -32,7 +33,17 @@ impl Egui {
     self.egui_glium.run(window, run_ui)
   }
 
-  pub fn paint(&mut self, display: &Display<WindowSurface>, frame: &mut glium::Frame) {
+  /// A text field has focus
+  pub fn wants_keyboard_input(&self) -> bool {
+    self.egui_glium.egui_ctx.wants_keyboard_input()
+  }
+
+  /// The pointer is over or dragging an egui area
+  pub fn wants_pointer_input(&self) -> bool {
+    self.egui_glium.egui_ctx.wants_pointer_input()
+  }
+
+  pub fn paint<S: Surface>(&mut self, display: &Display<WindowSurface>, frame: &mut S) {
     self.egui_glium.paint(display, frame)
   }
 }
@@ -0,0 +1,567 @@
+// frame profiler
+// cpu scopes are timed with Instant, gpu work with glium time elapsed
+// queries which are read back a few frames later without stalling
+
+use std::borrow::Cow;
+use std::cell::{Ref, RefCell};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::mem;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use glium::backend::Facade;
+use glium::draw_parameters::TimeElapsedQuery;
+
+pub const DEFAULT_HISTORY: usize = 300;
+
+/// Timed cpu scope, times are relative to the start of its frame
+#[derive(Debug, Clone)]
+pub struct CpuScope {
+  pub name: Cow<'static, str>,
+  /// Number of enclosing scopes
+  pub depth: u32,
+  pub start: Duration,
+  pub duration: Duration,
+}
+
+/// Gpu time summed over every query with the same name in a frame
+#[derive(Debug, Clone)]
+pub struct GpuScope {
+  pub name: Cow<'static, str>,
+  pub duration: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameCapture {
+  pub index: u64,
+  /// Since the profiler was created
+  pub start: Duration,
+  pub duration: Duration,
+  pub cpu: Vec<CpuScope>,
+  /// Filled in once the queries are ready, usually a couple frames later
+  pub gpu: Vec<GpuScope>,
+}
+
+impl FrameCapture {
+  fn new(index: u64, start: Duration) -> Self {
+    Self {
+      index,
+      start,
+      duration: Duration::ZERO,
+      cpu: Vec::new(),
+      gpu: Vec::new(),
+    }
+  }
+}
+
+struct PendingQuery {
+  frame: u64,
+  name: Cow<'static, str>,
+  query: Rc<TimeElapsedQuery>,
+}
+
+struct Inner {
+  enabled: bool,
+  paused: bool,
+  epoch: Instant,
+  frame_start: Instant,
+  current: FrameCapture,
+  /// Indices into `current.cpu` of the open scopes
+  stack: Vec<usize>,
+  history: VecDeque<FrameCapture>,
+  capacity: usize,
+  pending: Vec<PendingQuery>,
+  selected: Option<u64>,
+}
+
+/// Records cpu and gpu timings per frame and keeps the last few frames
+///
+/// Cheap to clone, every clone records into the same captures so a layer can
+/// keep one around for [`Profiler::scope`]
+#[derive(Clone)]
+pub struct Profiler {
+  inner: Rc<RefCell<Inner>>,
+}
+
+impl Profiler {
+  pub fn new() -> Self {
+    let now = Instant::now();
+
+    Self {
+      inner: Rc::new(RefCell::new(Inner {
+        enabled: false,
+        paused: false,
+        epoch: now,
+        frame_start: now,
+        current: FrameCapture::new(0, Duration::ZERO),
+        stack: Vec::new(),
+        history: VecDeque::with_capacity(DEFAULT_HISTORY),
+        capacity: DEFAULT_HISTORY,
+        pending: Vec::new(),
+        selected: None,
+      })),
+    }
+  }
+
+  /// Frames kept for the timeline and exports
+  pub fn with_history(self, frames: usize) -> Self {
+    {
+      let mut inner = self.inner.borrow_mut();
+      inner.capacity = frames.max(1);
+      let excess = inner.history.len().saturating_sub(inner.capacity);
+      inner.history.drain(..excess);
+    }
+
+    self
+  }
+
+  #[inline]
+  pub fn is_enabled(&self) -> bool {
+    self.inner.borrow().enabled
+  }
+
+  /// Disabled profilers record nothing
+  pub fn set_enabled(&self, enabled: bool) {
+    let mut inner = self.inner.borrow_mut();
+    inner.enabled = enabled;
+
+    if !enabled {
+      inner.current.cpu.clear();
+      inner.stack.clear();
+      inner.pending.clear();
+    }
+  }
+
+  #[inline]
+  pub fn is_paused(&self) -> bool {
+    self.inner.borrow().paused
+  }
+
+  /// Keep recording but stop adding frames to the history so it can be
+  /// inspected
+  pub fn set_paused(&self, paused: bool) {
+    self.inner.borrow_mut().paused = paused;
+  }
+
+  /// Time everything until the returned guard is dropped
+  pub fn scope(&self, name: impl Into<Cow<'static, str>>) -> Scope {
+    self.begin_scope(name);
+
+    Scope { profiler: self.clone() }
+  }
+
+  /// Has to be matched by [`Profiler::end_scope`], prefer [`Profiler::scope`]
+  pub fn begin_scope(&self, name: impl Into<Cow<'static, str>>) {
+    let mut inner = self.inner.borrow_mut();
+
+    if !inner.enabled {
+      return;
+    }
+
+    let scope = CpuScope {
+      name: name.into(),
+      depth: inner.stack.len() as u32,
+      start: inner.frame_start.elapsed(),
+      duration: Duration::ZERO,
+    };
+
+    let index = inner.current.cpu.len();
+    inner.current.cpu.push(scope);
+    inner.stack.push(index);
+  }
+
+  pub fn end_scope(&self) {
+    let mut inner = self.inner.borrow_mut();
+
+    if !inner.enabled {
+      return;
+    }
+
+    let Some(index) = inner.stack.pop() else {
+      warn!("profiler scope ended without being started");
+      return;
+    };
+
+    let end = inner.frame_start.elapsed();
+    let scope = &mut inner.current.cpu[index];
+    scope.duration = end.saturating_sub(scope.start);
+  }
+
+  /// Query for [`DrawParameters::time_elapsed_query`] that is read back into
+  /// the current frame under `name`, use a new one for every draw call and
+  /// drop it once the draw is submitted
+  ///
+  /// `None` while disabled or when the driver has no timer queries
+  ///
+  /// [`DrawParameters::time_elapsed_query`]: glium::DrawParameters::time_elapsed_query
+  pub fn gpu_query<F: Facade + ?Sized>(
+    &self,
+    facade: &F,
+    name: impl Into<Cow<'static, str>>,
+  ) -> Option<Rc<TimeElapsedQuery>> {
+    let mut inner = self.inner.borrow_mut();
+
+    if !inner.enabled {
+      return None;
+    }
+
+    let query = Rc::new(TimeElapsedQuery::new(facade).ok()?);
+    let frame = inner.current.index;
+
+    inner.pending.push(PendingQuery {
+      frame,
+      name: name.into(),
+      query: query.clone(),
+    });
+
+    Some(query)
+  }
+
+  pub(crate) fn begin_frame(&self) {
+    let mut inner = self.inner.borrow_mut();
+
+    inner.frame_start = Instant::now();
+    let start = inner.frame_start.duration_since(inner.epoch);
+    let index = inner.current.index;
+
+    inner.current = FrameCapture::new(index, start);
+    inner.stack.clear();
+  }
+
+  pub(crate) fn end_frame(&self) {
+    let mut inner = self.inner.borrow_mut();
+
+    if !inner.stack.is_empty() {
+      warn!(
+        "{} profiler scopes still open at the end of the frame",
+        inner.stack.len()
+      );
+      inner.stack.clear();
+    }
+
+    let next = FrameCapture::new(inner.current.index + 1, Duration::ZERO);
+    let mut capture = mem::replace(&mut inner.current, next);
+
+    if !inner.enabled {
+      return;
+    }
+
+    capture.duration = inner.frame_start.elapsed();
+
+    if !inner.paused {
+      if inner.history.len() == inner.capacity {
+        inner.history.pop_front();
+      }
+      inner.history.push_back(capture);
+    }
+
+    inner.poll_queries();
+  }
+
+  /// Drop queries of a context that is going away
+  pub(crate) fn release_gpu(&self) {
+    self.inner.borrow_mut().pending.clear();
+  }
+
+  /// Recorded frames, oldest first
+  pub fn frames(&self) -> Ref<'_, VecDeque<FrameCapture>> {
+    Ref::map(self.inner.borrow(), |inner| &inner.history)
+  }
+
+  pub fn clear(&self) {
+    let mut inner = self.inner.borrow_mut();
+    inner.history.clear();
+    inner.selected = None;
+  }
+
+  /// Frame shown by the timeline widget, the latest when `None`
+  #[inline]
+  pub fn selected_frame(&self) -> Option<u64> {
+    self.inner.borrow().selected
+  }
+
+  pub fn select_frame(&self, index: Option<u64>) {
+    self.inner.borrow_mut().selected = index;
+  }
+
+  /// Write the recorded frames in the Chrome trace event format, open it in
+  /// `chrome://tracing` or Perfetto
+  pub fn export_chrome_trace(&self, writer: impl Write) -> io::Result<()> {
+    let inner = self.inner.borrow();
+
+    write_chrome_trace(writer, inner.history.iter())
+  }
+
+  pub fn save_chrome_trace(&self, path: impl AsRef<Path>) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path.as_ref())?);
+
+    self.export_chrome_trace(file)?;
+    info!("wrote chrome trace {}", path.as_ref().display());
+
+    Ok(())
+  }
+}
+
+impl Default for Profiler {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Inner {
+  /// Move finished queries into their frames
+  fn poll_queries(&mut self) {
+    let mut i = 0;
+
+    while i < self.pending.len() {
+      let pending = &self.pending[i];
+
+      // still in use by a backend or not finished on the gpu yet
+      if Rc::strong_count(&pending.query) > 1 || !pending.query.is_ready() {
+        i += 1;
+        continue;
+      }
+
+      let PendingQuery { frame, name, query } = self.pending.swap_remove(i);
+
+      let Ok(query) = Rc::try_unwrap(query) else {
+        continue;
+      };
+
+      let duration = Duration::from_nanos(query.get());
+
+      let Some(capture) = self.history.iter_mut().rev().find(|capture| capture.index == frame) else {
+        continue;
+      };
+
+      match capture.gpu.iter_mut().find(|scope| scope.name == name) {
+        Some(scope) => scope.duration += duration,
+        None => capture.gpu.push(GpuScope { name, duration }),
+      }
+    }
+  }
+}
+
+/// Ends its scope when dropped
+#[must_use = "the scope ends when the guard is dropped"]
+pub struct Scope {
+  profiler: Profiler,
+}
+
+impl Drop for Scope {
+  fn drop(&mut self) {
+    self.profiler.end_scope();
+  }
+}
+
+fn write_chrome_trace<'a>(writer: impl Write, frames: impl Iterator<Item = &'a FrameCapture>) -> io::Result<()> {
+  const CPU_THREAD: u32 = 1;
+  const GPU_THREAD: u32 = 2;
+
+  let mut writer = writer;
+
+  let event = |writer: &mut dyn Write, name: &str, category: &str, tid: u32, start: Duration, duration: Duration| {
+    writeln!(writer, ",")?;
+    write!(
+      writer,
+      r#"{{"name":"{}","cat":"{category}","ph":"X","pid":1,"tid":{tid},"ts":{:.3},"dur":{:.3}}}"#,
+      escape_json(name),
+      start.as_secs_f64() * 1e6,
+      duration.as_secs_f64() * 1e6,
+    )
+  };
+
+  writeln!(writer, r#"{{"traceEvents":["#)?;
+
+  // metadata events name the threads in the viewer, they go first so every
+  // other event can be written with a leading separator
+  writeln!(
+    writer,
+    r#"{{"name":"thread_name","ph":"M","pid":1,"tid":{CPU_THREAD},"args":{{"name":"cpu"}}}},"#
+  )?;
+  write!(
+    writer,
+    r#"{{"name":"thread_name","ph":"M","pid":1,"tid":{GPU_THREAD},"args":{{"name":"gpu"}}}}"#
+  )?;
+
+  for frame in frames {
+    event(
+      &mut writer,
+      &format!("frame {}", frame.index),
+      "frame",
+      CPU_THREAD,
+      frame.start,
+      frame.duration,
+    )?;
+
+    for scope in &frame.cpu {
+      event(
+        &mut writer,
+        &scope.name,
+        "cpu",
+        CPU_THREAD,
+        frame.start + scope.start,
+        scope.duration,
+      )?;
+    }
+
+    // gpu queries carry no timestamps, lay them out back to back from the
+    // start of the frame
+    let mut start = frame.start;
+    for scope in &frame.gpu {
+      event(&mut writer, &scope.name, "gpu", GPU_THREAD, start, scope.duration)?;
+      start += scope.duration;
+    }
+  }
+
+  writeln!(writer)?;
+  write!(writer, r#"],"displayTimeUnit":"ms","otherData":{{}}}}"#)?;
+  writeln!(writer)?;
+
+  writer.flush()
+}
+
+fn escape_json(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+
+  for c in text.chars() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      '\n' => escaped.push_str("\\n"),
+      '\r' => escaped.push_str("\\r"),
+      '\t' => escaped.push_str("\\t"),
+      c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+      c => escaped.push(c),
+    }
+  }
+
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::Deserialize;
+
+  use super::*;
+
+  #[derive(Deserialize)]
+  struct Trace {
+    #[serde(rename = "traceEvents")]
+    trace_events: Vec<Event>,
+    #[serde(rename = "displayTimeUnit")]
+    display_time_unit: String,
+  }
+
+  #[derive(Debug, Default, PartialEq, Deserialize)]
+  #[serde(default)]
+  struct Event {
+    name: String,
+    cat: String,
+    ph: String,
+    pid: u32,
+    tid: u32,
+    ts: f64,
+    dur: f64,
+    args: Args,
+  }
+
+  #[derive(Debug, Default, PartialEq, Deserialize)]
+  struct Args {
+    name: String,
+  }
+
+  fn complete(name: &str, cat: &str, tid: u32, ts: f64, dur: f64) -> Event {
+    Event {
+      name: name.to_owned(),
+      cat: cat.to_owned(),
+      ph: "X".to_owned(),
+      pid: 1,
+      tid,
+      ts,
+      dur,
+      ..Default::default()
+    }
+  }
+
+  fn thread_name(tid: u32, name: &str) -> Event {
+    Event {
+      name: "thread_name".to_owned(),
+      ph: "M".to_owned(),
+      pid: 1,
+      tid,
+      args: Args { name: name.to_owned() },
+      ..Default::default()
+    }
+  }
+
+  // json is close enough to ron that ron can read the trace back
+  fn parse(frames: &[FrameCapture]) -> Trace {
+    let mut out = Vec::new();
+    write_chrome_trace(&mut out, frames.iter()).unwrap();
+
+    let text = String::from_utf8(out).unwrap();
+    ron::from_str::<ron::Value>(&text).unwrap().into_rust().unwrap()
+  }
+
+  #[test]
+  fn chrome_trace_events() {
+    let name = "say \"hi\" \\ to\n\tthe trace";
+
+    let frame = FrameCapture {
+      index: 3,
+      start: Duration::from_micros(1000),
+      duration: Duration::from_micros(2000),
+      cpu: vec![CpuScope {
+        name: Cow::Owned(name.to_owned()),
+        depth: 0,
+        start: Duration::from_micros(500),
+        duration: Duration::from_micros(1000),
+      }],
+      gpu: vec![
+        GpuScope {
+          name: Cow::Borrowed("shadows"),
+          duration: Duration::from_micros(250),
+        },
+        GpuScope {
+          name: Cow::Borrowed("lighting"),
+          duration: Duration::from_micros(500),
+        },
+      ],
+    };
+
+    let trace = parse(&[frame]);
+
+    assert_eq!(trace.display_time_unit, "ms");
+    assert_eq!(
+      trace.trace_events,
+      [
+        thread_name(1, "cpu"),
+        thread_name(2, "gpu"),
+        complete("frame 3", "frame", 1, 1000.0, 2000.0),
+        complete(name, "cpu", 1, 1500.0, 1000.0),
+        complete("shadows", "gpu", 2, 1000.0, 250.0),
+        complete("lighting", "gpu", 2, 1250.0, 500.0),
+      ]
+    );
+  }
+
+  #[test]
+  fn empty_chrome_trace() {
+    let trace = parse(&[]);
+
+    assert_eq!(trace.trace_events, [thread_name(1, "cpu"), thread_name(2, "gpu")]);
+  }
+
+  #[test]
+  fn escape_json_control_characters() {
+    assert_eq!(escape_json("plain"), "plain");
+    assert_eq!(escape_json(r#"a "b" \c"#), r#"a \"b\" \\c"#);
+    assert_eq!(escape_json("\n\r\t"), r"\n\r\t");
+    assert_eq!(escape_json("\u{1}\u{1f}"), r"\u0001\u001f");
+    assert_eq!(escape_json("ünïcode"), "ünïcode");
+  }
+}
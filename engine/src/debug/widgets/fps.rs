@@ -0,0 +1,183 @@
+use egui::{
+  pos2, vec2, Align2, Color32, FontId, Pos2, Rect, Response, RichText, Rounding, Sense, Shape, Stroke, Widget,
+};
+
+use crate::engine::fps::FpsStats;
+
+const PLOT_HEIGHT: f32 = 64.0;
+const HISTOGRAM_HEIGHT: f32 = 40.0;
+const HISTOGRAM_BUCKETS: usize = 40;
+/// Milliseconds per histogram bucket
+const BUCKET_WIDTH: f32 = 1.0;
+
+/// 60 and 30 fps
+const TARGETS: [f32; 2] = [1000.0 / 60.0, 1000.0 / 30.0];
+
+/// Frame time graph, histogram, percentiles and the session summary with a
+/// button to reset it
+pub struct FrameTimeWidget<'a> {
+  stats: &'a mut FpsStats,
+}
+
+impl<'a> FrameTimeWidget<'a> {
+  pub fn new(stats: &'a mut FpsStats) -> Self {
+    Self { stats }
+  }
+}
+
+impl<'a> Widget for FrameTimeWidget<'a> {
+  fn ui(self, ui: &mut egui::Ui) -> Response {
+    let stats = self.stats;
+    let recent = stats.stats();
+
+    ui.vertical(|ui| {
+      frame_time_plot(ui, stats);
+      histogram(ui, stats);
+
+      egui::Grid::new("frame_time_stats").num_columns(4).show(ui, |ui| {
+        stat(ui, "avg", recent.average);
+        stat(ui, "p50", recent.p50);
+        ui.end_row();
+
+        stat(ui, "p95", recent.p95);
+        stat(ui, "p99", recent.p99);
+        ui.end_row();
+
+        stat(ui, "min", recent.min);
+        stat(ui, "max", recent.max);
+        ui.end_row();
+
+        ui.label("1% low");
+        ui.label(RichText::new(format!("{:.0} fps", recent.low_1)).monospace());
+        ui.label("0.1% low");
+        ui.label(RichText::new(format!("{:.0} fps", recent.low_0_1)).monospace());
+        ui.end_row();
+      });
+
+      ui.separator();
+
+      let session = *stats.session();
+
+      ui.horizontal(|ui| {
+        ui.label(
+          RichText::new(format!(
+            "session {:.0}s {} frames {:.0} fps",
+            session.started.elapsed().as_secs_f32(),
+            session.frames,
+            session.fps(),
+          ))
+          .monospace(),
+        );
+
+        if ui.button("reset").clicked() {
+          stats.reset_session();
+        }
+      });
+
+      if session.frames > 0 {
+        ui.label(
+          RichText::new(format!(
+            "min {:.2}ms max {:.2}ms avg {:.2}ms",
+            session.min,
+            session.max,
+            session.average()
+          ))
+          .monospace(),
+        );
+      }
+    })
+    .response
+  }
+}
+
+fn stat(ui: &mut egui::Ui, name: &str, frame_time: f32) {
+  ui.label(name);
+  ui.label(RichText::new(format!("{frame_time:.2}ms")).monospace());
+}
+
+// line through the recent frame times, scaled so 30 fps always fits
+fn frame_time_plot(ui: &mut egui::Ui, stats: &FpsStats) {
+  let width = ui.available_width();
+  let (response, painter) = ui.allocate_painter(vec2(width, PLOT_HEIGHT), Sense::hover());
+  let rect = response.rect;
+
+  painter.rect_filled(rect, Rounding::ZERO, Color32::from_gray(24));
+
+  let frame_times = stats.frame_times();
+  let max = frame_times.iter().copied().fold(TARGETS[1] * 1.25, f32::max);
+  let to_y = |frame_time: f32| rect.bottom() - frame_time / max * rect.height();
+
+  for target in TARGETS {
+    let y = to_y(target);
+
+    painter.hline(rect.x_range(), y, Stroke::new(1.0, Color32::from_gray(64)));
+    painter.text(
+      pos2(rect.left() + 2.0, y),
+      Align2::LEFT_BOTTOM,
+      format!("{target:.1}ms"),
+      FontId::monospace(10.0),
+      Color32::from_gray(128),
+    );
+  }
+
+  // x spans the whole history so the graph scrolls instead of stretching
+  let step = rect.width() / stats.capacity().saturating_sub(1).max(1) as f32;
+  let offset = stats.capacity() - frame_times.len();
+
+  let points: Vec<Pos2> = frame_times
+    .iter()
+    .enumerate()
+    .map(|(i, &frame_time)| pos2(rect.left() + (offset + i) as f32 * step, to_y(frame_time)))
+    .collect();
+
+  painter.add(Shape::line(points, Stroke::new(1.0, Color32::from_rgb(96, 160, 96))));
+
+  if let Some(pos) = response.hover_pos() {
+    let i = ((pos.x - rect.left()) / step).round() as usize;
+
+    if let Some(frame_time) = i.checked_sub(offset).and_then(|i| frame_times.get(i)) {
+      response.on_hover_text_at_pointer(format!("{frame_time:.2}ms"));
+    }
+  }
+}
+
+fn histogram(ui: &mut egui::Ui, stats: &FpsStats) {
+  let width = ui.available_width();
+  let (response, painter) = ui.allocate_painter(vec2(width, HISTOGRAM_HEIGHT), Sense::hover());
+  let rect = response.rect;
+
+  painter.rect_filled(rect, Rounding::ZERO, Color32::from_gray(24));
+
+  let counts = stats.histogram(BUCKET_WIDTH, HISTOGRAM_BUCKETS);
+  let max = counts.iter().copied().max().unwrap_or_default().max(1);
+  let bar_width = rect.width() / HISTOGRAM_BUCKETS as f32;
+
+  for (i, &count) in counts.iter().enumerate() {
+    if count == 0 {
+      continue;
+    }
+
+    let height = count as f32 / max as f32 * rect.height();
+    let x = rect.left() + i as f32 * bar_width;
+
+    let bar = Rect::from_min_max(
+      pos2(x, rect.bottom() - height),
+      pos2(x + bar_width - 1.0, rect.bottom()),
+    );
+
+    painter.rect_filled(bar, Rounding::ZERO, Color32::from_rgb(96, 128, 192));
+  }
+
+  if let Some(pos) = response.hover_pos() {
+    let i = (((pos.x - rect.left()) / bar_width) as usize).min(HISTOGRAM_BUCKETS - 1);
+    let from = i as f32 * BUCKET_WIDTH;
+
+    let range = if i == HISTOGRAM_BUCKETS - 1 {
+      format!("{from:.0}ms+")
+    } else {
+      format!("{from:.0}-{:.0}ms", from + BUCKET_WIDTH)
+    };
+
+    response.on_hover_text_at_pointer(format!("{range}: {} frames", counts[i]));
+  }
+}
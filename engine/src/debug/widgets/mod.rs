@@ -1,5 +1,10 @@
+mod fps;
+mod profiler;
+
 use egui::{Margin, Response, RichText, Rounding, Widget};
+pub use fps::FrameTimeWidget;
 use nalgebra::{ComplexField, Matrix4, Vector3};
+pub use profiler::ProfilerWidget;
 
 pub struct MatrixWidget<'a> {
   matrix: &'a Matrix4<f32>,
@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use egui::ecolor::Hsva;
+use egui::{pos2, vec2, Align2, Color32, FontId, Rect, Response, RichText, Rounding, Sense, Widget};
+
+use crate::debug::profiler::{FrameCapture, Profiler};
+
+const FRAME_STRIP_HEIGHT: f32 = 48.0;
+const ROW_HEIGHT: f32 = 18.0;
+const EXPORT_PATH: &str = "profile.json";
+
+/// Recording controls, a strip of recent frame times to pick a frame from and
+/// a flame graph of the picked frame's cpu scopes followed by its gpu timings
+pub struct ProfilerWidget<'a> {
+  profiler: &'a Profiler,
+}
+
+impl<'a> ProfilerWidget<'a> {
+  pub fn new(profiler: &'a Profiler) -> Self {
+    Self { profiler }
+  }
+}
+
+impl<'a> Widget for ProfilerWidget<'a> {
+  fn ui(self, ui: &mut egui::Ui) -> Response {
+    let profiler = self.profiler;
+
+    ui.vertical(|ui| {
+      ui.horizontal(|ui| {
+        let mut enabled = profiler.is_enabled();
+        if ui.checkbox(&mut enabled, "record").changed() {
+          profiler.set_enabled(enabled);
+        }
+
+        let mut paused = profiler.is_paused();
+        if ui.checkbox(&mut paused, "pause").changed() {
+          profiler.set_paused(paused);
+        }
+
+        if ui.button("clear").clicked() {
+          profiler.clear();
+        }
+
+        if ui.button("export").on_hover_text(EXPORT_PATH).clicked() {
+          if let Err(e) = profiler.save_chrome_trace(EXPORT_PATH) {
+            error!("failed to write chrome trace: {e}");
+          }
+        }
+      });
+
+      // copy what is drawn so the profiler isn't borrowed while selecting
+      let (durations, selected) = {
+        let frames = profiler.frames();
+        let durations: Vec<(u64, Duration)> = frames.iter().map(|frame| (frame.index, frame.duration)).collect();
+
+        let selected = profiler
+          .selected_frame()
+          .and_then(|index| frames.iter().find(|frame| frame.index == index))
+          .or(frames.back())
+          .cloned();
+
+        (durations, selected)
+      };
+
+      if let Some(index) = frame_strip(ui, &durations, selected.as_ref().map(|frame| frame.index)) {
+        profiler.select_frame(Some(index));
+      }
+
+      match selected {
+        Some(frame) => {
+          ui.label(RichText::new(format!("frame {} {:.3} ms", frame.index, millis(frame.duration))).monospace());
+          flame_graph(ui, &frame);
+
+          for scope in &frame.gpu {
+            ui.label(RichText::new(format!("gpu {}: {:.3} ms", scope.name, millis(scope.duration))).monospace());
+          }
+        }
+        None => {
+          ui.label(RichText::new("no frames recorded").color(Color32::from_gray(128)));
+        }
+      }
+    })
+    .response
+  }
+}
+
+// one bar per frame scaled to the slowest one, returns the clicked frame
+fn frame_strip(ui: &mut egui::Ui, durations: &[(u64, Duration)], selected: Option<u64>) -> Option<u64> {
+  let width = ui.available_width();
+  let (response, painter) = ui.allocate_painter(vec2(width, FRAME_STRIP_HEIGHT), Sense::click());
+  let rect = response.rect;
+
+  painter.rect_filled(rect, Rounding::ZERO, Color32::from_gray(24));
+
+  if durations.is_empty() {
+    return None;
+  }
+
+  let max = durations
+    .iter()
+    .map(|(_, duration)| *duration)
+    .max()
+    .unwrap_or_default();
+  let max = millis(max).max(1.0 / 60.0 * 1000.0);
+  let bar_width = rect.width() / durations.len() as f32;
+
+  for (i, (index, duration)) in durations.iter().enumerate() {
+    let height = (millis(*duration) / max) as f32 * rect.height();
+    let x = rect.left() + i as f32 * bar_width;
+
+    let bar = Rect::from_min_max(
+      pos2(x, rect.bottom() - height),
+      pos2(x + bar_width.max(1.0), rect.bottom()),
+    );
+
+    let color = if Some(*index) == selected {
+      Color32::WHITE
+    } else {
+      Color32::from_rgb(96, 160, 96)
+    };
+
+    painter.rect_filled(bar, Rounding::ZERO, color);
+  }
+
+  let hovered = response
+    .hover_pos()
+    .map(|pos| (((pos.x - rect.left()) / bar_width) as usize).min(durations.len() - 1));
+
+  let clicked = response.clicked();
+
+  if let Some(i) = hovered {
+    let (index, duration) = durations[i];
+    response.on_hover_text_at_pointer(format!("frame {index}: {:.3} ms", millis(duration)));
+
+    if clicked {
+      return Some(index);
+    }
+  }
+
+  None
+}
+
+// cpu scopes of one frame, one row per depth
+fn flame_graph(ui: &mut egui::Ui, frame: &FrameCapture) {
+  let depth = frame.cpu.iter().map(|scope| scope.depth + 1).max().unwrap_or(1);
+
+  let width = ui.available_width();
+  let (response, painter) = ui.allocate_painter(vec2(width, depth as f32 * ROW_HEIGHT), Sense::hover());
+  let rect = response.rect;
+
+  let total = frame.duration.as_secs_f32().max(f32::EPSILON);
+  let font = FontId::monospace(ROW_HEIGHT * 0.7);
+  let hover_pos = response.hover_pos();
+
+  let mut hovered = None;
+
+  for scope in &frame.cpu {
+    let left = rect.left() + scope.start.as_secs_f32() / total * rect.width();
+    let right = left + (scope.duration.as_secs_f32() / total * rect.width()).max(1.0);
+    let top = rect.top() + scope.depth as f32 * ROW_HEIGHT;
+
+    let bar = Rect::from_min_max(pos2(left, top), pos2(right, top + ROW_HEIGHT - 1.0));
+
+    painter.rect_filled(bar, Rounding::same(2.0), scope_color(&scope.name));
+
+    // only label bars wide enough to fit a few characters
+    if bar.width() > ROW_HEIGHT * 3.0 {
+      painter.with_clip_rect(bar).text(
+        pos2(bar.left() + 2.0, bar.center().y),
+        Align2::LEFT_CENTER,
+        &scope.name,
+        font.clone(),
+        Color32::BLACK,
+      );
+    }
+
+    if hover_pos.is_some_and(|pos| bar.contains(pos)) {
+      hovered = Some(scope);
+    }
+  }
+
+  if let Some(scope) = hovered {
+    response.on_hover_text_at_pointer(format!("{}: {:.3} ms", scope.name, millis(scope.duration)));
+  }
+}
+
+// stable color per scope name
+fn scope_color(name: &str) -> Color32 {
+  // fnv-1a
+  let hash = name.bytes().fold(0x811C9DC5u32, |hash, byte| {
+    (hash ^ byte as u32).wrapping_mul(0x01000193)
+  });
+
+  let hue = (hash % 360) as f32 / 360.0;
+
+  Hsva::new(hue, 0.45, 0.85, 1.0).into()
+}
+
+fn millis(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1000.0
+}
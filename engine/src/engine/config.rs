@@ -0,0 +1,196 @@
+use std::num::NonZeroU32;
+
+use glutin::context::GlProfile;
+use glutin::surface::SwapInterval;
+use winit::window::CursorIcon;
+
+use crate::math::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VsyncMode {
+  Off,
+  On,
+  /// Wait for this many vblanks between frames
+  Interval(NonZeroU32),
+}
+
+impl From<VsyncMode> for SwapInterval {
+  fn from(mode: VsyncMode) -> Self {
+    match mode {
+      VsyncMode::Off => SwapInterval::DontWait,
+      VsyncMode::On => SwapInterval::Wait(NonZeroU32::MIN),
+      VsyncMode::Interval(interval) => SwapInterval::Wait(interval),
+    }
+  }
+}
+
+/// How the window holds on to the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CursorMode {
+  #[default]
+  Free,
+  /// Kept inside the window, falls back to [`CursorMode::Locked`] where
+  /// confining isn't supported
+  Confined,
+  /// Kept in place, read movement through
+  /// [`Input::raw_mouse_delta`](crate::engine::input::Input::raw_mouse_delta).
+  /// Falls back to [`CursorMode::Confined`] where locking isn't supported
+  Locked,
+}
+
+/// Window, gl context and presentation settings passed to
+/// [`Engine::new`](crate::Engine::new)
+///
+/// Msaa, gl version, gl profile and srgb are fixed once the context is
+/// created, everything else can be changed through
+/// [`EngineContext`](crate::EngineContext)
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+  pub title: String,
+  /// Logical inner size, platform default when `None`
+  pub size: Option<(u32, u32)>,
+  /// Logical outer position, platform default when `None`
+  pub position: Option<(i32, i32)>,
+  pub resizable: bool,
+  pub decorations: bool,
+  /// Borderless fullscreen on the current monitor
+  pub fullscreen: bool,
+  pub cursor_mode: CursorMode,
+  pub cursor_visible: bool,
+  pub cursor_icon: CursorIcon,
+  pub vsync: VsyncMode,
+  /// Samples per pixel, 0 to disable
+  pub msaa: u8,
+  pub gl_version: (u8, u8),
+  /// Driver default when `None`
+  pub gl_profile: Option<GlProfile>,
+  /// Whether the surface is srgb capable, driver default when `None`
+  pub srgb: Option<bool>,
+  pub clear_color: Color,
+}
+
+impl EngineConfig {
+  pub fn new(title: impl Into<String>) -> Self {
+    Self {
+      title: title.into(),
+      size: None,
+      position: None,
+      resizable: true,
+      decorations: true,
+      fullscreen: false,
+      cursor_mode: CursorMode::Free,
+      cursor_visible: true,
+      cursor_icon: CursorIcon::Default,
+      vsync: VsyncMode::On,
+      msaa: 4,
+      gl_version: (4, 0),
+      gl_profile: None,
+      srgb: None,
+      clear_color: Color(0.0, 0.0, 0.0, 0.0),
+    }
+  }
+
+  pub fn title(mut self, title: impl Into<String>) -> Self {
+    self.title = title.into();
+    self
+  }
+
+  pub fn size(mut self, width: u32, height: u32) -> Self {
+    self.size = Some((width, height));
+    self
+  }
+
+  pub fn position(mut self, x: i32, y: i32) -> Self {
+    self.position = Some((x, y));
+    self
+  }
+
+  pub fn resizable(mut self, resizable: bool) -> Self {
+    self.resizable = resizable;
+    self
+  }
+
+  pub fn decorations(mut self, decorations: bool) -> Self {
+    self.decorations = decorations;
+    self
+  }
+
+  pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+    self.fullscreen = fullscreen;
+    self
+  }
+
+  pub fn cursor_mode(mut self, mode: CursorMode) -> Self {
+    self.cursor_mode = mode;
+    self
+  }
+
+  pub fn cursor_visible(mut self, visible: bool) -> Self {
+    self.cursor_visible = visible;
+    self
+  }
+
+  pub fn cursor_icon(mut self, icon: CursorIcon) -> Self {
+    self.cursor_icon = icon;
+    self
+  }
+
+  pub fn vsync(mut self, vsync: VsyncMode) -> Self {
+    self.vsync = vsync;
+    self
+  }
+
+  pub fn msaa(mut self, samples: u8) -> Self {
+    self.msaa = samples;
+    self
+  }
+
+  pub fn gl_version(mut self, major: u8, minor: u8) -> Self {
+    self.gl_version = (major, minor);
+    self
+  }
+
+  pub fn gl_profile(mut self, profile: GlProfile) -> Self {
+    self.gl_profile = Some(profile);
+    self
+  }
+
+  pub fn srgb(mut self, srgb: bool) -> Self {
+    self.srgb = Some(srgb);
+    self
+  }
+
+  pub fn clear_color(mut self, color: Color) -> Self {
+    self.clear_color = color;
+    self
+  }
+}
+
+impl Default for EngineConfig {
+  fn default() -> Self {
+    Self::new("algurulgar")
+  }
+}
+
+/// Queued through [`EngineContext`](crate::EngineContext) and applied to the
+/// window after the current frame's update or event
+#[derive(Debug, Clone)]
+pub enum WindowCommand {
+  Title(String),
+  Size((u32, u32)),
+  Position((i32, i32)),
+  Resizable(bool),
+  Decorations(bool),
+  Fullscreen(bool),
+  CursorMode(CursorMode),
+  CursorVisible(bool),
+  CursorIcon(CursorIcon),
+  /// Creates the gl context again, see
+  /// [`EngineContext::set_vsync`](crate::EngineContext::set_vsync)
+  Vsync(VsyncMode),
+  /// Physical pixels from the top left of the window
+  ImeCursorArea {
+    position: (i32, i32),
+    size: (u32, u32),
+  },
+}
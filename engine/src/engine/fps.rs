@@ -1,68 +1,211 @@
+use std::collections::VecDeque;
 use std::time::Instant;
 
-use crate::window::viewport;
+/// Frames kept for [`FpsStats::stats`] and the plots
+pub const DEFAULT_HISTORY: usize = 600;
 
+/// Seconds between updates of [`FpsStats::text`]
+const TEXT_INTERVAL: f32 = 0.25;
+
+/// Frame time statistics over the recent history, times in milliseconds
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+  pub frames: usize,
+  pub average: f32,
+  pub min: f32,
+  pub max: f32,
+  pub p50: f32,
+  pub p95: f32,
+  pub p99: f32,
+  /// Average fps of the slowest 1% of frames
+  pub low_1: f32,
+  /// Average fps of the slowest 0.1% of frames
+  pub low_0_1: f32,
+}
+
+impl FrameStats {
+  /// Average fps
+  #[inline]
+  pub fn fps(&self) -> f32 {
+    fps(self.average)
+  }
+}
+
+/// Everything since the last [`FpsStats::reset_session`], times in
+/// milliseconds
+#[derive(Debug, Clone, Copy)]
+pub struct SessionStats {
+  pub started: Instant,
+  pub frames: u64,
+  /// Sum of every frame time
+  pub total: f64,
+  pub min: f32,
+  pub max: f32,
+}
+
+impl SessionStats {
+  fn new() -> Self {
+    Self {
+      started: Instant::now(),
+      frames: 0,
+      total: 0.0,
+      min: f32::INFINITY,
+      max: 0.0,
+    }
+  }
+
+  #[inline]
+  pub fn average(&self) -> f32 {
+    if self.frames == 0 {
+      0.0
+    } else {
+      (self.total / self.frames as f64) as f32
+    }
+  }
+
+  /// Average fps
+  #[inline]
+  pub fn fps(&self) -> f32 {
+    fps(self.average())
+  }
+}
+
+/// Frame times of the last few hundred frames plus a session summary
 pub struct FpsStats {
-  last_update: Instant,
-  current: u32,
-  average: u32,
-  max: u32,
-  min: u32,
-  avg_window: [u32; 10],
-  avg_cursor: usize,
+  frame_times: VecDeque<f32>,
+  capacity: usize,
+  session: SessionStats,
+  last_text_update: Instant,
+  /// Current, average and 1% low fps, refreshed a few times per second
   pub text: String,
 }
 
 impl FpsStats {
   pub fn new() -> Self {
+    Self::with_history(DEFAULT_HISTORY)
+  }
+
+  pub fn with_history(frames: usize) -> Self {
+    let capacity = frames.max(1);
+
     Self {
-      last_update: Instant::now(),
-      current: 0,
-      average: 0,
-      max: 0,
-      min: 0,
-      avg_window: [0; 10],
-      avg_cursor: 0,
+      frame_times: VecDeque::with_capacity(capacity),
+      capacity,
+      session: SessionStats::new(),
+      last_text_update: Instant::now(),
       text: String::new(),
     }
   }
 
-  pub fn update(&mut self, fps: u32) {
-    self.current = fps;
+  /// Record a frame that took `delta_time` seconds
+  pub fn update(&mut self, delta_time: f32) {
+    let frame_time = delta_time * 1000.0;
 
-    if self.last_update.elapsed().as_secs_f32() > 0.1 {
-      if self.current > self.max {
-        self.max = self.current;
-      }
+    if self.frame_times.len() == self.capacity {
+      self.frame_times.pop_front();
+    }
+    self.frame_times.push_back(frame_time);
 
-      if self.current < self.min || self.min == 0 {
-        self.min = self.current;
-      }
+    self.session.frames += 1;
+    self.session.total += frame_time as f64;
+    self.session.min = self.session.min.min(frame_time);
+    self.session.max = self.session.max.max(frame_time);
 
-      self.last_update = Instant::now();
+    if self.last_text_update.elapsed().as_secs_f32() > TEXT_INTERVAL {
+      self.last_text_update = Instant::now();
 
-      self.update_average();
+      let stats = self.stats();
 
       self.text = format!(
-        "FPS: {: >4} AVG: {: >4} MAX: {: >4} MIN: {: >4}\t{:?}",
-        self.current,
-        self.average,
-        self.max,
-        self.min,
-        viewport(),
+        "FPS: {: >4.0} AVG: {: >4.0} 1% LOW: {: >4.0} P99: {: >6.2}ms",
+        self.fps(),
+        stats.fps(),
+        stats.low_1,
+        stats.p99,
       );
     }
   }
 
-  fn update_average(&mut self) {
-    if self.avg_cursor >= self.avg_window.len() {
-      self.avg_cursor = 0;
+  /// Last frame time in milliseconds
+  #[inline]
+  pub fn frame_time(&self) -> f32 {
+    self.frame_times.back().copied().unwrap_or_default()
+  }
+
+  /// Fps of the last frame
+  #[inline]
+  pub fn fps(&self) -> f32 {
+    fps(self.frame_time())
+  }
+
+  /// Recent frame times in milliseconds, oldest first
+  #[inline]
+  pub fn frame_times(&self) -> &VecDeque<f32> {
+    &self.frame_times
+  }
+
+  #[inline]
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Percentiles and lows over the recent frame times, sorts a copy of the
+  /// history so avoid calling it more than once per frame
+  pub fn stats(&self) -> FrameStats {
+    if self.frame_times.is_empty() {
+      return FrameStats::default();
     }
 
-    self.average = self.avg_window.iter().sum::<u32>() / self.avg_window.len() as u32;
+    let mut sorted: Vec<f32> = self.frame_times.iter().copied().collect();
+    sorted.sort_unstable_by(f32::total_cmp);
+
+    let frames = sorted.len();
+    let average = sorted.iter().sum::<f32>() / frames as f32;
+
+    FrameStats {
+      frames,
+      average,
+      min: sorted[0],
+      max: sorted[frames - 1],
+      p50: percentile(&sorted, 0.50),
+      p95: percentile(&sorted, 0.95),
+      p99: percentile(&sorted, 0.99),
+      low_1: low(&sorted, 0.01),
+      low_0_1: low(&sorted, 0.001),
+    }
+  }
+
+  /// Count recent frames into `buckets` of `bucket_width` milliseconds, the
+  /// last bucket also counts everything slower
+  pub fn histogram(&self, bucket_width: f32, buckets: usize) -> Vec<u32> {
+    let mut histogram = vec![0; buckets];
+
+    if buckets == 0 || bucket_width <= 0.0 {
+      return histogram;
+    }
+
+    for &frame_time in &self.frame_times {
+      let bucket = ((frame_time / bucket_width) as usize).min(buckets - 1);
+      histogram[bucket] += 1;
+    }
+
+    histogram
+  }
+
+  #[inline]
+  pub fn session(&self) -> &SessionStats {
+    &self.session
+  }
+
+  /// Start a new session, the recent history is kept
+  pub fn reset_session(&mut self) {
+    self.session = SessionStats::new();
+  }
 
-    self.avg_window[self.avg_cursor] = self.current;
-    self.avg_cursor += 1;
+  /// Clear the history and start a new session
+  pub fn reset(&mut self) {
+    self.frame_times.clear();
+    self.reset_session();
   }
 }
 
@@ -71,3 +214,119 @@ impl Default for FpsStats {
     Self::new()
   }
 }
+
+#[inline]
+fn fps(frame_time: f32) -> f32 {
+  if frame_time > 0.0 {
+    1000.0 / frame_time
+  } else {
+    0.0
+  }
+}
+
+// nearest rank on ascending frame times
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+  let rank = (p * sorted.len() as f32).ceil() as usize;
+
+  sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// average fps of the slowest `fraction` of frames, at least one frame
+fn low(sorted: &[f32], fraction: f32) -> f32 {
+  let count = ((sorted.len() as f32 * fraction).ceil() as usize).clamp(1, sorted.len());
+  let slowest = &sorted[sorted.len() - count..];
+
+  fps(slowest.iter().sum::<f32>() / count as f32)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // powers of two seconds so the frame times in milliseconds are exact
+  const FRAME: f32 = 0.0078125;
+
+  #[test]
+  fn history_wraps_around() {
+    let mut fps_stats = FpsStats::with_history(3);
+
+    for delta_time in [0.5, 0.25, 0.125, 0.0625] {
+      fps_stats.update(delta_time);
+    }
+
+    assert_eq!(fps_stats.frame_times(), &[250.0, 125.0, 62.5]);
+    assert_eq!(fps_stats.frame_time(), 62.5);
+    assert_eq!(fps_stats.fps(), 16.0);
+
+    let stats = fps_stats.stats();
+    assert_eq!(stats.frames, 3);
+    assert_eq!((stats.min, stats.max), (62.5, 250.0));
+
+    // the session still counts the frame that fell out
+    assert_eq!(fps_stats.session().frames, 4);
+    assert_eq!(fps_stats.session().max, 500.0);
+    assert_eq!(fps_stats.session().average(), 234.375);
+  }
+
+  #[test]
+  fn percentiles_use_the_nearest_rank() {
+    let mut fps_stats = FpsStats::new();
+
+    // 1 to 100 frames long, newest first so the history has to be sorted
+    for frames in (1..=100).rev() {
+      fps_stats.update(frames as f32 * FRAME);
+    }
+
+    let ms = |frames: f32| frames * FRAME * 1000.0;
+    let stats = fps_stats.stats();
+
+    assert_eq!(stats.frames, 100);
+    assert_eq!(stats.min, ms(1.0));
+    assert_eq!(stats.max, ms(100.0));
+    assert_eq!(stats.average, ms(50.5));
+    assert_eq!(stats.p50, ms(50.0));
+    assert_eq!(stats.p95, ms(95.0));
+    assert_eq!(stats.p99, ms(99.0));
+  }
+
+  #[test]
+  fn lows_average_the_slowest_frames() {
+    let mut fps_stats = FpsStats::with_history(1000);
+
+    // 990 frames at 64 fps, 9 at 32 fps and one at 16 fps
+    for i in 0..1000 {
+      let delta_time = match i {
+        500 => 8.0 * FRAME,
+        100..=108 => 4.0 * FRAME,
+        _ => 2.0 * FRAME,
+      };
+
+      fps_stats.update(delta_time);
+    }
+
+    let stats = fps_stats.stats();
+
+    assert_eq!(stats.low_0_1, 16.0);
+    assert_eq!(stats.low_1, 1000.0 / 34.375);
+  }
+
+  #[test]
+  fn lows_cover_at_least_one_frame() {
+    let mut fps_stats = FpsStats::new();
+
+    fps_stats.update(2.0 * FRAME);
+    fps_stats.update(8.0 * FRAME);
+
+    let stats = fps_stats.stats();
+
+    assert_eq!(stats.low_1, 16.0);
+    assert_eq!(stats.low_0_1, 16.0);
+    assert_eq!(stats.p50, 15.625);
+  }
+
+  #[test]
+  fn empty_history() {
+    assert_eq!(FpsStats::new().stats(), FrameStats::default());
+    assert_eq!(FpsStats::new().fps(), 0.0);
+  }
+}
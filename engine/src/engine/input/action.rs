@@ -0,0 +1,398 @@
+// named actions and axes bound to keys, mouse buttons and the scroll wheel
+// bindings are plain data so they can be saved, loaded and changed at runtime
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+use crate::engine::input::{Input, InputState};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+  #[error(transparent)]
+  Io(#[from] io::Error),
+  #[error(transparent)]
+  Ron(#[from] ron::Error),
+  #[error(transparent)]
+  RonParse(#[from] ron::error::SpannedError),
+  #[error(transparent)]
+  TomlSerialize(#[from] toml::ser::Error),
+  #[error(transparent)]
+  TomlParse(#[from] toml::de::Error),
+  #[error("{0} is neither .ron nor .toml")]
+  UnknownFormat(PathBuf),
+}
+
+/// Something that can be pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+  Key(KeyCode),
+  Mouse(MouseButton),
+  /// Pressed for a single frame whenever the wheel moves this way
+  ScrollUp,
+  ScrollDown,
+  ScrollLeft,
+  ScrollRight,
+}
+
+impl Binding {
+  pub fn state(&self, input: &Input) -> InputState {
+    let scrolled = |scrolled: bool| {
+      if scrolled {
+        InputState::PressedThisFrame
+      } else {
+        InputState::None
+      }
+    };
+
+    match *self {
+      Binding::Key(code) => input.key_state(code),
+      Binding::Mouse(button) => input.mouse_state(button),
+      Binding::ScrollUp => scrolled(input.scroll_delta().y > 0.0),
+      Binding::ScrollDown => scrolled(input.scroll_delta().y < 0.0),
+      Binding::ScrollLeft => scrolled(input.scroll_delta().x < 0.0),
+      Binding::ScrollRight => scrolled(input.scroll_delta().x > 0.0),
+    }
+  }
+
+  /// True if it went down since the previous frame, even if it is already
+  /// released again
+  pub fn was_pressed_this_frame(&self, input: &Input) -> bool {
+    match *self {
+      Binding::Key(code) => input.key_was_pressed_this_frame(code),
+      Binding::Mouse(button) => input.button_was_pressed_this_frame(button),
+      _ => self.state(input) == InputState::PressedThisFrame,
+    }
+  }
+
+  /// True if it went up since the previous frame, even if it is already
+  /// pressed again
+  pub fn was_released_this_frame(&self, input: &Input) -> bool {
+    match *self {
+      Binding::Key(code) => input.key_was_released_this_frame(code),
+      Binding::Mouse(button) => input.button_was_released_this_frame(button),
+      _ => false,
+    }
+  }
+}
+
+impl From<KeyCode> for Binding {
+  fn from(code: KeyCode) -> Self {
+    Binding::Key(code)
+  }
+}
+
+impl From<MouseButton> for Binding {
+  fn from(button: MouseButton) -> Self {
+    Binding::Mouse(button)
+  }
+}
+
+/// Source of an axis value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AxisBinding {
+  /// -1 while `negative` is pressed, 1 while `positive` is, 0 for both
+  Buttons { negative: Binding, positive: Binding },
+  /// Lines scrolled this frame, up is positive
+  ScrollY,
+  /// Lines scrolled this frame, right is positive
+  ScrollX,
+}
+
+impl AxisBinding {
+  pub fn value(&self, input: &Input) -> f32 {
+    match self {
+      AxisBinding::Buttons { negative, positive } => {
+        let pressed = |binding: &Binding| is_pressed(binding.state(input)) as i32 as f32;
+
+        pressed(positive) - pressed(negative)
+      }
+      AxisBinding::ScrollY => input.scroll_delta().y,
+      AxisBinding::ScrollX => input.scroll_delta().x,
+    }
+  }
+}
+
+/// Named actions and axes
+///
+/// Actions combine their bindings with the same states as [`InputState`],
+/// held while any binding is held. Axes add up their bindings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+  #[serde(default)]
+  actions: BTreeMap<String, Vec<Binding>>,
+  #[serde(default)]
+  axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl InputMap {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_action(mut self, action: impl Into<String>, bindings: impl IntoIterator<Item = Binding>) -> Self {
+    self.rebind(action, bindings);
+    self
+  }
+
+  pub fn with_axis(mut self, axis: impl Into<String>, bindings: impl IntoIterator<Item = AxisBinding>) -> Self {
+    self.rebind_axis(axis, bindings);
+    self
+  }
+
+  /// Add a binding to `action`, creating it if needed
+  pub fn bind(&mut self, action: impl Into<String>, binding: impl Into<Binding>) {
+    let bindings = self.actions.entry(action.into()).or_default();
+    let binding = binding.into();
+
+    if !bindings.contains(&binding) {
+      bindings.push(binding);
+    }
+  }
+
+  pub fn unbind(&mut self, action: &str, binding: impl Into<Binding>) {
+    let binding = binding.into();
+
+    if let Some(bindings) = self.actions.get_mut(action) {
+      bindings.retain(|b| *b != binding);
+    }
+  }
+
+  /// Replace every binding of `action`
+  pub fn rebind(&mut self, action: impl Into<String>, bindings: impl IntoIterator<Item = Binding>) {
+    self.actions.insert(action.into(), bindings.into_iter().collect());
+  }
+
+  pub fn bind_axis(&mut self, axis: impl Into<String>, binding: AxisBinding) {
+    let bindings = self.axes.entry(axis.into()).or_default();
+
+    if !bindings.contains(&binding) {
+      bindings.push(binding);
+    }
+  }
+
+  pub fn unbind_axis(&mut self, axis: &str, binding: AxisBinding) {
+    if let Some(bindings) = self.axes.get_mut(axis) {
+      bindings.retain(|b| *b != binding);
+    }
+  }
+
+  /// Replace every binding of `axis`
+  pub fn rebind_axis(&mut self, axis: impl Into<String>, bindings: impl IntoIterator<Item = AxisBinding>) {
+    self.axes.insert(axis.into(), bindings.into_iter().collect());
+  }
+
+  /// Empty for unknown actions
+  pub fn bindings(&self, action: &str) -> &[Binding] {
+    self.actions.get(action).map(Vec::as_slice).unwrap_or_default()
+  }
+
+  /// Empty for unknown axes
+  pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+    self.axes.get(axis).map(Vec::as_slice).unwrap_or_default()
+  }
+
+  pub fn actions(&self) -> impl Iterator<Item = (&str, &[Binding])> {
+    self
+      .actions
+      .iter()
+      .map(|(action, bindings)| (action.as_str(), bindings.as_slice()))
+  }
+
+  pub fn axes(&self) -> impl Iterator<Item = (&str, &[AxisBinding])> {
+    self
+      .axes
+      .iter()
+      .map(|(axis, bindings)| (axis.as_str(), bindings.as_slice()))
+  }
+
+  /// Combined state of every binding, [`InputState::None`] for unknown
+  /// actions
+  pub fn state(&self, input: &Input, action: &str) -> InputState {
+    let mut combined = InputState::None;
+
+    for binding in self.bindings(action) {
+      combined = match (combined, binding.state(input)) {
+        (InputState::Held, _) | (_, InputState::Held) => InputState::Held,
+        (InputState::PressedThisFrame, _) | (_, InputState::PressedThisFrame) => InputState::PressedThisFrame,
+        (InputState::ReleasedThisFrame, _) | (_, InputState::ReleasedThisFrame) => InputState::ReleasedThisFrame,
+        _ => InputState::None,
+      };
+    }
+
+    combined
+  }
+
+  pub fn pressed_this_frame(&self, input: &Input, action: &str) -> bool {
+    self.state(input, action) == InputState::PressedThisFrame
+  }
+
+  pub fn released_this_frame(&self, input: &Input, action: &str) -> bool {
+    self.state(input, action) == InputState::ReleasedThisFrame
+  }
+
+  /// Any binding went down since the previous frame, see
+  /// [`Binding::was_pressed_this_frame`]
+  pub fn was_pressed_this_frame(&self, input: &Input, action: &str) -> bool {
+    self
+      .bindings(action)
+      .iter()
+      .any(|binding| binding.was_pressed_this_frame(input))
+  }
+
+  /// Any binding went up since the previous frame
+  pub fn was_released_this_frame(&self, input: &Input, action: &str) -> bool {
+    self
+      .bindings(action)
+      .iter()
+      .any(|binding| binding.was_released_this_frame(input))
+  }
+
+  pub fn held(&self, input: &Input, action: &str) -> bool {
+    self.state(input, action) == InputState::Held
+  }
+
+  pub fn pressed(&self, input: &Input, action: &str) -> bool {
+    is_pressed(self.state(input, action))
+  }
+
+  /// Sum of every binding, 0 for unknown axes
+  pub fn axis(&self, input: &Input, axis: &str) -> f32 {
+    self
+      .axis_bindings(axis)
+      .iter()
+      .map(|binding| binding.value(input))
+      .sum()
+  }
+
+  pub fn from_ron(ron: &str) -> Result<Self, Error> {
+    Ok(ron::from_str(ron)?)
+  }
+
+  pub fn to_ron(&self) -> Result<String, Error> {
+    Ok(ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)
+  }
+
+  pub fn from_toml(toml: &str) -> Result<Self, Error> {
+    Ok(toml::from_str(toml)?)
+  }
+
+  pub fn to_toml(&self) -> Result<String, Error> {
+    Ok(toml::to_string_pretty(self)?)
+  }
+
+  /// Read a `.ron` or `.toml` file
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+
+    match Format::of(path)? {
+      Format::Ron => Self::from_ron(&text),
+      Format::Toml => Self::from_toml(&text),
+    }
+  }
+
+  /// Write a `.ron` or `.toml` file
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+
+    let text = match Format::of(path)? {
+      Format::Ron => self.to_ron()?,
+      Format::Toml => self.to_toml()?,
+    };
+
+    fs::write(path, text)?;
+
+    Ok(())
+  }
+}
+
+enum Format {
+  Ron,
+  Toml,
+}
+
+impl Format {
+  fn of(path: &Path) -> Result<Self, Error> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+      Some("ron") => Ok(Format::Ron),
+      Some("toml") => Ok(Format::Toml),
+      _ => Err(Error::UnknownFormat(path.to_path_buf())),
+    }
+  }
+}
+
+#[inline]
+fn is_pressed(state: InputState) -> bool {
+  state == InputState::PressedThisFrame || state == InputState::Held
+}
+
+#[cfg(test)]
+mod tests {
+  use std::env;
+
+  use super::*;
+
+  fn map() -> InputMap {
+    InputMap::new()
+      .with_action("jump", [Binding::Key(KeyCode::Space), Binding::ScrollUp])
+      .with_action(
+        "fire",
+        [Binding::Mouse(MouseButton::Left), Binding::Mouse(MouseButton::Other(4))],
+      )
+      .with_axis(
+        "zoom",
+        [
+          AxisBinding::Buttons {
+            negative: Binding::Key(KeyCode::Minus),
+            positive: Binding::Key(KeyCode::Equal),
+          },
+          AxisBinding::ScrollY,
+        ],
+      )
+      .with_axis("pan", [AxisBinding::ScrollX])
+  }
+
+  #[test]
+  fn ron_round_trip() {
+    let map = map();
+
+    assert_eq!(InputMap::from_ron(&map.to_ron().unwrap()).unwrap(), map);
+  }
+
+  #[test]
+  fn toml_round_trip() {
+    let map = map();
+
+    assert_eq!(InputMap::from_toml(&map.to_toml().unwrap()).unwrap(), map);
+  }
+
+  #[test]
+  fn save_and_load_by_extension() {
+    let map = map();
+    let dir = env::temp_dir().join(format!("algurulgar-action-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    for name in ["bindings.ron", "bindings.toml"] {
+      let path = dir.join(name);
+
+      map.save(&path).unwrap();
+      assert_eq!(InputMap::load(&path).unwrap(), map);
+    }
+
+    let path = dir.join("bindings.json");
+    assert!(matches!(map.save(&path), Err(Error::UnknownFormat(_))));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn missing_tables_are_empty() {
+    assert_eq!(InputMap::from_ron("()").unwrap(), InputMap::new());
+    assert_eq!(InputMap::from_toml("").unwrap(), InputMap::new());
+  }
+}
@@ -1,15 +1,18 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Debug, Display, Formatter};
+use std::mem;
 
+use serde::{Deserialize, Serialize};
 use winit::event::ElementState;
-use winit::keyboard::{KeyCode, NativeKeyCode, PhysicalKey};
+use winit::keyboard::{self, KeyCode, ModifiersState, NamedKey, NativeKeyCode, PhysicalKey};
 
-use crate::engine::input::InputState;
+use crate::engine::input::{settle, transition, Input, InputState, RawInput};
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Key {
   PhyiscalKey(PhysicalKey),
+  /// What the key means in the current layout
+  Logical(LogicalKey),
 }
 
 impl Key {
@@ -26,6 +29,13 @@ impl Key {
       _ => None,
     }
   }
+
+  pub fn logical(&self) -> Option<LogicalKey> {
+    match self {
+      Key::Logical(key) => Some(*key),
+      _ => None,
+    }
+  }
 }
 
 impl From<KeyCode> for Key {
@@ -46,11 +56,31 @@ impl From<NativeKeyCode> for Key {
   }
 }
 
+impl From<LogicalKey> for Key {
+  fn from(logical_key: LogicalKey) -> Self {
+    Key::Logical(logical_key)
+  }
+}
+
+impl From<NamedKey> for Key {
+  fn from(named_key: NamedKey) -> Self {
+    Key::Logical(LogicalKey::Named(named_key))
+  }
+}
+
+/// Lowercased, see [`LogicalKey::Character`]
+impl From<char> for Key {
+  fn from(character: char) -> Self {
+    Key::Logical(LogicalKey::character(character))
+  }
+}
+
 impl Debug for Key {
   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
     match self {
       Key::PhyiscalKey(PhysicalKey::Code(code)) => write!(f, "Key::Code({:?})", code),
       Key::PhyiscalKey(PhysicalKey::Unidentified(code)) => write!(f, "Key::Unidentified({:?})", code),
+      Key::Logical(key) => write!(f, "Key::Logical({:?})", key),
     }
   }
 }
@@ -60,80 +90,362 @@ impl Display for Key {
     match self {
       Key::PhyiscalKey(PhysicalKey::Code(code)) => write!(f, "{:?}", code),
       Key::PhyiscalKey(PhysicalKey::Unidentified(code)) => write!(f, "{:?}", code),
+      Key::Logical(key) => write!(f, "{}", key),
     }
   }
 }
 
-thread_local! {
-  static KEYBOARD_STATE: RefCell<HashMap<Key, InputState>> = RefCell::new(HashMap::new());
+/// Layout dependent key
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LogicalKey {
+  Named(NamedKey),
+  /// Always lowercase so a key released after shift still matches the press
+  Character(char),
 }
 
-// not using option becuase once a key is pressed, it will always have a value
-// just using whatever `NativeKeyCode::Unidentified` is as a default
-static mut LAST_KEY_PRESSED: Key = Key::PhyiscalKey(PhysicalKey::Unidentified(NativeKeyCode::Unidentified));
+impl LogicalKey {
+  pub fn character(character: char) -> Self {
+    LogicalKey::Character(character.to_lowercase().next().unwrap_or(character))
+  }
 
-pub fn set_key_state(key: Key, state: ElementState) {
-  KEYBOARD_STATE.with(|input_state| {
-    let mut input_state = input_state.borrow_mut();
-    let current_state = input_state.get(&key).copied().unwrap_or(InputState::None);
+  /// `None` for dead keys, unidentified keys and characters that are more
+  /// than one char
+  pub fn from_winit(key: &keyboard::Key) -> Option<Self> {
+    match key {
+      keyboard::Key::Named(named) => Some(LogicalKey::Named(*named)),
+      keyboard::Key::Character(text) => {
+        let mut chars = text.chars();
 
-    match state {
-      ElementState::Pressed if current_state == InputState::None => {
-        input_state.insert(key, InputState::PressedThisFrame);
+        match (chars.next(), chars.next()) {
+          (Some(character), None) => Some(LogicalKey::character(character)),
+          _ => None,
+        }
       }
-      ElementState::Released if current_state == InputState::Held || current_state == InputState::PressedThisFrame => {
-        input_state.insert(key, InputState::ReleasedThisFrame);
-      }
-      _ => {}
+      _ => None,
     }
-  });
-
-  unsafe {
-    LAST_KEY_PRESSED = key;
   }
 }
 
-pub fn key_state<K: Into<Key>>(key: K) -> InputState {
-  KEYBOARD_STATE.with(|input_state| {
-    input_state
-      .borrow()
-      .get(&key.into())
-      .copied()
-      .unwrap_or(InputState::None)
-  })
+impl Display for LogicalKey {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      LogicalKey::Named(named) => write!(f, "{:?}", named),
+      LogicalKey::Character(character) => write!(f, "{}", character),
+    }
+  }
 }
 
-pub fn key_pressed_this_frame<K: Into<Key>>(key: K) -> bool {
-  key_state(key) == InputState::PressedThisFrame
+/// A key going down, with the modifiers held at that moment
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyPress {
+  pub physical: Option<PhysicalKey>,
+  pub logical: Option<LogicalKey>,
+  pub modifiers: ModifiersState,
 }
 
-pub fn key_released_this_frame<K: Into<Key>>(key: K) -> bool {
-  key_state(key) == InputState::ReleasedThisFrame
+impl KeyPress {
+  /// Whether `key` is either the physical or the logical key of the press
+  pub fn is<K: Into<Key>>(&self, key: K) -> bool {
+    match key.into() {
+      Key::PhyiscalKey(physical) => self.physical == Some(physical),
+      Key::Logical(logical) => self.logical == Some(logical),
+    }
+  }
+
+  /// Shift, control, alt and super themselves
+  pub fn is_modifier(&self) -> bool {
+    let physical = self.physical.is_some_and(|physical| {
+      matches!(
+        physical,
+        PhysicalKey::Code(
+          KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight
+            | KeyCode::SuperLeft
+            | KeyCode::SuperRight
+        )
+      )
+    });
+
+    let logical = self.logical.is_some_and(|logical| {
+      matches!(
+        logical,
+        LogicalKey::Named(NamedKey::Shift | NamedKey::Control | NamedKey::Alt | NamedKey::Super)
+      )
+    });
+
+    physical || logical
+  }
 }
 
-pub fn key_held<K: Into<Key>>(key: K) -> bool {
-  key_state(key) == InputState::Held
+#[derive(Clone)]
+pub(crate) struct KeyboardState {
+  // keys down as events arrive, so repeats and unmatched releases are dropped
+  down: HashSet<Key>,
+  // the logical key each held physical key produced when it went down
+  logical_down: HashMap<PhysicalKey, LogicalKey>,
+  states: HashMap<Key, InputState>,
+  // transitions since the last frame, and during the current frame
+  pending: Vec<(Key, ElementState)>,
+  current: Vec<(Key, ElementState)>,
+  pending_presses: Vec<KeyPress>,
+  presses: Vec<KeyPress>,
+  modifiers: ModifiersState,
+  // not using option becuase once a key is pressed, it will always have a value
+  // just using whatever `NativeKeyCode::Unidentified` is as a default
+  last_key_pressed: Key,
 }
 
-pub fn key_pressed<K: Into<Key>>(key: K) -> bool {
-  let state = key_state(key);
-  state == InputState::PressedThisFrame || state == InputState::Held
+impl Default for KeyboardState {
+  fn default() -> Self {
+    Self {
+      down: HashSet::new(),
+      logical_down: HashMap::new(),
+      states: HashMap::new(),
+      pending: Vec::new(),
+      current: Vec::new(),
+      pending_presses: Vec::new(),
+      presses: Vec::new(),
+      modifiers: ModifiersState::empty(),
+      last_key_pressed: Key::PhyiscalKey(PhysicalKey::Unidentified(NativeKeyCode::Unidentified)),
+    }
+  }
 }
 
-pub(crate) fn update_keyboard_state() {
-  KEYBOARD_STATE.with(|input_state| {
-    let mut input_state = input_state.borrow_mut();
+impl Input {
+  /// Queue a transition for the next frame, every transition is kept so a key
+  /// pressed and released between two frames is still seen
+  pub fn set_key_state(&mut self, key: Key, state: ElementState) {
+    let keyboard = &mut self.keyboard;
+
+    let changed = match state {
+      ElementState::Pressed => keyboard.down.insert(key),
+      ElementState::Released => keyboard.down.remove(&key),
+    };
 
-    for (_, state) in input_state.iter_mut() {
-      match state {
-        InputState::PressedThisFrame => *state = InputState::Held,
-        InputState::ReleasedThisFrame => *state = InputState::None,
-        _ => (),
+    if changed {
+      keyboard.pending.push((key, state));
+
+      if state.is_pressed() {
+        push_key_press(&mut keyboard.pending_presses, key, keyboard.modifiers);
       }
     }
-  });
+
+    keyboard.last_key_pressed = key;
+  }
+
+  /// Logical key to record for a transition of `physical`, a release gets
+  /// the logical key of its press. Shift+1 goes down as `!`, letting go of
+  /// shift first would otherwise release `1` and leave `!` held
+  pub fn logical_key_for(
+    &mut self,
+    physical: PhysicalKey,
+    logical: Option<LogicalKey>,
+    state: ElementState,
+  ) -> Option<LogicalKey> {
+    let logical_down = &mut self.keyboard.logical_down;
+
+    match state {
+      ElementState::Pressed => {
+        match logical {
+          Some(logical) => logical_down.insert(physical, logical),
+          None => logical_down.remove(&physical),
+        };
+
+        logical
+      }
+      ElementState::Released => logical_down.remove(&physical).or(logical),
+    }
+  }
+
+  pub fn set_modifiers(&mut self, modifiers: ModifiersState) {
+    self.keyboard.modifiers = modifiers;
+  }
+
+  /// Modifiers held right now
+  #[inline]
+  pub fn modifiers(&self) -> ModifiersState {
+    self.keyboard.modifiers
+  }
+
+  pub fn shift_pressed(&self) -> bool {
+    self.modifiers().shift_key()
+  }
+
+  pub fn control_pressed(&self) -> bool {
+    self.modifiers().control_key()
+  }
+
+  pub fn alt_pressed(&self) -> bool {
+    self.modifiers().alt_key()
+  }
+
+  pub fn super_pressed(&self) -> bool {
+    self.modifiers().super_key()
+  }
+
+  /// Keys pressed since the previous frame in order, without repeats
+  pub fn key_presses(&self) -> &[KeyPress] {
+    if self.keyboard_blocked() {
+      return &[];
+    }
+
+    self.raw().key_presses()
+  }
+
+  /// Every key transition since the previous frame in order
+  pub fn key_events(&self) -> &[(Key, ElementState)] {
+    if self.keyboard_blocked() {
+      return &[];
+    }
+
+    self.raw().key_events()
+  }
+
+  /// State at the end of the previous frame's events, a key pressed and
+  /// released in between is [`InputState::ReleasedThisFrame`]
+  pub fn key_state<K: Into<Key>>(&self, key: K) -> InputState {
+    if self.keyboard_blocked() {
+      return InputState::None;
+    }
+
+    self.raw().key_state(key)
+  }
+
+  pub fn key_pressed_this_frame<K: Into<Key>>(&self, key: K) -> bool {
+    self.key_state(key) == InputState::PressedThisFrame
+  }
+
+  pub fn key_released_this_frame<K: Into<Key>>(&self, key: K) -> bool {
+    self.key_state(key) == InputState::ReleasedThisFrame
+  }
+
+  /// True if the key went down since the previous frame, even if it is already
+  /// released again
+  pub fn key_was_pressed_this_frame<K: Into<Key>>(&self, key: K) -> bool {
+    self.key_transitions(key.into(), ElementState::Pressed) > 0
+  }
+
+  /// True if the key went up since the previous frame, even if it is already
+  /// pressed again
+  pub fn key_was_released_this_frame<K: Into<Key>>(&self, key: K) -> bool {
+    self.key_transitions(key.into(), ElementState::Released) > 0
+  }
+
+  /// How often the key went down since the previous frame
+  pub fn key_press_count<K: Into<Key>>(&self, key: K) -> usize {
+    self.key_transitions(key.into(), ElementState::Pressed)
+  }
+
+  fn key_transitions(&self, key: Key, state: ElementState) -> usize {
+    self
+      .key_events()
+      .iter()
+      .filter(|transition| **transition == (key, state))
+      .count()
+  }
+
+  pub fn key_held<K: Into<Key>>(&self, key: K) -> bool {
+    self.key_state(key) == InputState::Held
+  }
+
+  pub fn key_pressed<K: Into<Key>>(&self, key: K) -> bool {
+    let state = self.key_state(key);
+    state == InputState::PressedThisFrame || state == InputState::Held
+  }
+
+  pub fn last_key_pressed(&self) -> Key {
+    self.keyboard.last_key_pressed
+  }
+
+  pub(super) fn keys_down(&self) -> impl Iterator<Item = Key> + '_ {
+    self.keyboard.down.iter().copied()
+  }
+
+  pub(super) fn release_keys(&mut self) {
+    let keyboard = &mut self.keyboard;
+
+    for key in keyboard.down.drain() {
+      keyboard.pending.push((key, ElementState::Released));
+    }
+
+    keyboard.logical_down.clear();
+
+    keyboard.modifiers = ModifiersState::empty();
+  }
+
+  pub(super) fn update_keyboard_state(&mut self) {
+    let keyboard = &mut self.keyboard;
+
+    keyboard.current = mem::take(&mut keyboard.pending);
+    keyboard.presses = mem::take(&mut keyboard.pending_presses);
+
+    for state in keyboard.states.values_mut() {
+      settle(state);
+    }
+
+    for &(key, state) in &keyboard.current {
+      let input_state = keyboard.states.entry(key).or_insert(InputState::None);
+      *input_state = transition(*input_state, state);
+    }
+  }
+}
+
+impl<'a> RawInput<'a> {
+  pub fn key_presses(self) -> &'a [KeyPress] {
+    &self.input.keyboard.presses
+  }
+
+  pub fn key_events(self) -> &'a [(Key, ElementState)] {
+    &self.input.keyboard.current
+  }
+
+  pub fn key_state<K: Into<Key>>(self, key: K) -> InputState {
+    self
+      .input
+      .keyboard
+      .states
+      .get(&key.into())
+      .copied()
+      .unwrap_or(InputState::None)
+  }
+
+  pub fn key_pressed_this_frame<K: Into<Key>>(self, key: K) -> bool {
+    self.key_state(key) == InputState::PressedThisFrame
+  }
+
+  pub fn key_released_this_frame<K: Into<Key>>(self, key: K) -> bool {
+    self.key_state(key) == InputState::ReleasedThisFrame
+  }
+
+  pub fn key_held<K: Into<Key>>(self, key: K) -> bool {
+    self.key_state(key) == InputState::Held
+  }
+
+  pub fn key_pressed<K: Into<Key>>(self, key: K) -> bool {
+    matches!(self.key_state(key), InputState::PressedThisFrame | InputState::Held)
+  }
 }
 
-pub fn last_key_pressed() -> Key {
-  unsafe { LAST_KEY_PRESSED }
+// the window sends the physical key of a keystroke followed by its logical key,
+// so a logical key is merged into the press right before it
+fn push_key_press(pending: &mut Vec<KeyPress>, key: Key, modifiers: ModifiersState) {
+  match key {
+    Key::Logical(logical) => match pending.last_mut() {
+      Some(last) if last.physical.is_some() && last.logical.is_none() => last.logical = Some(logical),
+      _ => pending.push(KeyPress {
+        physical: None,
+        logical: Some(logical),
+        modifiers,
+      }),
+    },
+    Key::PhyiscalKey(physical) => pending.push(KeyPress {
+      physical: Some(physical),
+      logical: None,
+      modifiers,
+    }),
+  }
 }
@@ -1,5 +1,21 @@
+pub mod action;
 pub mod keyboard;
 pub mod mouse;
+pub mod record;
+pub mod shortcut;
+pub mod text;
+pub mod touch;
+
+use std::mem;
+
+use winit::event::{ElementState, MouseButton, TouchPhase};
+
+use crate::engine::input::keyboard::KeyboardState;
+use crate::engine::input::mouse::MouseState;
+use crate::engine::input::record::InputEvent;
+use crate::engine::input::text::TextState;
+use crate::engine::input::touch::TouchState;
+use crate::math::{Position, Vec2};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum InputState {
@@ -9,10 +25,457 @@ pub enum InputState {
   None,
 }
 
-pub fn update_input_state() {
-  keyboard::update_keyboard_state();
-  mouse::update_mouse_state();
+/// Keyboard, mouse, touch and text input of one window, owned by its
+/// [`EngineContext`](crate::EngineContext)
+///
+/// Events are queued as they arrive and show up in queries once
+/// [`Input::update`] starts the next frame, so every layer sees the same state
+/// for the whole frame
+#[derive(Clone, Default)]
+pub struct Input {
+  keyboard: KeyboardState,
+  mouse: MouseState,
+  text: TextState,
+  touch: TouchState,
+  pending_events: Vec<InputEvent>,
+  events: Vec<InputEvent>,
+  egui_wants_keyboard: bool,
+  egui_wants_pointer: bool,
+}
+
+/// Queries of an [`Input`] that answer even while egui wants the input, from
+/// [`Input::raw`]
+#[derive(Copy, Clone)]
+pub struct RawInput<'a> {
+  input: &'a Input,
+}
+
+impl Input {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Start a new frame with the events queued since the last one, called by
+  /// the engine before layers update, `delta_time` times double clicks and
+  /// touch gestures
+  pub fn update(&mut self, delta_time: f32) {
+    self.events = mem::take(&mut self.pending_events);
+
+    self.update_keyboard_state();
+    self.update_mouse_state(delta_time);
+    self.update_text_state();
+    self.update_touch_state(delta_time);
+  }
+
+  /// Every event applied since the previous frame in the order it arrived,
+  /// including ones played back from a recording, not filtered for egui
+  #[inline]
+  pub fn events(&self) -> &[InputEvent] {
+    &self.events
+  }
+
+  /// Updated after egui runs each frame
+  pub(crate) fn set_egui_focus(&mut self, wants_keyboard: bool, wants_pointer: bool) {
+    self.egui_wants_keyboard = wants_keyboard;
+    self.egui_wants_pointer = wants_pointer;
+  }
+
+  /// An egui text field has focus, key and text queries report nothing while
+  /// it does
+  #[inline]
+  pub fn egui_wants_keyboard(&self) -> bool {
+    self.egui_wants_keyboard
+  }
+
+  /// The pointer is over egui, button, scroll, click and drag queries report
+  /// nothing while it is
+  #[inline]
+  pub fn egui_wants_pointer(&self) -> bool {
+    self.egui_wants_pointer
+  }
+
+  /// Queries that ignore egui, e.g. for a layer that draws below egui but
+  /// still needs every key
+  #[inline]
+  pub fn raw(&self) -> RawInput<'_> {
+    RawInput { input: self }
+  }
+
+  fn keyboard_blocked(&self) -> bool {
+    self.egui_wants_keyboard
+  }
+
+  fn pointer_blocked(&self) -> bool {
+    self.egui_wants_pointer
+  }
+
+  /// Release every held key and button and cancel every touch next frame
+  /// and clear the modifiers, the window won't send releases once it lost
+  /// focus
+  pub fn release_all(&mut self) {
+    self.release_keys();
+    self.release_buttons();
+    self.cancel_touches();
+  }
+
+  /// Events that bring a fresh [`Input`] to the state held right now, what
+  /// happened in earlier frames is not included
+  pub(crate) fn snapshot(&self) -> Vec<InputEvent> {
+    let mut events = vec![
+      InputEvent::Modifiers(self.modifiers()),
+      if self.cursor_in_window() {
+        InputEvent::CursorEntered
+      } else {
+        InputEvent::CursorLeft
+      },
+      InputEvent::MousePosition {
+        x: self.mouse_position().x,
+        y: self.mouse_position().y,
+      },
+      InputEvent::EguiFocus {
+        keyboard: self.egui_wants_keyboard,
+        pointer: self.egui_wants_pointer,
+      },
+    ];
+
+    // the pixel position is left out until the cursor moved, a drag would
+    // otherwise start from where it was
+    if let Some(position) = self.last_pixel_position() {
+      events.push(InputEvent::MousePixelPosition {
+        x: position.x,
+        y: position.y,
+      });
+    }
+
+    events.extend(self.keys_down().map(|key| InputEvent::Key {
+      key,
+      state: ElementState::Pressed,
+    }));
+    events.extend(self.buttons_down().map(|button| InputEvent::MouseButton {
+      button,
+      state: ElementState::Pressed,
+    }));
+    events.extend(
+      self
+        .raw()
+        .touches()
+        .filter(|point| matches!(point.phase, TouchPhase::Started | TouchPhase::Moved))
+        .map(|point| InputEvent::Touch {
+          id: point.id,
+          phase: TouchPhase::Started,
+          x: point.pixel_position.x,
+          y: point.pixel_position.y,
+          normalized_x: point.position.x,
+          normalized_y: point.position.y,
+        }),
+    );
+
+    events
+  }
+
+  fn queue_event(&mut self, event: InputEvent) {
+    self.pending_events.push(event);
+  }
+
+  /// Apply an event as if it came from the window, seen after the next
+  /// [`Input::update`]
+  pub fn inject(&mut self, event: InputEvent) {
+    event.apply(self);
+  }
+
+  pub fn press_key(&mut self, key: impl Into<Key>) {
+    self.inject(InputEvent::Key {
+      key: key.into(),
+      state: ElementState::Pressed,
+    });
+  }
+
+  pub fn release_key(&mut self, key: impl Into<Key>) {
+    self.inject(InputEvent::Key {
+      key: key.into(),
+      state: ElementState::Released,
+    });
+  }
+
+  /// Press and release before the next frame
+  pub fn tap_key(&mut self, key: impl Into<Key>) {
+    let key = key.into();
+
+    self.press_key(key);
+    self.release_key(key);
+  }
+
+  pub fn press_button(&mut self, button: MouseButton) {
+    self.inject(InputEvent::MouseButton {
+      button,
+      state: ElementState::Pressed,
+    });
+  }
+
+  pub fn release_button(&mut self, button: MouseButton) {
+    self.inject(InputEvent::MouseButton {
+      button,
+      state: ElementState::Released,
+    });
+  }
+
+  /// Press and release before the next frame
+  pub fn click(&mut self, button: MouseButton) {
+    self.press_button(button);
+    self.release_button(button);
+  }
+
+  /// Move the cursor to `position` in physical pixels inside a window of
+  /// `size`
+  pub fn move_mouse(&mut self, position: Position, size: Vec2) {
+    let normalized = normalize_position(position, size);
+
+    self.inject(InputEvent::MousePosition {
+      x: normalized.x,
+      y: normalized.y,
+    });
+    self.inject(InputEvent::MousePixelPosition {
+      x: position.x,
+      y: position.y,
+    });
+  }
+
+  /// Lines, positive y scrolls up
+  pub fn scroll(&mut self, delta: Vec2) {
+    self.inject(InputEvent::Scroll { x: delta.x, y: delta.y });
+  }
+
+  pub fn type_text(&mut self, text: impl Into<String>) {
+    self.inject(InputEvent::Text(text.into()));
+  }
+
+  /// Start, move or lift finger `id` at `position` in physical pixels inside
+  /// a window of `size`
+  pub fn inject_touch(&mut self, id: u64, phase: TouchPhase, position: Position, size: Vec2) {
+    let normalized = normalize_position(position, size);
+
+    self.inject(InputEvent::Touch {
+      id,
+      phase,
+      x: position.x,
+      y: position.y,
+      normalized_x: normalized.x,
+      normalized_y: normalized.y,
+    });
+  }
+}
+
+/// What a state from last frame becomes before this frame's transitions
+pub(crate) fn settle(state: &mut InputState) {
+  match state {
+    InputState::PressedThisFrame => *state = InputState::Held,
+    InputState::ReleasedThisFrame => *state = InputState::None,
+    _ => (),
+  }
+}
+
+/// Apply one press or release, repeated presses and releases are filtered
+/// out before
+pub(crate) fn transition(current: InputState, state: ElementState) -> InputState {
+  match (current, state) {
+    (InputState::None | InputState::ReleasedThisFrame, ElementState::Pressed) => InputState::PressedThisFrame,
+    (InputState::Held | InputState::PressedThisFrame, ElementState::Released) => InputState::ReleasedThisFrame,
+    (current, _) => current,
+  }
 }
 
 pub use keyboard::*;
 pub use mouse::*;
+pub use text::*;
+pub use touch::*;
+
+#[cfg(test)]
+mod tests {
+  use winit::keyboard::{KeyCode, ModifiersState};
+
+  use super::*;
+  use crate::math::vec2;
+
+  const DT: f32 = 1.0 / 60.0;
+  const SIZE: Vec2 = vec2(800.0, 600.0);
+
+  #[test]
+  fn press_and_release_within_one_frame() {
+    let mut input = Input::new();
+
+    input.tap_key(KeyCode::KeyA);
+    input.click(MouseButton::Left);
+    input.update(DT);
+
+    assert_eq!(input.key_state(KeyCode::KeyA), InputState::ReleasedThisFrame);
+    assert!(input.key_was_pressed_this_frame(KeyCode::KeyA));
+    assert_eq!(input.key_press_count(KeyCode::KeyA), 1);
+    assert!(input.button_released_this_frame(MouseButton::Left));
+    assert!(input.button_was_pressed_this_frame(MouseButton::Left));
+
+    input.update(DT);
+
+    assert_eq!(input.key_state(KeyCode::KeyA), InputState::None);
+    assert!(!input.key_was_pressed_this_frame(KeyCode::KeyA));
+    assert_eq!(input.mouse_state(MouseButton::Left), InputState::None);
+  }
+
+  #[test]
+  fn held_key_settles() {
+    let mut input = Input::new();
+
+    input.press_key(KeyCode::Space);
+    input.update(DT);
+    assert!(input.key_pressed_this_frame(KeyCode::Space));
+
+    // repeats while held don't press again
+    input.press_key(KeyCode::Space);
+    input.update(DT);
+    assert!(input.key_held(KeyCode::Space));
+    assert_eq!(input.key_press_count(KeyCode::Space), 0);
+
+    input.release_key(KeyCode::Space);
+    input.update(DT);
+    assert!(input.key_released_this_frame(KeyCode::Space));
+  }
+
+  #[test]
+  fn release_all_on_focus_loss() {
+    let mut input = Input::new();
+
+    input.press_key(KeyCode::ShiftLeft);
+    input.inject(InputEvent::Modifiers(ModifiersState::SHIFT));
+    input.press_button(MouseButton::Right);
+    input.inject_touch(0, TouchPhase::Started, vec2(100.0, 100.0), SIZE);
+    input.update(DT);
+
+    assert!(input.key_pressed(KeyCode::ShiftLeft));
+    assert!(input.button_pressed(MouseButton::Right));
+
+    input.inject(InputEvent::FocusLost);
+    input.update(DT);
+
+    assert!(input.key_released_this_frame(KeyCode::ShiftLeft));
+    assert!(input.button_released_this_frame(MouseButton::Right));
+    assert!(!input.shift_pressed());
+    assert_eq!(input.touch(0).map(|touch| touch.phase), Some(TouchPhase::Cancelled));
+
+    input.update(DT);
+
+    assert_eq!(input.key_state(KeyCode::ShiftLeft), InputState::None);
+    assert_eq!(input.touches().count(), 0);
+
+    // nothing left to release
+    input.release_all();
+    input.update(DT);
+    assert!(input.key_events().is_empty());
+    assert!(input.button_events().is_empty());
+  }
+
+  #[test]
+  fn multi_clicks_within_the_interval() {
+    let mut input = Input::new();
+    input.move_mouse(vec2(100.0, 100.0), SIZE);
+
+    for count in 1..=3 {
+      input.click(MouseButton::Left);
+      input.update(0.2);
+
+      assert_eq!(input.click_count(MouseButton::Left), count);
+    }
+
+    assert!(input.triple_clicked(MouseButton::Left));
+
+    input.update(0.2);
+    assert_eq!(input.click_count(MouseButton::Left), 0);
+
+    // too slow for a double click
+    input.update(DEFAULT_MULTI_CLICK_INTERVAL.as_secs_f32());
+    input.click(MouseButton::Left);
+    input.update(DT);
+    assert_eq!(input.click_count(MouseButton::Left), 1);
+
+    // too far away for a double click
+    input.move_mouse(vec2(100.0 + DEFAULT_DRAG_THRESHOLD * 2.0, 100.0), SIZE);
+    input.click(MouseButton::Left);
+    input.update(DT);
+    assert_eq!(input.click_count(MouseButton::Left), 1);
+
+    input.click(MouseButton::Left);
+    input.update(DT);
+    assert!(input.double_clicked(MouseButton::Left));
+  }
+
+  #[test]
+  fn drag_starts_past_the_threshold() {
+    let mut input = Input::new();
+    let origin = vec2(100.0, 100.0);
+
+    input.move_mouse(origin, SIZE);
+    input.press_button(MouseButton::Left);
+    input.update(DT);
+    assert!(!input.dragging(MouseButton::Left));
+
+    input.move_mouse(origin + vec2(DEFAULT_DRAG_THRESHOLD / 2.0, 0.0), SIZE);
+    input.update(DT);
+    assert!(!input.drag_started(MouseButton::Left));
+
+    input.move_mouse(origin + vec2(DEFAULT_DRAG_THRESHOLD * 2.0, 0.0), SIZE);
+    input.update(DT);
+    assert!(input.drag_started(MouseButton::Left));
+    assert_eq!(input.drag_origin(MouseButton::Left), Some(origin));
+
+    input.move_mouse(origin + vec2(50.0, 0.0), SIZE);
+    input.update(DT);
+    assert!(!input.drag_started(MouseButton::Left));
+    assert!(input.dragging(MouseButton::Left));
+
+    input.release_button(MouseButton::Left);
+    input.update(DT);
+    assert!(input.drag_ended(MouseButton::Left));
+    assert!(!input.dragging(MouseButton::Left));
+
+    // a click without moving never drags
+    input.click(MouseButton::Left);
+    input.update(DT);
+    assert!(!input.drag_started(MouseButton::Left));
+    assert!(!input.drag_ended(MouseButton::Left));
+  }
+
+  #[test]
+  fn egui_focus_filters_queries_but_not_raw() {
+    let mut input = Input::new();
+
+    input.press_key(KeyCode::KeyW);
+    input.press_button(MouseButton::Left);
+    input.scroll(vec2(0.0, 1.0));
+    input.type_text("w");
+    input.set_egui_focus(true, true);
+    input.update(DT);
+
+    assert_eq!(input.key_state(KeyCode::KeyW), InputState::None);
+    assert!(input.key_events().is_empty());
+    assert!(input.typed_text().is_empty());
+    assert!(!input.button_pressed(MouseButton::Left));
+    assert_eq!(input.scroll_delta(), Vec2::zeros());
+    assert_eq!(input.click_count(MouseButton::Left), 0);
+
+    let raw = input.raw();
+    assert!(raw.key_pressed_this_frame(KeyCode::KeyW));
+    assert_eq!(raw.typed_text(), "w");
+    assert!(raw.button_pressed_this_frame(MouseButton::Left));
+    assert_eq!(raw.scroll_delta(), vec2(0.0, 1.0));
+    assert_eq!(raw.click_count(MouseButton::Left), 1);
+
+    // the recording sees everything
+    assert_eq!(input.events().len(), 4);
+
+    // only the pointer is over egui, keys go through
+    input.set_egui_focus(false, true);
+    assert!(input.key_pressed(KeyCode::KeyW));
+    assert!(!input.button_pressed(MouseButton::Left));
+
+    input.set_egui_focus(false, false);
+    assert!(input.button_pressed(MouseButton::Left));
+  }
+}
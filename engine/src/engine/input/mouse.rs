@@ -1,73 +1,488 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::time::Duration;
 
 use winit::event::{ElementState, MouseButton};
 
-use crate::engine::input::InputState;
-use crate::math::Position;
+use crate::engine::input::{settle, transition, Input, InputState, RawInput};
+use crate::math::{Position, Vec2};
 
-thread_local! {
-  static MOUSE_STATE: RefCell<HashMap<MouseButton, InputState>> = RefCell::new(HashMap::new());
-  static MOUSE_POSITION: RefCell<Position> = RefCell::new(Position::zeros());
-}
+/// Pixels one line of wheel scrolling is worth, used to convert between line
+/// and pixel scrolling
+pub const PIXELS_PER_LINE: f32 = 40.0;
 
-pub fn set_mouse_state(button: MouseButton, state: ElementState) {
-  MOUSE_STATE.with(|mouse_state| {
-    let mut mouse_state = mouse_state.borrow_mut();
-    let current_state = mouse_state.get(&button).copied().unwrap_or(InputState::None);
+/// Clicks closer together than this count as double and triple clicks
+pub const DEFAULT_MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(500);
 
-    if state == ElementState::Pressed && current_state == InputState::None {
-      mouse_state.insert(button, InputState::PressedThisFrame);
-    } else if state == ElementState::Released && current_state == InputState::Held {
-      mouse_state.insert(button, InputState::ReleasedThisFrame);
-    }
-  });
-}
+/// Pixels the cursor has to move with a button held before it is a drag
+pub const DEFAULT_DRAG_THRESHOLD: f32 = 4.0;
 
-pub fn mouse_state(button: MouseButton) -> InputState {
-  MOUSE_STATE.with(|mouse_state| mouse_state.borrow().get(&button).copied().unwrap_or(InputState::None))
+// things that happened since the last frame, and during the current frame
+#[derive(Clone, Default)]
+struct MouseFrame {
+  buttons: Vec<(MouseButton, ElementState)>,
+  scroll: Vec2,
+  pixel_scroll: Vec2,
+  delta: Vec2,
+  raw_delta: Vec2,
+  entered: bool,
+  left: bool,
+  clicks: HashMap<MouseButton, u32>,
+  drags_started: Vec<MouseButton>,
+  drags_ended: Vec<MouseButton>,
 }
 
-pub fn set_mouse_position(pos: Position) {
-  MOUSE_POSITION.with(|mouse_position| {
-    *mouse_position.borrow_mut() = pos;
-  });
+#[derive(Copy, Clone)]
+struct Click {
+  time: Duration,
+  position: Position,
+  count: u32,
 }
 
-pub fn mouse_position() -> Position {
-  MOUSE_POSITION.with(|mouse_position| *mouse_position.borrow())
+#[derive(Copy, Clone)]
+struct Drag {
+  origin: Position,
+  active: bool,
 }
 
-pub fn button_pressed_this_frame(button: MouseButton) -> bool {
-  mouse_state(button) == InputState::PressedThisFrame
+#[derive(Clone)]
+pub(crate) struct MouseState {
+  // buttons down as events arrive, so repeats and unmatched releases are dropped
+  down: HashSet<MouseButton>,
+  buttons: HashMap<MouseButton, InputState>,
+  position: Position,
+  pixel_position: Position,
+  // none until the cursor moves inside the window so entering doesn't jump
+  last_pixel_position: Option<Position>,
+  in_window: bool,
+  pending: MouseFrame,
+  current: MouseFrame,
+  // advanced by every frame's delta_time so replayed clicks are timed the same
+  clock: Duration,
+  last_clicks: HashMap<MouseButton, Click>,
+  drags: HashMap<MouseButton, Drag>,
+  multi_click_interval: Duration,
+  drag_threshold: f32,
 }
 
-pub fn button_released_this_frame(button: MouseButton) -> bool {
-  mouse_state(button) == InputState::ReleasedThisFrame
+impl Default for MouseState {
+  fn default() -> Self {
+    Self {
+      down: HashSet::new(),
+      buttons: HashMap::new(),
+      position: Position::zeros(),
+      pixel_position: Position::zeros(),
+      last_pixel_position: None,
+      in_window: false,
+      pending: MouseFrame::default(),
+      current: MouseFrame::default(),
+      clock: Duration::ZERO,
+      last_clicks: HashMap::new(),
+      drags: HashMap::new(),
+      multi_click_interval: DEFAULT_MULTI_CLICK_INTERVAL,
+      drag_threshold: DEFAULT_DRAG_THRESHOLD,
+    }
+  }
 }
 
-pub fn button_held(button: MouseButton) -> bool {
-  mouse_state(button) == InputState::Held
+/// Physical pixels inside a window of `size` to [-1, 1] with y pointing down
+pub fn normalize_position(position: Position, size: Vec2) -> Position {
+  Position::new(position.x / size.x * 2.0 - 1.0, position.y / size.y * 2.0 - 1.0)
 }
 
-pub fn button_pressed(button: MouseButton) -> bool {
-  mouse_state(button) == InputState::PressedThisFrame || mouse_state(button) == InputState::Held
+impl Input {
+  /// Queue a transition for the next frame, every transition is kept so a
+  /// click between two frames is still seen
+  pub fn set_mouse_state(&mut self, button: MouseButton, state: ElementState) {
+    let mouse = &mut self.mouse;
+
+    match state {
+      ElementState::Pressed if mouse.down.insert(button) => {
+        mouse.pending.buttons.push((button, state));
+        press(mouse, button);
+      }
+      ElementState::Released if mouse.down.remove(&button) => {
+        mouse.pending.buttons.push((button, state));
+
+        if mouse.drags.remove(&button).is_some_and(|drag| drag.active) {
+          mouse.pending.drags_ended.push(button);
+        }
+      }
+      _ => (),
+    }
+  }
+
+  /// State at the end of the previous frame's events, a button pressed and
+  /// released in between is [`InputState::ReleasedThisFrame`]
+  pub fn mouse_state(&self, button: MouseButton) -> InputState {
+    if self.pointer_blocked() {
+      return InputState::None;
+    }
+
+    self.raw().mouse_state(button)
+  }
+
+  /// Normalized to [-1, 1] with y pointing down
+  pub fn set_mouse_position(&mut self, pos: Position) {
+    self.mouse.position = pos;
+  }
+
+  /// Normalized to [-1, 1] with y pointing down
+  #[inline]
+  pub fn mouse_position(&self) -> Position {
+    self.mouse.position
+  }
+
+  /// Physical pixels from the top left of the window, also moves drags along
+  pub fn set_mouse_pixel_position(&mut self, pos: Position) {
+    let mouse = &mut self.mouse;
+
+    if let Some(last) = mouse.last_pixel_position {
+      mouse.pending.delta += pos - last;
+    }
+
+    mouse.pixel_position = pos;
+    mouse.last_pixel_position = Some(pos);
+
+    let threshold = mouse.drag_threshold;
+
+    for (button, drag) in &mut mouse.drags {
+      if !drag.active && (pos - drag.origin).norm() > threshold {
+        drag.active = true;
+        mouse.pending.drags_started.push(*button);
+      }
+    }
+  }
+
+  /// Physical pixels from the top left of the window
+  #[inline]
+  pub fn mouse_pixel_position(&self) -> Position {
+    self.mouse.pixel_position
+  }
+
+  /// Physical pixels the cursor moved since the previous frame
+  #[inline]
+  pub fn mouse_delta(&self) -> Vec2 {
+    self.mouse.current.delta
+  }
+
+  /// Add movement straight from the mouse, in device units without
+  /// acceleration
+  pub fn add_raw_mouse_delta(&mut self, delta: Vec2) {
+    self.mouse.pending.raw_delta += delta;
+  }
+
+  /// Movement straight from the mouse since the previous frame, keeps going
+  /// while the cursor is locked or at the edge of the screen
+  #[inline]
+  pub fn raw_mouse_delta(&self) -> Vec2 {
+    self.mouse.current.raw_delta
+  }
+
+  pub fn set_cursor_in_window(&mut self, in_window: bool) {
+    let mouse = &mut self.mouse;
+
+    mouse.in_window = in_window;
+    mouse.last_pixel_position = None;
+
+    if in_window {
+      mouse.pending.entered = true;
+    } else {
+      mouse.pending.left = true;
+    }
+  }
+
+  #[inline]
+  pub fn cursor_in_window(&self) -> bool {
+    self.mouse.in_window
+  }
+
+  #[inline]
+  pub fn cursor_entered_this_frame(&self) -> bool {
+    self.mouse.current.entered
+  }
+
+  #[inline]
+  pub fn cursor_left_this_frame(&self) -> bool {
+    self.mouse.current.left
+  }
+
+  /// Add wheel movement in lines, positive y scrolls up
+  pub fn add_scroll_delta(&mut self, delta: Vec2) {
+    self.mouse.pending.scroll += delta;
+    self.mouse.pending.pixel_scroll += delta * PIXELS_PER_LINE;
+  }
+
+  /// Add touchpad movement in pixels, positive y scrolls up
+  pub fn add_pixel_scroll_delta(&mut self, delta: Vec2) {
+    self.mouse.pending.scroll += delta / PIXELS_PER_LINE;
+    self.mouse.pending.pixel_scroll += delta;
+  }
+
+  /// Lines scrolled since the previous frame, pixel scrolling counts
+  /// [`PIXELS_PER_LINE`] pixels per line
+  #[inline]
+  pub fn scroll_delta(&self) -> Vec2 {
+    if self.pointer_blocked() {
+      return Vec2::zeros();
+    }
+
+    self.raw().scroll_delta()
+  }
+
+  /// Pixels scrolled since the previous frame, line scrolling counts
+  /// [`PIXELS_PER_LINE`] pixels per line
+  #[inline]
+  pub fn pixel_scroll_delta(&self) -> Vec2 {
+    if self.pointer_blocked() {
+      return Vec2::zeros();
+    }
+
+    self.raw().pixel_scroll_delta()
+  }
+
+  pub fn button_pressed_this_frame(&self, button: MouseButton) -> bool {
+    self.mouse_state(button) == InputState::PressedThisFrame
+  }
+
+  pub fn button_released_this_frame(&self, button: MouseButton) -> bool {
+    self.mouse_state(button) == InputState::ReleasedThisFrame
+  }
+
+  pub fn button_held(&self, button: MouseButton) -> bool {
+    self.mouse_state(button) == InputState::Held
+  }
+
+  pub fn button_pressed(&self, button: MouseButton) -> bool {
+    let state = self.mouse_state(button);
+    state == InputState::PressedThisFrame || state == InputState::Held
+  }
+
+  pub fn button_released(&self, button: MouseButton) -> bool {
+    self.mouse_state(button) == InputState::ReleasedThisFrame
+  }
+
+  /// True if the button went down since the previous frame, even if it is
+  /// already released again
+  pub fn button_was_pressed_this_frame(&self, button: MouseButton) -> bool {
+    self.button_transitions(button, ElementState::Pressed) > 0
+  }
+
+  /// True if the button went up since the previous frame, even if it is
+  /// already pressed again
+  pub fn button_was_released_this_frame(&self, button: MouseButton) -> bool {
+    self.button_transitions(button, ElementState::Released) > 0
+  }
+
+  fn button_transitions(&self, button: MouseButton, state: ElementState) -> usize {
+    self
+      .button_events()
+      .iter()
+      .filter(|transition| **transition == (button, state))
+      .count()
+  }
+
+  /// Every button transition since the previous frame in order
+  pub fn button_events(&self) -> &[(MouseButton, ElementState)] {
+    if self.pointer_blocked() {
+      return &[];
+    }
+
+    self.raw().button_events()
+  }
+
+  /// 1 for a single click, 2 for a double click and so on when `button` was
+  /// pressed since the previous frame, 0 otherwise
+  pub fn click_count(&self, button: MouseButton) -> u32 {
+    if self.pointer_blocked() {
+      return 0;
+    }
+
+    self.raw().click_count(button)
+  }
+
+  pub fn double_clicked(&self, button: MouseButton) -> bool {
+    self.click_count(button) == 2
+  }
+
+  pub fn triple_clicked(&self, button: MouseButton) -> bool {
+    self.click_count(button) == 3
+  }
+
+  pub fn drag_started(&self, button: MouseButton) -> bool {
+    !self.pointer_blocked() && self.raw().drag_started(button)
+  }
+
+  pub fn drag_ended(&self, button: MouseButton) -> bool {
+    !self.pointer_blocked() && self.raw().drag_ended(button)
+  }
+
+  /// True from [`Input::drag_started`] until the button is released
+  pub fn dragging(&self, button: MouseButton) -> bool {
+    self.drag_origin(button).is_some()
+  }
+
+  /// Pixel position `button` was pressed at while it is dragging
+  pub fn drag_origin(&self, button: MouseButton) -> Option<Position> {
+    if self.pointer_blocked() {
+      return None;
+    }
+
+    self.raw().drag_origin(button)
+  }
+
+  #[inline]
+  pub fn multi_click_interval(&self) -> Duration {
+    self.mouse.multi_click_interval
+  }
+
+  /// Longest time between clicks of a double or triple click,
+  /// [`DEFAULT_MULTI_CLICK_INTERVAL`] by default
+  pub fn set_multi_click_interval(&mut self, interval: Duration) {
+    self.mouse.multi_click_interval = interval;
+  }
+
+  #[inline]
+  pub fn drag_threshold(&self) -> f32 {
+    self.mouse.drag_threshold
+  }
+
+  /// Pixels before a held button starts a drag, also how far apart the clicks
+  /// of a double click can be, [`DEFAULT_DRAG_THRESHOLD`] by default
+  pub fn set_drag_threshold(&mut self, pixels: f32) {
+    self.mouse.drag_threshold = pixels;
+  }
+
+  pub(super) fn buttons_down(&self) -> impl Iterator<Item = MouseButton> + '_ {
+    self.mouse.down.iter().copied()
+  }
+
+  /// `None` until the cursor moved inside the window
+  pub(super) fn last_pixel_position(&self) -> Option<Position> {
+    self.mouse.last_pixel_position
+  }
+
+  pub(super) fn release_buttons(&mut self) {
+    let mouse = &mut self.mouse;
+
+    for button in mouse.down.drain() {
+      mouse.pending.buttons.push((button, ElementState::Released));
+
+      if mouse.drags.remove(&button).is_some_and(|drag| drag.active) {
+        mouse.pending.drags_ended.push(button);
+      }
+    }
+  }
+
+  pub(super) fn update_mouse_state(&mut self, delta_time: f32) {
+    let mouse = &mut self.mouse;
+
+    mouse.clock += Duration::from_secs_f32(delta_time.max(0.0));
+    mouse.current = mem::take(&mut mouse.pending);
+
+    for state in mouse.buttons.values_mut() {
+      settle(state);
+    }
+
+    for &(button, state) in &mouse.current.buttons {
+      let input_state = mouse.buttons.entry(button).or_insert(InputState::None);
+      *input_state = transition(*input_state, state);
+    }
+  }
 }
 
-pub fn button_released(button: MouseButton) -> bool {
-  mouse_state(button) == InputState::ReleasedThisFrame
+impl<'a> RawInput<'a> {
+  pub fn mouse_state(self, button: MouseButton) -> InputState {
+    self
+      .input
+      .mouse
+      .buttons
+      .get(&button)
+      .copied()
+      .unwrap_or(InputState::None)
+  }
+
+  pub fn button_pressed_this_frame(self, button: MouseButton) -> bool {
+    self.mouse_state(button) == InputState::PressedThisFrame
+  }
+
+  pub fn button_released_this_frame(self, button: MouseButton) -> bool {
+    self.mouse_state(button) == InputState::ReleasedThisFrame
+  }
+
+  pub fn button_held(self, button: MouseButton) -> bool {
+    self.mouse_state(button) == InputState::Held
+  }
+
+  pub fn button_pressed(self, button: MouseButton) -> bool {
+    matches!(
+      self.mouse_state(button),
+      InputState::PressedThisFrame | InputState::Held
+    )
+  }
+
+  pub fn button_events(self) -> &'a [(MouseButton, ElementState)] {
+    &self.input.mouse.current.buttons
+  }
+
+  pub fn scroll_delta(self) -> Vec2 {
+    self.input.mouse.current.scroll
+  }
+
+  pub fn pixel_scroll_delta(self) -> Vec2 {
+    self.input.mouse.current.pixel_scroll
+  }
+
+  pub fn click_count(self, button: MouseButton) -> u32 {
+    self.input.mouse.current.clicks.get(&button).copied().unwrap_or(0)
+  }
+
+  pub fn drag_started(self, button: MouseButton) -> bool {
+    self.input.mouse.current.drags_started.contains(&button)
+  }
+
+  pub fn drag_ended(self, button: MouseButton) -> bool {
+    self.input.mouse.current.drags_ended.contains(&button)
+  }
+
+  pub fn drag_origin(self, button: MouseButton) -> Option<Position> {
+    self
+      .input
+      .mouse
+      .drags
+      .get(&button)
+      .filter(|drag| drag.active)
+      .map(|drag| drag.origin)
+  }
 }
 
-pub(crate) fn update_mouse_state() {
-  MOUSE_STATE.with(|mouse_state| {
-    let mut mouse_state = mouse_state.borrow_mut();
+fn press(mouse: &mut MouseState, button: MouseButton) {
+  let position = mouse.pixel_position;
 
-    for (_, state) in mouse_state.iter_mut() {
-      match state {
-        InputState::PressedThisFrame => *state = InputState::Held,
-        InputState::ReleasedThisFrame => *state = InputState::None,
-        _ => {}
-      }
+  let count = match mouse.last_clicks.get(&button) {
+    Some(last)
+      if mouse.clock.saturating_sub(last.time) <= mouse.multi_click_interval
+        && (position - last.position).norm() <= mouse.drag_threshold =>
+    {
+      last.count + 1
     }
-  });
+    _ => 1,
+  };
+
+  mouse.last_clicks.insert(
+    button,
+    Click {
+      time: mouse.clock,
+      position,
+      count,
+    },
+  );
+  mouse.pending.clicks.insert(button, count);
+  mouse.drags.insert(
+    button,
+    Drag {
+      origin: position,
+      active: false,
+    },
+  );
 }
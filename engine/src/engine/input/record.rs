@@ -0,0 +1,479 @@
+// input recording and playback
+// one ron value per line, a header followed by every frame with its
+// delta_time and the input events applied before it, so a recording can be
+// read even if the app crashed halfway through
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use winit::event::{ElementState, MouseButton, TouchPhase};
+use winit::keyboard::ModifiersState;
+
+use crate::engine::input::{ImeEvent, Input, Key};
+use crate::math::vec2;
+
+const VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+  #[error(transparent)]
+  Io(#[from] io::Error),
+  #[error(transparent)]
+  Serialize(#[from] ron::Error),
+  #[error("{path}:{line}: {source}")]
+  Parse {
+    path: PathBuf,
+    line: usize,
+    source: ron::error::SpannedError,
+  },
+  #[error("{0} is empty")]
+  Empty(PathBuf),
+  #[error("{path} is version {version} but only version {VERSION} is supported")]
+  Version { path: PathBuf, version: u32 },
+  #[error("{path}:{line}: expected frame {expected} but found {found}")]
+  FrameOrder {
+    path: PathBuf,
+    line: usize,
+    expected: u64,
+    found: u64,
+  },
+}
+
+/// Input that changes the state read through [`Input`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+  Key {
+    key: Key,
+    state: ElementState,
+  },
+  Modifiers(ModifiersState),
+  MouseButton {
+    button: MouseButton,
+    state: ElementState,
+  },
+  /// Normalized like [`Input::set_mouse_position`]
+  MousePosition {
+    x: f32,
+    y: f32,
+  },
+  /// Physical pixels like [`Input::set_mouse_pixel_position`]
+  MousePixelPosition {
+    x: f32,
+    y: f32,
+  },
+  CursorEntered,
+  CursorLeft,
+  /// Unaccelerated device movement like [`Input::add_raw_mouse_delta`]
+  RawMouseMotion {
+    x: f32,
+    y: f32,
+  },
+  /// Lines like [`Input::add_scroll_delta`]
+  Scroll {
+    x: f32,
+    y: f32,
+  },
+  /// Pixels like [`Input::add_pixel_scroll_delta`]
+  PixelScroll {
+    x: f32,
+    y: f32,
+  },
+  Text(String),
+  Ime(ImeEvent),
+  /// Physical pixels and normalized like [`Input::set_touch`]
+  Touch {
+    id: u64,
+    phase: TouchPhase,
+    x: f32,
+    y: f32,
+    normalized_x: f32,
+    normalized_y: f32,
+  },
+  /// Trackpad pinch like [`Input::add_pinch_gesture`]
+  PinchGesture(f32),
+  /// Trackpad rotation in radians like [`Input::add_rotation_gesture`]
+  RotationGesture(f32),
+  /// The window lost focus, see [`Input::release_all`]
+  FocusLost,
+  /// What egui wants after a frame like [`Input::egui_wants_keyboard`] and
+  /// [`Input::egui_wants_pointer`], recorded since it filters queries
+  EguiFocus {
+    keyboard: bool,
+    pointer: bool,
+  },
+}
+
+impl InputEvent {
+  /// Update the input state as if the event came from the window, the event
+  /// is also listed in [`Input::events`] next frame
+  pub fn apply(&self, input: &mut Input) {
+    input.queue_event(self.clone());
+
+    match self {
+      InputEvent::Key { key, state } => input.set_key_state(*key, *state),
+      InputEvent::Modifiers(modifiers) => input.set_modifiers(*modifiers),
+      InputEvent::MouseButton { button, state } => input.set_mouse_state(*button, *state),
+      InputEvent::MousePosition { x, y } => input.set_mouse_position(vec2(*x, *y)),
+      InputEvent::MousePixelPosition { x, y } => input.set_mouse_pixel_position(vec2(*x, *y)),
+      InputEvent::CursorEntered => input.set_cursor_in_window(true),
+      InputEvent::CursorLeft => input.set_cursor_in_window(false),
+      InputEvent::RawMouseMotion { x, y } => input.add_raw_mouse_delta(vec2(*x, *y)),
+      InputEvent::Scroll { x, y } => input.add_scroll_delta(vec2(*x, *y)),
+      InputEvent::PixelScroll { x, y } => input.add_pixel_scroll_delta(vec2(*x, *y)),
+      InputEvent::Text(text) => input.push_text(text),
+      InputEvent::Ime(event) => input.push_ime_event(event.clone()),
+      InputEvent::Touch {
+        id,
+        phase,
+        x,
+        y,
+        normalized_x,
+        normalized_y,
+      } => input.set_touch(*id, *phase, vec2(*x, *y), vec2(*normalized_x, *normalized_y)),
+      InputEvent::PinchGesture(delta) => input.add_pinch_gesture(*delta),
+      InputEvent::RotationGesture(angle) => input.add_rotation_gesture(*angle),
+      InputEvent::FocusLost => input.release_all(),
+      InputEvent::EguiFocus { keyboard, pointer } => input.set_egui_focus(*keyboard, *pointer),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+  version: u32,
+}
+
+/// Everything needed to replay one frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameInput {
+  /// Frames since the recording started
+  pub frame: u64,
+  pub delta_time: f32,
+  /// In the order they arrived, applied before the frame's update
+  pub events: Vec<InputEvent>,
+}
+
+/// Writes every frame's input to a file as it happens
+pub struct InputRecorder {
+  writer: BufWriter<File>,
+  frame: u64,
+  events: Vec<InputEvent>,
+  snapshot_taken: bool,
+}
+
+impl InputRecorder {
+  pub fn create(path: impl AsRef<Path>) -> Result<Self, Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", ron::to_string(&Header { version: VERSION })?)?;
+
+    Ok(Self {
+      writer,
+      frame: 0,
+      events: Vec::new(),
+      snapshot_taken: false,
+    })
+  }
+
+  /// Record what `input` holds right now ahead of every other event, so a
+  /// recording started mid-session plays back from the same state, held keys
+  /// and buttons are pressed on the first frame. Only the first call does
+  /// anything
+  pub fn record_state(&mut self, input: &Input) {
+    if !self.snapshot_taken {
+      self.snapshot_taken = true;
+      self.events.splice(0..0, input.snapshot());
+    }
+  }
+
+  /// Record an event for the next frame
+  pub fn record(&mut self, event: InputEvent) {
+    self.events.push(event);
+  }
+
+  /// Write the events recorded since the last frame together with the
+  /// frame's `delta_time`
+  pub fn end_frame(&mut self, delta_time: f32) -> Result<(), Error> {
+    let frame = FrameInput {
+      frame: self.frame,
+      delta_time,
+      events: std::mem::take(&mut self.events),
+    };
+
+    writeln!(self.writer, "{}", ron::to_string(&frame)?)?;
+    self.frame += 1;
+
+    Ok(())
+  }
+
+  /// Frames written so far
+  #[inline]
+  pub fn frames(&self) -> u64 {
+    self.frame
+  }
+
+  pub fn finish(mut self) -> Result<(), Error> {
+    self.writer.flush()?;
+
+    Ok(())
+  }
+}
+
+/// Recorded frames read back in order
+pub struct InputPlayer {
+  frames: VecDeque<FrameInput>,
+}
+
+impl InputPlayer {
+  pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path)?);
+
+    let parse_error = |line: usize, source| Error::Parse {
+      path: path.to_path_buf(),
+      line: line + 1,
+      source,
+    };
+
+    let mut lines = reader.lines().enumerate();
+
+    let Some((_, header)) = lines.next() else {
+      return Err(Error::Empty(path.to_path_buf()));
+    };
+
+    let header: Header = ron::from_str(&header?).map_err(|e| parse_error(0, e))?;
+
+    if header.version != VERSION {
+      return Err(Error::Version {
+        path: path.to_path_buf(),
+        version: header.version,
+      });
+    }
+
+    let mut frames = VecDeque::new();
+
+    for (i, line) in lines {
+      let line = line?;
+
+      if line.trim().is_empty() {
+        continue;
+      }
+
+      let frame: FrameInput = ron::from_str(&line).map_err(|e| parse_error(i, e))?;
+      let expected = frames.len() as u64;
+
+      if frame.frame != expected {
+        return Err(Error::FrameOrder {
+          path: path.to_path_buf(),
+          line: i + 1,
+          expected,
+          found: frame.frame,
+        });
+      }
+
+      frames.push_back(frame);
+    }
+
+    Ok(Self { frames })
+  }
+
+  /// Frames left to play
+  #[inline]
+  pub fn remaining(&self) -> usize {
+    self.frames.len()
+  }
+
+  pub fn next_frame(&mut self) -> Option<FrameInput> {
+    self.frames.pop_front()
+  }
+}
+
+enum Mode {
+  Live,
+  Recording(InputRecorder),
+  Playing(InputPlayer),
+}
+
+/// Records live input or replays a recording in its place
+pub struct InputReplay {
+  mode: Mode,
+  // live and played input don't mix, set when switching between them
+  release_input: bool,
+}
+
+impl InputReplay {
+  pub fn new() -> Self {
+    Self {
+      mode: Mode::Live,
+      release_input: false,
+    }
+  }
+
+  /// Record from the next frame on, stops any recording or playback
+  pub fn record(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+    self.stop();
+
+    let path = path.as_ref();
+    self.mode = Mode::Recording(InputRecorder::create(path)?);
+    info!("recording input to {}", path.display());
+
+    Ok(())
+  }
+
+  /// Replace live input with a recording from the next frame on, live input
+  /// resumes once it ends
+  pub fn play(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+    self.stop();
+
+    let path = path.as_ref();
+    let player = InputPlayer::open(path)?;
+    info!("playing {} frames of input from {}", player.remaining(), path.display());
+
+    self.mode = Mode::Playing(player);
+    self.release_input = true;
+
+    Ok(())
+  }
+
+  /// Back to live input, finishing a recording
+  pub fn stop(&mut self) {
+    match std::mem::replace(&mut self.mode, Mode::Live) {
+      Mode::Recording(recorder) => {
+        let frames = recorder.frames();
+
+        match recorder.finish() {
+          Ok(()) => info!("recorded {frames} frames of input"),
+          Err(e) => error!("failed to finish input recording: {e}"),
+        }
+      }
+      // keys the recording held would stay down
+      Mode::Playing(_) => self.release_input = true,
+      Mode::Live => (),
+    }
+  }
+
+  #[inline]
+  pub fn is_recording(&self) -> bool {
+    matches!(self.mode, Mode::Recording(_))
+  }
+
+  #[inline]
+  pub fn is_playing(&self) -> bool {
+    matches!(self.mode, Mode::Playing(_))
+  }
+
+  /// Apply input from the window unless a recording is playing, the app
+  /// keeps it from egui and the layers as well while one is
+  pub(crate) fn live_event(&mut self, input: &mut Input, event: InputEvent) {
+    match &mut self.mode {
+      Mode::Live => event.apply(input),
+      Mode::Recording(recorder) => {
+        recorder.record_state(input);
+        event.apply(input);
+        recorder.record(event);
+      }
+      Mode::Playing(_) => (),
+    }
+  }
+
+  /// Called before input state is updated for a frame, returns the
+  /// `delta_time` the frame should use
+  pub(crate) fn begin_frame(&mut self, input: &mut Input, delta_time: f32) -> f32 {
+    if std::mem::take(&mut self.release_input) {
+      input.release_all();
+    }
+
+    match &mut self.mode {
+      Mode::Live => delta_time,
+      Mode::Recording(recorder) => {
+        recorder.record_state(input);
+
+        if let Err(e) = recorder.end_frame(delta_time) {
+          error!("stopping input recording: {e}");
+          self.stop();
+        }
+
+        delta_time
+      }
+      Mode::Playing(player) => match player.next_frame() {
+        Some(frame) => {
+          frame.events.iter().for_each(|event| event.apply(input));
+          frame.delta_time
+        }
+        None => {
+          info!("input playback finished");
+          self.mode = Mode::Live;
+          input.release_all();
+
+          delta_time
+        }
+      },
+    }
+  }
+}
+
+impl Default for InputReplay {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Drop for InputReplay {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{env, fs};
+
+  use winit::keyboard::KeyCode;
+
+  use super::*;
+  use crate::engine::input::InputState;
+
+  #[test]
+  fn snapshot_restores_what_is_held() {
+    let mut input = Input::new();
+    input.press_key(KeyCode::KeyW);
+    input.press_button(MouseButton::Left);
+    input.set_cursor_in_window(true);
+    input.set_mouse_position(vec2(0.5, -0.5));
+    input.update(0.016);
+
+    let mut fresh = Input::new();
+    input.snapshot().iter().for_each(|event| event.apply(&mut fresh));
+    fresh.update(0.016);
+
+    assert!(fresh.key_pressed(KeyCode::KeyW));
+    assert_eq!(fresh.mouse_state(MouseButton::Left), InputState::PressedThisFrame);
+    assert!(fresh.cursor_in_window());
+    assert_eq!(fresh.mouse_position(), vec2(0.5, -0.5));
+  }
+
+  #[test]
+  fn frames_have_to_be_in_order() {
+    let path = env::temp_dir().join(format!("algurulgar-record-{}.ron", std::process::id()));
+    fs::write(
+      &path,
+      "(version: 1)\n(frame: 0, delta_time: 0.016, events: [])\n(frame: 2, delta_time: 0.016, events: [])\n",
+    )
+    .unwrap();
+
+    let result = InputPlayer::open(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+      result,
+      Err(Error::FrameOrder {
+        line: 3,
+        expected: 1,
+        found: 2,
+        ..
+      })
+    ));
+  }
+}
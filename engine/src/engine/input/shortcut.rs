@@ -0,0 +1,623 @@
+// keyboard shortcuts made of modifiers and a key, optionally a sequence of
+// them like ctrl+k ctrl+c
+// a registry matches them against the keys pressed each frame and reports
+// the ones egui already uses
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use winit::keyboard::{KeyCode, ModifiersState, NamedKey};
+
+use crate::engine::input::{Input, Key, KeyPress, LogicalKey};
+
+/// Control everywhere but macos where it is command
+pub const COMMAND: ModifiersState = if cfg!(target_os = "macos") {
+  ModifiersState::SUPER
+} else {
+  ModifiersState::CONTROL
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+  #[error("empty shortcut")]
+  Empty,
+  #[error("unknown key {0:?}")]
+  UnknownKey(String),
+  #[error("unknown modifier {0:?}")]
+  UnknownModifier(String),
+}
+
+/// Modifiers held while a key is pressed, the modifiers have to match exactly
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Chord {
+  pub modifiers: ModifiersState,
+  pub key: Key,
+}
+
+impl Chord {
+  pub fn new(modifiers: ModifiersState, key: impl Into<Key>) -> Self {
+    Self {
+      modifiers,
+      key: key.into(),
+    }
+  }
+
+  pub fn matches(&self, press: &KeyPress) -> bool {
+    self.modifiers == press.modifiers && press.is(self.key)
+  }
+}
+
+impl Display for Chord {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let names = [
+      (ModifiersState::CONTROL, "Ctrl"),
+      (ModifiersState::SHIFT, "Shift"),
+      (ModifiersState::ALT, "Alt"),
+      (ModifiersState::SUPER, "Super"),
+    ];
+
+    for (modifier, name) in names {
+      if self.modifiers.contains(modifier) {
+        write!(f, "{name}+")?;
+      }
+    }
+
+    write!(f, "{}", self.key)
+  }
+}
+
+/// `Ctrl+Shift+S` style, modifiers are `Ctrl`, `Shift`, `Alt`, `Super` and
+/// `Cmd` for [`COMMAND`]. A single character is a logical key, anything else
+/// a [`KeyCode`] like `KeyS` or `F5` or else a [`NamedKey`] like `Escape`
+impl FromStr for Chord {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut parts = s.rsplit('+');
+    let key = parts.next().filter(|key| !key.is_empty()).ok_or(Error::Empty)?;
+    let mut modifiers = ModifiersState::empty();
+
+    for modifier in parts {
+      modifiers |= match modifier.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => ModifiersState::CONTROL,
+        "shift" => ModifiersState::SHIFT,
+        "alt" | "option" => ModifiersState::ALT,
+        "super" | "meta" | "win" => ModifiersState::SUPER,
+        "cmd" | "command" => COMMAND,
+        _ => return Err(Error::UnknownModifier(modifier.to_string())),
+      };
+    }
+
+    let mut chars = key.chars();
+
+    let key = match (chars.next(), chars.next()) {
+      (Some(character), None) => Key::from(character),
+      _ => match (ron::from_str::<KeyCode>(key), ron::from_str::<NamedKey>(key)) {
+        (Ok(code), _) => Key::from(code),
+        (_, Ok(named)) => Key::from(named),
+        _ => return Err(Error::UnknownKey(key.to_string())),
+      },
+    };
+
+    Ok(Self { modifiers, key })
+  }
+}
+
+/// One or more chords pressed one after another
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Shortcut {
+  chords: Vec<Chord>,
+}
+
+impl Shortcut {
+  pub fn new(modifiers: ModifiersState, key: impl Into<Key>) -> Self {
+    Self {
+      chords: vec![Chord::new(modifiers, key)],
+    }
+  }
+
+  /// Continue the sequence with another chord
+  pub fn then(mut self, modifiers: ModifiersState, key: impl Into<Key>) -> Self {
+    self.chords.push(Chord::new(modifiers, key));
+    self
+  }
+
+  #[inline]
+  pub fn chords(&self) -> &[Chord] {
+    &self.chords
+  }
+
+  fn starts_with(&self, chords: &[Chord]) -> bool {
+    self.chords.starts_with(chords)
+  }
+}
+
+impl From<Chord> for Shortcut {
+  fn from(chord: Chord) -> Self {
+    Self { chords: vec![chord] }
+  }
+}
+
+impl Display for Shortcut {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    for (i, chord) in self.chords.iter().enumerate() {
+      if i > 0 {
+        write!(f, " ")?;
+      }
+
+      write!(f, "{chord}")?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Chords separated by whitespace, `Ctrl+K Ctrl+C`
+impl FromStr for Shortcut {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let chords = s
+      .split_whitespace()
+      .map(str::parse)
+      .collect::<Result<Vec<Chord>, _>>()?;
+
+    if chords.is_empty() {
+      return Err(Error::Empty);
+    }
+
+    Ok(Self { chords })
+  }
+}
+
+/// A shortcut egui handles itself
+#[derive(Copy, Clone, Debug)]
+pub struct EguiShortcut {
+  pub name: &'static str,
+  pub modifiers: ModifiersState,
+  pub key: KeyCode,
+  pub character: char,
+}
+
+impl EguiShortcut {
+  const fn new(name: &'static str, modifiers: ModifiersState, key: KeyCode, character: char) -> Self {
+    Self {
+      name,
+      modifiers,
+      key,
+      character,
+    }
+  }
+
+  pub fn matches(&self, chord: &Chord) -> bool {
+    self.modifiers == chord.modifiers
+      && (chord.key == Key::from(self.key) || chord.key == Key::from(LogicalKey::character(self.character)))
+  }
+}
+
+/// Text editing and zoom shortcuts egui reacts to
+pub const EGUI_SHORTCUTS: &[EguiShortcut] = &[
+  EguiShortcut::new("select all", COMMAND, KeyCode::KeyA, 'a'),
+  EguiShortcut::new("copy", COMMAND, KeyCode::KeyC, 'c'),
+  EguiShortcut::new("cut", COMMAND, KeyCode::KeyX, 'x'),
+  EguiShortcut::new("paste", COMMAND, KeyCode::KeyV, 'v'),
+  EguiShortcut::new("undo", COMMAND, KeyCode::KeyZ, 'z'),
+  EguiShortcut::new("redo", COMMAND, KeyCode::KeyY, 'y'),
+  EguiShortcut::new("redo", COMMAND.union(ModifiersState::SHIFT), KeyCode::KeyZ, 'z'),
+  EguiShortcut::new("zoom in", COMMAND, KeyCode::Equal, '='),
+  EguiShortcut::new("zoom in", COMMAND.union(ModifiersState::SHIFT), KeyCode::Equal, '+'),
+  EguiShortcut::new("zoom out", COMMAND, KeyCode::Minus, '-'),
+  EguiShortcut::new("reset zoom", COMMAND, KeyCode::Digit0, '0'),
+];
+
+/// Two shortcuts that can't both work
+#[derive(Clone, Debug)]
+pub enum Conflict {
+  /// The same sequence or one that starts the other, the shorter one wins
+  /// and the first registered one among equal ones
+  Shortcut { name: String, other: String },
+  /// The first chord is already used by egui
+  Egui { name: String, egui: EguiShortcut },
+}
+
+impl Display for Conflict {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Conflict::Shortcut { name, other } => write!(f, "shortcut {name:?} conflicts with {other:?}"),
+      Conflict::Egui { name, egui } => write!(f, "shortcut {name:?} is also egui's {}", egui.name),
+    }
+  }
+}
+
+struct Entry {
+  name: String,
+  shortcut: Shortcut,
+  callback: Option<Box<dyn FnMut()>>,
+}
+
+/// Named shortcuts matched against the keys pressed each frame
+///
+/// Triggered shortcuts run their callback and can be polled with
+/// [`Shortcuts::triggered`] for the rest of the frame
+#[derive(Default)]
+pub struct Shortcuts {
+  entries: Vec<Entry>,
+  /// Chords of a sequence pressed so far
+  progress: Vec<Chord>,
+  triggered: Vec<String>,
+}
+
+impl Shortcuts {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add or replace the shortcut called `name`, conflicts are logged and
+  /// returned
+  pub fn register(&mut self, name: impl Into<String>, shortcut: impl Into<Shortcut>) -> Vec<Conflict> {
+    self.insert(name.into(), shortcut.into(), None)
+  }
+
+  /// Like [`Shortcuts::register`] but also runs `callback` when triggered
+  pub fn register_with(
+    &mut self,
+    name: impl Into<String>,
+    shortcut: impl Into<Shortcut>,
+    callback: impl FnMut() + 'static,
+  ) -> Vec<Conflict> {
+    self.insert(name.into(), shortcut.into(), Some(Box::new(callback)))
+  }
+
+  fn insert(&mut self, name: String, shortcut: Shortcut, callback: Option<Box<dyn FnMut()>>) -> Vec<Conflict> {
+    self.unregister(&name);
+
+    let conflicts = self.conflicts_of(&name, &shortcut);
+
+    for conflict in &conflicts {
+      warn!("{conflict}");
+    }
+
+    self.entries.push(Entry {
+      name,
+      shortcut,
+      callback,
+    });
+
+    conflicts
+  }
+
+  pub fn unregister(&mut self, name: &str) {
+    self.entries.retain(|entry| entry.name != name);
+    self.progress.clear();
+  }
+
+  pub fn shortcut(&self, name: &str) -> Option<&Shortcut> {
+    self
+      .entries
+      .iter()
+      .find(|entry| entry.name == name)
+      .map(|entry| &entry.shortcut)
+  }
+
+  pub fn shortcuts(&self) -> impl Iterator<Item = (&str, &Shortcut)> {
+    self.entries.iter().map(|entry| (entry.name.as_str(), &entry.shortcut))
+  }
+
+  /// Every conflict between the registered shortcuts and with egui
+  pub fn conflicts(&self) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+
+    for (i, entry) in self.entries.iter().enumerate() {
+      conflicts.extend(egui_conflicts(&entry.name, &entry.shortcut));
+
+      for other in &self.entries[..i] {
+        if overlaps(&entry.shortcut, &other.shortcut) {
+          conflicts.push(Conflict::Shortcut {
+            name: entry.name.clone(),
+            other: other.name.clone(),
+          });
+        }
+      }
+    }
+
+    conflicts
+  }
+
+  fn conflicts_of(&self, name: &str, shortcut: &Shortcut) -> Vec<Conflict> {
+    let mut conflicts = egui_conflicts(name, shortcut);
+
+    conflicts.extend(
+      self
+        .entries
+        .iter()
+        .filter(|entry| overlaps(shortcut, &entry.shortcut))
+        .map(|entry| Conflict::Shortcut {
+          name: name.to_string(),
+          other: entry.name.clone(),
+        }),
+    );
+
+    conflicts
+  }
+
+  /// True for the frame `name` was triggered in
+  pub fn triggered(&self, name: &str) -> bool {
+    self.triggered.iter().any(|triggered| triggered == name)
+  }
+
+  /// Shortcuts triggered this frame in order
+  pub fn triggered_this_frame(&self) -> impl Iterator<Item = &str> {
+    self.triggered.iter().map(String::as_str)
+  }
+
+  /// True while the start of a sequence has been pressed
+  #[inline]
+  pub fn is_pending(&self) -> bool {
+    !self.progress.is_empty()
+  }
+
+  /// Match this frame's key presses, called once per frame after input state
+  /// is updated
+  pub(crate) fn update(&mut self, input: &Input) {
+    self.triggered.clear();
+
+    for press in input.key_presses() {
+      if !press.is_modifier() {
+        self.press(press);
+      }
+    }
+  }
+
+  fn press(&mut self, press: &KeyPress) {
+    // a chord that doesn't continue the sequence may still start a new one
+    let restart = !self.progress.is_empty();
+
+    if self.advance(press) || !restart {
+      return;
+    }
+
+    self.progress.clear();
+    self.advance(press);
+  }
+
+  fn advance(&mut self, press: &KeyPress) -> bool {
+    let depth = self.progress.len();
+
+    let Some(chord) = self
+      .entries
+      .iter()
+      .filter(|entry| entry.shortcut.starts_with(&self.progress))
+      .filter_map(|entry| entry.shortcut.chords.get(depth))
+      .find(|chord| chord.matches(press))
+      .copied()
+    else {
+      return false;
+    };
+
+    self.progress.push(chord);
+
+    let progress = &self.progress;
+
+    if let Some(entry) = self.entries.iter_mut().find(|entry| entry.shortcut.chords == *progress) {
+      if let Some(callback) = &mut entry.callback {
+        callback();
+      }
+
+      self.triggered.push(entry.name.clone());
+      self.progress.clear();
+    }
+
+    true
+  }
+}
+
+fn egui_conflicts(name: &str, shortcut: &Shortcut) -> Vec<Conflict> {
+  let Some(first) = shortcut.chords.first() else {
+    return Vec::new();
+  };
+
+  EGUI_SHORTCUTS
+    .iter()
+    .filter(|egui| egui.matches(first))
+    .map(|egui| Conflict::Egui {
+      name: name.to_string(),
+      egui: *egui,
+    })
+    .collect()
+}
+
+/// Equal or one is the start of the other, so the longer one can never be
+/// pressed
+fn overlaps(a: &Shortcut, b: &Shortcut) -> bool {
+  a.starts_with(&b.chords) || b.starts_with(&a.chords)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::cell::Cell;
+  use std::rc::Rc;
+
+  use winit::keyboard::PhysicalKey;
+
+  use super::*;
+
+  const CTRL: ModifiersState = ModifiersState::CONTROL;
+  const NONE: ModifiersState = ModifiersState::empty();
+
+  fn press(modifiers: ModifiersState, code: KeyCode, character: char) -> KeyPress {
+    KeyPress {
+      physical: Some(PhysicalKey::Code(code)),
+      logical: Some(LogicalKey::character(character)),
+      modifiers,
+    }
+  }
+
+  fn triggered(shortcuts: &mut Shortcuts) -> Vec<String> {
+    std::mem::take(&mut shortcuts.triggered)
+  }
+
+  #[test]
+  fn parse_chord() {
+    let chord = |s: &str| s.parse::<Chord>().unwrap();
+
+    assert_eq!(chord("Ctrl+Shift+S"), Chord::new(CTRL | ModifiersState::SHIFT, 's'));
+    assert_eq!(chord("ctrl+KeyS"), Chord::new(CTRL, KeyCode::KeyS));
+    assert_eq!(chord("alt+F5"), Chord::new(ModifiersState::ALT, KeyCode::F5));
+    // key codes win over named keys with the same name
+    assert_eq!(chord("Escape"), Chord::new(NONE, KeyCode::Escape));
+    assert_eq!(chord("ZoomIn"), Chord::new(NONE, NamedKey::ZoomIn));
+    assert_eq!(chord("Cmd+z"), Chord::new(COMMAND, 'z'));
+    assert_eq!(chord("Meta+Win+a"), Chord::new(ModifiersState::SUPER, 'a'));
+
+    assert!(matches!("".parse::<Chord>(), Err(Error::Empty)));
+    assert!(matches!("Ctrl+".parse::<Chord>(), Err(Error::Empty)));
+    assert!(matches!("Hyper+a".parse::<Chord>(), Err(Error::UnknownModifier(m)) if m == "Hyper"));
+    assert!(matches!("Ctrl+Nope".parse::<Chord>(), Err(Error::UnknownKey(k)) if k == "Nope"));
+  }
+
+  #[test]
+  fn parse_shortcut() {
+    let shortcut = "Ctrl+K  Ctrl+c".parse::<Shortcut>().unwrap();
+
+    assert_eq!(shortcut, Shortcut::new(CTRL, 'k').then(CTRL, 'c'));
+    assert_eq!(shortcut.to_string(), "Ctrl+k Ctrl+c");
+    assert_eq!(shortcut.to_string().parse::<Shortcut>().unwrap(), shortcut);
+
+    let physical = Shortcut::new(CTRL | ModifiersState::ALT, KeyCode::Digit1);
+    assert_eq!(physical.to_string().parse::<Shortcut>().unwrap(), physical);
+
+    assert!(matches!(" ".parse::<Shortcut>(), Err(Error::Empty)));
+    assert!(matches!(
+      "Ctrl+K Bogus+c".parse::<Shortcut>(),
+      Err(Error::UnknownModifier(_))
+    ));
+  }
+
+  #[test]
+  fn sequence() {
+    let mut shortcuts = Shortcuts::new();
+    shortcuts.register("comment", Shortcut::new(CTRL, 'k').then(CTRL, 'c'));
+
+    shortcuts.press(&press(CTRL, KeyCode::KeyK, 'k'));
+    assert!(shortcuts.is_pending());
+    assert!(triggered(&mut shortcuts).is_empty());
+
+    shortcuts.press(&press(CTRL, KeyCode::KeyC, 'c'));
+    assert!(!shortcuts.is_pending());
+    assert_eq!(triggered(&mut shortcuts), ["comment"]);
+  }
+
+  #[test]
+  fn broken_sequence_restarts() {
+    let mut shortcuts = Shortcuts::new();
+    shortcuts.register("comment", Shortcut::new(CTRL, 'k').then(CTRL, 'c'));
+    shortcuts.register("save", Shortcut::new(CTRL, 's'));
+
+    // a chord that doesn't continue the sequence starts its own
+    shortcuts.press(&press(CTRL, KeyCode::KeyK, 'k'));
+    shortcuts.press(&press(CTRL, KeyCode::KeyS, 's'));
+    assert!(!shortcuts.is_pending());
+    assert_eq!(triggered(&mut shortcuts), ["save"]);
+
+    // or the same sequence again
+    shortcuts.press(&press(CTRL, KeyCode::KeyK, 'k'));
+    shortcuts.press(&press(CTRL, KeyCode::KeyK, 'k'));
+    assert!(shortcuts.is_pending());
+    shortcuts.press(&press(CTRL, KeyCode::KeyC, 'c'));
+    assert_eq!(triggered(&mut shortcuts), ["comment"]);
+
+    // anything else drops it
+    shortcuts.press(&press(CTRL, KeyCode::KeyK, 'k'));
+    shortcuts.press(&press(NONE, KeyCode::KeyX, 'x'));
+    assert!(!shortcuts.is_pending());
+    shortcuts.press(&press(CTRL, KeyCode::KeyC, 'c'));
+    assert!(triggered(&mut shortcuts).is_empty());
+  }
+
+  #[test]
+  fn modifiers_match_exactly() {
+    let mut shortcuts = Shortcuts::new();
+    shortcuts.register("save", Shortcut::new(CTRL, 's'));
+
+    shortcuts.press(&press(CTRL | ModifiersState::SHIFT, KeyCode::KeyS, 's'));
+    shortcuts.press(&press(NONE, KeyCode::KeyS, 's'));
+    assert!(triggered(&mut shortcuts).is_empty());
+
+    // logical keys follow the layout, s is on the physical o key in dvorak
+    shortcuts.press(&press(CTRL, KeyCode::KeyO, 's'));
+    assert_eq!(triggered(&mut shortcuts), ["save"]);
+  }
+
+  #[test]
+  fn callbacks_run() {
+    let count = Rc::new(Cell::new(0));
+    let counter = count.clone();
+
+    let mut shortcuts = Shortcuts::new();
+    shortcuts.register_with("count", Shortcut::new(NONE, KeyCode::F5), move || {
+      counter.set(counter.get() + 1)
+    });
+
+    shortcuts.press(&press(NONE, KeyCode::F5, 'x'));
+    shortcuts.press(&press(NONE, KeyCode::F5, 'x'));
+    assert_eq!(count.get(), 2);
+  }
+
+  #[test]
+  fn overlapping_shortcuts() {
+    let k = Shortcut::new(CTRL, 'k');
+    let kc = Shortcut::new(CTRL, 'k').then(CTRL, 'c');
+
+    assert!(overlaps(&k, &k));
+    assert!(overlaps(&k, &kc));
+    assert!(overlaps(&kc, &k));
+    assert!(!overlaps(&kc, &Shortcut::new(CTRL, 'k').then(CTRL, 'd')));
+    assert!(!overlaps(&k, &Shortcut::new(CTRL | ModifiersState::SHIFT, 'k')));
+    // a logical and a physical key are different chords
+    assert!(!overlaps(&k, &Shortcut::new(CTRL, KeyCode::KeyK)));
+
+    let mut shortcuts = Shortcuts::new();
+    assert!(shortcuts.register("open", k.clone()).is_empty());
+
+    let conflicts = shortcuts.register("comment", kc);
+    assert!(matches!(
+      &conflicts[..],
+      [Conflict::Shortcut { name, other }] if name == "comment" && other == "open"
+    ));
+    assert_eq!(shortcuts.conflicts().len(), 1);
+
+    // registering again under the same name replaces it
+    assert!(shortcuts.register("open", Shortcut::new(CTRL, 'o')).is_empty());
+    assert!(shortcuts.conflicts().is_empty());
+    assert_eq!(shortcuts.shortcut("open"), Some(&Shortcut::new(CTRL, 'o')));
+  }
+
+  #[test]
+  fn egui_shortcut_conflicts() {
+    let egui = |shortcut: Shortcut| {
+      let mut shortcuts = Shortcuts::new();
+      let conflicts = shortcuts.register("mine", shortcut);
+
+      assert_eq!(conflicts.len(), shortcuts.conflicts().len());
+
+      conflicts
+        .into_iter()
+        .map(|conflict| match conflict {
+          Conflict::Egui { egui, .. } => egui.name,
+          conflict => panic!("{conflict}"),
+        })
+        .collect::<Vec<_>>()
+    };
+
+    // by physical or logical key
+    assert_eq!(egui(Shortcut::new(COMMAND, KeyCode::KeyC)), ["copy"]);
+    assert_eq!(egui(Shortcut::new(COMMAND, 'v')), ["paste"]);
+    assert_eq!(egui(Shortcut::new(COMMAND | ModifiersState::SHIFT, 'z')), ["redo"]);
+
+    // other modifiers or only later in a sequence are fine
+    assert!(egui(Shortcut::new(COMMAND | ModifiersState::ALT, 'c')).is_empty());
+    assert!(egui(Shortcut::new(NONE, 'c')).is_empty());
+    assert!(egui(Shortcut::new(COMMAND, 'k').then(COMMAND, 'c')).is_empty());
+  }
+}
@@ -0,0 +1,126 @@
+// typed text and ime composition
+// both are queued while events arrive and handed to layers on the next frame
+
+use std::mem;
+
+use serde::{Deserialize, Serialize};
+use winit::event::Ime;
+
+use crate::engine::input::{Input, RawInput};
+
+/// Composition state of the input method
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImeEvent {
+  Enabled,
+  /// Text being composed, replaces the previous preedit. `cursor` is a byte
+  /// range into `text`, `None` hides the cursor
+  Preedit {
+    text: String,
+    cursor: Option<(usize, usize)>,
+  },
+  /// Finished text, also added to [`typed_text`]
+  Commit(String),
+  Disabled,
+}
+
+impl From<Ime> for ImeEvent {
+  fn from(ime: Ime) -> Self {
+    match ime {
+      Ime::Enabled => ImeEvent::Enabled,
+      Ime::Preedit(text, cursor) => ImeEvent::Preedit { text, cursor },
+      Ime::Commit(text) => ImeEvent::Commit(text),
+      Ime::Disabled => ImeEvent::Disabled,
+    }
+  }
+}
+
+/// Text that is being composed and not committed yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preedit {
+  pub text: String,
+  pub cursor: Option<(usize, usize)>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct TextState {
+  pending_text: String,
+  text: String,
+  pending_ime: Vec<ImeEvent>,
+  ime: Vec<ImeEvent>,
+  preedit: Option<Preedit>,
+}
+
+impl Input {
+  /// Queue typed text for the next frame, control characters are dropped
+  /// since they are better read as keys
+  pub fn push_text(&mut self, text: &str) {
+    self.text.pending_text.extend(text.chars().filter(|c| !c.is_control()));
+  }
+
+  /// Queue an ime event for the next frame, committed text is also queued as
+  /// typed text
+  pub fn push_ime_event(&mut self, event: ImeEvent) {
+    let state = &mut self.text;
+
+    match &event {
+      ImeEvent::Preedit { text, cursor } if !text.is_empty() => {
+        state.preedit = Some(Preedit {
+          text: text.clone(),
+          cursor: *cursor,
+        });
+      }
+      ImeEvent::Preedit { .. } | ImeEvent::Disabled => state.preedit = None,
+      ImeEvent::Commit(text) => {
+        state.preedit = None;
+        state.pending_text.push_str(text);
+      }
+      ImeEvent::Enabled => (),
+    }
+
+    state.pending_ime.push(event);
+  }
+
+  /// Text typed since the previous frame, respects the keyboard layout, dead
+  /// keys and ime commits
+  #[inline]
+  pub fn typed_text(&self) -> &str {
+    if self.keyboard_blocked() {
+      return "";
+    }
+
+    self.raw().typed_text()
+  }
+
+  /// Ime events since the previous frame, in order
+  #[inline]
+  pub fn ime_events(&self) -> &[ImeEvent] {
+    if self.keyboard_blocked() {
+      return &[];
+    }
+
+    self.raw().ime_events()
+  }
+
+  /// Current composition, shown in place of the cursor by text fields
+  #[inline]
+  pub fn ime_preedit(&self) -> Option<&Preedit> {
+    self.text.preedit.as_ref()
+  }
+
+  pub(super) fn update_text_state(&mut self) {
+    let state = &mut self.text;
+
+    state.text = mem::take(&mut state.pending_text);
+    state.ime = mem::take(&mut state.pending_ime);
+  }
+}
+
+impl<'a> RawInput<'a> {
+  pub fn typed_text(self) -> &'a str {
+    &self.input.text.text
+  }
+
+  pub fn ime_events(self) -> &'a [ImeEvent] {
+    &self.input.text.ime
+  }
+}
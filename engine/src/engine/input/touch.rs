@@ -0,0 +1,604 @@
+// touch points and the gestures recognized from them
+// touch events are queued and replayed in order at the start of a frame, so
+// gestures only depend on the events and the frame's delta_time
+
+use std::collections::BTreeMap;
+use std::f32::consts::{PI, TAU};
+use std::mem;
+use std::time::Duration;
+
+use winit::event::TouchPhase;
+
+use crate::engine::input::{Input, RawInput};
+use crate::math::{Position, Vec2};
+
+/// Pixels a finger can move and still tap or long press
+pub const TAP_SLOP: f32 = 10.0;
+
+/// How long a finger has to rest before it is a long press
+pub const LONG_PRESS_DURATION: Duration = Duration::from_millis(500);
+
+/// Pixels a finger has to move for a swipe
+pub const SWIPE_MIN_DISTANCE: f32 = 50.0;
+
+/// Longest a swipe can take
+pub const SWIPE_MAX_DURATION: Duration = Duration::from_millis(500);
+
+/// One finger on the screen
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TouchPoint {
+  pub id: u64,
+  /// `Ended` and `Cancelled` points are listed for the frame they ended in
+  pub phase: TouchPhase,
+  /// Normalized to [-1, 1] with y pointing down like
+  /// [`Input::mouse_position`], use
+  /// [`OrthoCamera::screen_to_world`](crate::render::camera::ortho::OrthoCamera::screen_to_world)
+  /// for world coordinates
+  pub position: Position,
+  /// Physical pixels from the top left of the window
+  pub pixel_position: Position,
+  /// Physical pixels where the finger went down
+  pub start_position: Position,
+  started: Duration,
+  // moved past the tap slop, or shared the screen with another finger
+  moved: bool,
+  multi: bool,
+  long_pressed: bool,
+}
+
+/// Recognized from the touch points, or sent by a trackpad for pinch and
+/// rotate
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gesture {
+  /// A finger went down and up without moving
+  Tap {
+    /// Physical pixels from the top left of the window
+    position: Position,
+  },
+  /// A finger rests for [`LONG_PRESS_DURATION`] without moving, sent once
+  LongPress {
+    /// Physical pixels from the top left of the window
+    position: Position,
+  },
+  /// A finger moved quickly and let go
+  Swipe {
+    /// Physical pixels from the top left of the window
+    start: Position,
+    /// Physical pixels from `start` to where the finger let go
+    delta: Vec2,
+  },
+  /// Distance between two fingers changed by `scale` since the previous
+  /// frame
+  Pinch {
+    /// Physical pixels from the top left of the window, between the fingers.
+    /// A trackpad pinch uses every finger on the screen, or the origin
+    center: Position,
+    /// Ratio to the previous distance, above 1 when they move apart
+    scale: f32,
+  },
+  /// Two fingers turned since the previous frame
+  Rotate {
+    /// Physical pixels from the top left of the window, between the fingers.
+    /// A trackpad rotation uses every finger on the screen, or the origin
+    center: Position,
+    /// Radians, clockwise on screen is positive
+    angle: f32,
+  },
+}
+
+/// Queued like the window sends it
+#[derive(Copy, Clone)]
+struct TouchEvent {
+  id: u64,
+  phase: TouchPhase,
+  pixel_position: Position,
+  position: Position,
+}
+
+#[derive(Copy, Clone)]
+struct TwoFingers {
+  ids: (u64, u64),
+  distance: f32,
+  angle: f32,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct TouchState {
+  points: BTreeMap<u64, TouchPoint>,
+  pending: Vec<TouchEvent>,
+  pending_pinch: f32,
+  pending_rotation: f32,
+  gestures: Vec<Gesture>,
+  two_fingers: Option<TwoFingers>,
+  clock: Duration,
+}
+
+impl Input {
+  /// Queue a touch event for the next frame, `pixel_position` in physical
+  /// pixels and `position` normalized like [`Input::set_mouse_position`]
+  pub fn set_touch(&mut self, id: u64, phase: TouchPhase, pixel_position: Position, position: Position) {
+    self.touch.pending.push(TouchEvent {
+      id,
+      phase,
+      pixel_position,
+      position,
+    });
+  }
+
+  /// Trackpad pinch, positive `delta` zooms in
+  pub fn add_pinch_gesture(&mut self, delta: f32) {
+    self.touch.pending_pinch += delta;
+  }
+
+  /// Trackpad rotation in radians, clockwise is positive
+  pub fn add_rotation_gesture(&mut self, angle: f32) {
+    self.touch.pending_rotation += angle;
+  }
+
+  /// Fingers on the screen ordered by id, including ones that ended this
+  /// frame
+  pub fn touches(&self) -> impl Iterator<Item = &TouchPoint> {
+    let blocked = self.pointer_blocked();
+
+    self.raw().touches().filter(move |_| !blocked)
+  }
+
+  pub fn touch(&self, id: u64) -> Option<&TouchPoint> {
+    self.touches().find(|touch| touch.id == id)
+  }
+
+  /// Gestures recognized since the previous frame in order
+  pub fn gestures(&self) -> &[Gesture] {
+    if self.pointer_blocked() {
+      return &[];
+    }
+
+    self.raw().gestures()
+  }
+
+  /// Every pinch this frame combined, 1 without one
+  pub fn pinch_scale(&self) -> f32 {
+    self
+      .gestures()
+      .iter()
+      .filter_map(|gesture| match gesture {
+        Gesture::Pinch { scale, .. } => Some(*scale),
+        _ => None,
+      })
+      .product()
+  }
+
+  /// Every rotation this frame combined in radians
+  pub fn rotation(&self) -> f32 {
+    self
+      .gestures()
+      .iter()
+      .filter_map(|gesture| match gesture {
+        Gesture::Rotate { angle, .. } => Some(*angle),
+        _ => None,
+      })
+      .sum()
+  }
+
+  pub(super) fn cancel_touches(&mut self) {
+    let active: Vec<_> = self
+      .touch
+      .points
+      .values()
+      .filter(|point| matches!(point.phase, TouchPhase::Started | TouchPhase::Moved))
+      .map(|point| (point.id, point.pixel_position, point.position))
+      .collect();
+
+    for (id, pixel_position, position) in active {
+      self.set_touch(id, TouchPhase::Cancelled, pixel_position, position);
+    }
+  }
+
+  pub(super) fn update_touch_state(&mut self, delta_time: f32) {
+    let touch = &mut self.touch;
+
+    touch.clock += Duration::from_secs_f32(delta_time.max(0.0));
+    touch.gestures.clear();
+    touch
+      .points
+      .retain(|_, point| matches!(point.phase, TouchPhase::Started | TouchPhase::Moved));
+
+    for event in mem::take(&mut touch.pending) {
+      touch.apply(event);
+    }
+
+    touch.recognize_long_press();
+    touch.recognize_two_fingers();
+
+    let center = touch.center();
+    let pinch = mem::take(&mut touch.pending_pinch);
+    let rotation = mem::take(&mut touch.pending_rotation);
+
+    if pinch != 0.0 {
+      touch.gestures.push(Gesture::Pinch {
+        center,
+        scale: (1.0 + pinch).max(f32::EPSILON),
+      });
+    }
+
+    if rotation != 0.0 {
+      touch.gestures.push(Gesture::Rotate {
+        center,
+        angle: rotation,
+      });
+    }
+  }
+}
+
+impl<'a> RawInput<'a> {
+  pub fn touches(self) -> impl Iterator<Item = &'a TouchPoint> {
+    self.input.touch.points.values()
+  }
+
+  pub fn gestures(self) -> &'a [Gesture] {
+    &self.input.touch.gestures
+  }
+}
+
+impl TouchState {
+  fn apply(&mut self, event: TouchEvent) {
+    match event.phase {
+      TouchPhase::Started => {
+        let mut multi = false;
+
+        for point in self.points.values_mut() {
+          if matches!(point.phase, TouchPhase::Started | TouchPhase::Moved) {
+            point.multi = true;
+            multi = true;
+          }
+        }
+
+        self.points.insert(
+          event.id,
+          TouchPoint {
+            id: event.id,
+            phase: TouchPhase::Started,
+            position: event.position,
+            pixel_position: event.pixel_position,
+            start_position: event.pixel_position,
+            started: self.clock,
+            moved: false,
+            multi,
+            long_pressed: false,
+          },
+        );
+      }
+      TouchPhase::Moved => {
+        if let Some(point) = self.points.get_mut(&event.id) {
+          point.phase = TouchPhase::Moved;
+          point.position = event.position;
+          point.pixel_position = event.pixel_position;
+          point.moved |= (event.pixel_position - point.start_position).norm() > TAP_SLOP;
+        }
+      }
+      TouchPhase::Ended => {
+        if let Some(point) = self.points.get_mut(&event.id) {
+          point.phase = TouchPhase::Ended;
+          point.position = event.position;
+          point.pixel_position = event.pixel_position;
+
+          let point = *point;
+          self.recognize_release(&point);
+        }
+      }
+      TouchPhase::Cancelled => {
+        if let Some(point) = self.points.get_mut(&event.id) {
+          point.phase = TouchPhase::Cancelled;
+        }
+      }
+    }
+  }
+
+  fn recognize_release(&mut self, point: &TouchPoint) {
+    if point.multi || point.long_pressed {
+      return;
+    }
+
+    let delta = point.pixel_position - point.start_position;
+    let duration = self.clock.saturating_sub(point.started);
+
+    if delta.norm() <= TAP_SLOP {
+      self.gestures.push(Gesture::Tap {
+        position: point.pixel_position,
+      });
+    } else if delta.norm() >= SWIPE_MIN_DISTANCE && duration <= SWIPE_MAX_DURATION {
+      self.gestures.push(Gesture::Swipe {
+        start: point.start_position,
+        delta,
+      });
+    }
+  }
+
+  fn recognize_long_press(&mut self) {
+    let clock = self.clock;
+
+    for point in self.points.values_mut() {
+      let resting = !point.moved && !point.multi && !point.long_pressed && point.phase != TouchPhase::Cancelled;
+
+      if resting && clock.saturating_sub(point.started) >= LONG_PRESS_DURATION {
+        point.long_pressed = true;
+        self.gestures.push(Gesture::LongPress {
+          position: point.pixel_position,
+        });
+      }
+    }
+  }
+
+  // compares the two fingers to the previous frame, starts over whenever
+  // another pair of fingers is down
+  fn recognize_two_fingers(&mut self) {
+    let mut active = self
+      .points
+      .values()
+      .filter(|point| matches!(point.phase, TouchPhase::Started | TouchPhase::Moved));
+
+    let (Some(a), Some(b), None) = (active.next(), active.next(), active.next()) else {
+      self.two_fingers = None;
+      return;
+    };
+
+    let offset = b.pixel_position - a.pixel_position;
+    let center = (a.pixel_position + b.pixel_position) / 2.0;
+
+    let current = TwoFingers {
+      ids: (a.id, b.id),
+      distance: offset.norm(),
+      angle: offset.y.atan2(offset.x),
+    };
+
+    if let Some(previous) = self.two_fingers.filter(|previous| previous.ids == current.ids) {
+      if previous.distance > 0.0 && current.distance != previous.distance {
+        self.gestures.push(Gesture::Pinch {
+          center,
+          scale: current.distance / previous.distance,
+        });
+      }
+
+      // wrapped so crossing the -pi/pi boundary doesn't jump a full turn
+      let angle = (current.angle - previous.angle + PI).rem_euclid(TAU) - PI;
+
+      if angle != 0.0 {
+        self.gestures.push(Gesture::Rotate { center, angle });
+      }
+    }
+
+    self.two_fingers = Some(current);
+  }
+
+  // between every finger on the screen, the origin without one
+  fn center(&self) -> Position {
+    let count = self.points.len();
+
+    if count == 0 {
+      return Position::zeros();
+    }
+
+    self.points.values().map(|point| point.pixel_position).sum::<Position>() / count as f32
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::f32::consts::FRAC_PI_2;
+
+  use super::*;
+  use crate::engine::input::record::InputEvent;
+  use crate::math::vec2;
+
+  const DT: f32 = 1.0 / 60.0;
+  const SIZE: Vec2 = vec2(800.0, 600.0);
+
+  fn touch(input: &mut Input, id: u64, phase: TouchPhase, x: f32, y: f32) {
+    input.inject_touch(id, phase, vec2(x, y), SIZE);
+  }
+
+  #[test]
+  fn tap_within_the_slop() {
+    let mut input = Input::new();
+
+    touch(&mut input, 0, TouchPhase::Started, 400.0, 300.0);
+    input.update(DT);
+
+    let point = *input.touch(0).unwrap();
+    assert_eq!(point.phase, TouchPhase::Started);
+    assert_eq!(point.position, vec2(0.0, 0.0));
+    assert_eq!(point.pixel_position, vec2(400.0, 300.0));
+
+    touch(&mut input, 0, TouchPhase::Moved, 405.0, 300.0);
+    touch(&mut input, 0, TouchPhase::Ended, 405.0, 300.0);
+    input.update(DT);
+
+    assert_eq!(
+      input.gestures(),
+      [Gesture::Tap {
+        position: vec2(405.0, 300.0)
+      }]
+    );
+    assert_eq!(input.touch(0).map(|point| point.phase), Some(TouchPhase::Ended));
+
+    input.update(DT);
+    assert!(input.gestures().is_empty());
+    assert_eq!(input.touches().count(), 0);
+  }
+
+  #[test]
+  fn long_press_is_sent_once() {
+    let mut input = Input::new();
+
+    touch(&mut input, 0, TouchPhase::Started, 100.0, 100.0);
+    input.update(DT);
+    input.update(0.3);
+    assert!(input.gestures().is_empty());
+
+    input.update(0.3);
+    assert_eq!(
+      input.gestures(),
+      [Gesture::LongPress {
+        position: vec2(100.0, 100.0)
+      }]
+    );
+
+    input.update(0.3);
+    assert!(input.gestures().is_empty());
+
+    // no tap after a long press
+    touch(&mut input, 0, TouchPhase::Ended, 100.0, 100.0);
+    input.update(DT);
+    assert!(input.gestures().is_empty());
+  }
+
+  #[test]
+  fn moving_finger_is_no_long_press() {
+    let mut input = Input::new();
+
+    touch(&mut input, 0, TouchPhase::Started, 100.0, 100.0);
+    input.update(DT);
+    touch(&mut input, 0, TouchPhase::Moved, 100.0, 100.0 + TAP_SLOP * 2.0);
+    input.update(1.0);
+
+    assert!(input.gestures().is_empty());
+  }
+
+  #[test]
+  fn quick_swipe() {
+    let mut input = Input::new();
+
+    touch(&mut input, 0, TouchPhase::Started, 100.0, 100.0);
+    input.update(DT);
+    touch(&mut input, 0, TouchPhase::Moved, 140.0, 100.0);
+    input.update(0.05);
+    touch(&mut input, 0, TouchPhase::Moved, 180.0, 100.0);
+    touch(&mut input, 0, TouchPhase::Ended, 180.0, 100.0);
+    input.update(0.05);
+
+    assert_eq!(
+      input.gestures(),
+      [Gesture::Swipe {
+        start: vec2(100.0, 100.0),
+        delta: vec2(80.0, 0.0),
+      }]
+    );
+  }
+
+  #[test]
+  fn slow_or_short_swipe_is_nothing() {
+    let mut input = Input::new();
+
+    touch(&mut input, 0, TouchPhase::Started, 100.0, 100.0);
+    input.update(DT);
+    touch(&mut input, 0, TouchPhase::Moved, 180.0, 100.0);
+    input.update(SWIPE_MAX_DURATION.as_secs_f32());
+    touch(&mut input, 0, TouchPhase::Ended, 180.0, 100.0);
+    input.update(DT);
+    assert!(input.gestures().is_empty());
+
+    touch(&mut input, 1, TouchPhase::Started, 100.0, 100.0);
+    input.update(DT);
+    touch(
+      &mut input,
+      1,
+      TouchPhase::Ended,
+      100.0 + SWIPE_MIN_DISTANCE / 2.0,
+      100.0,
+    );
+    input.update(DT);
+    assert!(input.gestures().is_empty());
+  }
+
+  #[test]
+  fn pinch_between_two_fingers() {
+    let mut input = Input::new();
+
+    touch(&mut input, 0, TouchPhase::Started, 100.0, 100.0);
+    touch(&mut input, 1, TouchPhase::Started, 200.0, 100.0);
+    input.update(DT);
+    assert!(input.gestures().is_empty());
+
+    touch(&mut input, 1, TouchPhase::Moved, 300.0, 100.0);
+    input.update(DT);
+
+    assert_eq!(
+      input.gestures(),
+      [Gesture::Pinch {
+        center: vec2(200.0, 100.0),
+        scale: 2.0,
+      }]
+    );
+    assert_eq!(input.pinch_scale(), 2.0);
+
+    // neither finger taps when they let go
+    touch(&mut input, 0, TouchPhase::Ended, 100.0, 100.0);
+    touch(&mut input, 1, TouchPhase::Ended, 300.0, 100.0);
+    input.update(DT);
+    assert!(input.gestures().is_empty());
+    assert_eq!(input.pinch_scale(), 1.0);
+  }
+
+  #[test]
+  fn rotate_between_two_fingers() {
+    let mut input = Input::new();
+
+    touch(&mut input, 0, TouchPhase::Started, 100.0, 100.0);
+    touch(&mut input, 1, TouchPhase::Started, 200.0, 100.0);
+    input.update(DT);
+
+    // a quarter turn clockwise around the first finger
+    touch(&mut input, 1, TouchPhase::Moved, 100.0, 200.0);
+    input.update(DT);
+
+    let [Gesture::Rotate { center, angle }] = input.gestures() else {
+      panic!("expected one rotation, got {:?}", input.gestures());
+    };
+
+    assert_eq!(*center, vec2(100.0, 150.0));
+    assert!((angle - FRAC_PI_2).abs() < 1e-5);
+    assert!((input.rotation() - FRAC_PI_2).abs() < 1e-5);
+  }
+
+  #[test]
+  fn trackpad_gestures_without_fingers() {
+    let mut input = Input::new();
+
+    input.inject(InputEvent::PinchGesture(0.25));
+    input.inject(InputEvent::PinchGesture(0.25));
+    input.inject(InputEvent::RotationGesture(0.1));
+    input.update(DT);
+
+    assert_eq!(
+      input.gestures(),
+      [
+        Gesture::Pinch {
+          center: Position::zeros(),
+          scale: 1.5,
+        },
+        Gesture::Rotate {
+          center: Position::zeros(),
+          angle: 0.1,
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn focus_loss_cancels_without_gestures() {
+    let mut input = Input::new();
+
+    touch(&mut input, 0, TouchPhase::Started, 100.0, 100.0);
+    input.update(DT);
+
+    input.release_all();
+    input.update(DT);
+
+    assert_eq!(input.touch(0).map(|point| point.phase), Some(TouchPhase::Cancelled));
+    assert!(input.gestures().is_empty());
+
+    input.update(1.0);
+    assert!(input.gestures().is_empty());
+    assert_eq!(input.touches().count(), 0);
+  }
+}
@@ -1,34 +1,312 @@
-use glium::Frame;
+use std::mem;
+
+use glium::backend::Facade;
+use glium::Surface;
 use winit::event::WindowEvent;
 use winit::window::Window;
 
 use crate::engine::EngineContext;
+use crate::math::Color;
+use crate::render::target::Target;
+
+pub trait Layer<T: 'static = ()> {
+  /// Shown in profiler scopes, the type name by default
+  fn name(&self) -> &'static str {
+    std::any::type_name::<Self>()
+  }
+
+  /// Called when the layer is added to the [`LayerStack`]
+  fn on_attach(&mut self, context: &mut EngineContext<T>) {
+    let _ = context;
+  }
+
+  /// Called right after [`Layer::on_attach`] to create textures, buffers and
+  /// shaders through [`EngineContext::gpu`]
+  ///
+  /// Skipped when attached while the context is lost,
+  /// [`Layer::on_context_restored`] runs once it is back
+  fn on_gpu_init(&mut self, context: &mut EngineContext<T>) {
+    let _ = context;
+  }
+
+  /// Called when the app is suspended or the vsync mode changes, right before
+  /// the gl context is dropped
+  ///
+  /// Gpu objects the layer created itself must be dropped here, anything in
+  /// [`EngineContext::assets`] is handled by the engine
+  fn on_context_lost(&mut self, context: &mut EngineContext<T>) {
+    let _ = context;
+  }
+
+  /// Called once the gl context and [`EngineContext::assets`] are
+  /// back, calls [`Layer::on_gpu_init`] by default
+  fn on_context_restored(&mut self, context: &mut EngineContext<T>) {
+    self.on_gpu_init(context);
+  }
+
+  /// Called when the layer is removed from the [`LayerStack`], right before it
+  /// is dropped
+  fn on_detach(&mut self, context: &mut EngineContext<T>) {
+    let _ = context;
+  }
+
+  /// Called zero or more times per frame before update at a fixed rate, see
+  /// [`EngineContext::fixed_delta_time`]
+  fn fixed_update(&mut self, context: &mut EngineContext<T>) {
+    let _ = context;
+  }
 
-pub trait Layer {
   /// Called every frame before draw to update state
-  fn update(&mut self, context: &mut EngineContext) {
+  fn update(&mut self, context: &mut EngineContext<T>) {
     let _ = context;
   }
 
-  /// Called every frame after update to draw to display
-  fn draw(&mut self, context: &mut EngineContext, display: &mut Frame) {
+  /// Called every frame after update to draw to the window or an offscreen
+  /// target when running headless
+  ///
+  /// [`EngineContext::alpha`] gives how far the frame is between fixed updates
+  fn draw(&mut self, context: &mut EngineContext<T>, target: &mut Target) {
     let _ = context;
-    let _ = display;
+    let _ = target;
   }
 
   /// Called every frame for egui rendering
-  fn egui(&mut self, context: &mut EngineContext, ctx: egui::Context) {
+  fn egui(&mut self, context: &mut EngineContext<T>, ctx: egui::Context) {
     let _ = context;
     let _ = ctx;
   }
 
-  /// Called when a window event is received
+  /// Checked after every update, the window accepts ime composition while any
+  /// layer returns true, e.g. while one of its text fields is focused
+  fn ime_allowed(&self) -> bool {
+    false
+  }
+
+  /// Called when a window event is received, topmost layer first
   /// Returns true if the event was consumed
-  fn handle_window_event(&mut self, context: &mut EngineContext, event: &WindowEvent, window: &Window) -> bool {
+  fn handle_window_event(&mut self, context: &mut EngineContext<T>, event: &WindowEvent, window: &Window) -> bool {
     let _ = context;
     let _ = event;
     let _ = window;
 
     false
   }
+
+  /// Called when a user event sent through [`EngineContext::event_proxy`] is
+  /// received, topmost layer first
+  /// Returns true if the event was consumed
+  fn handle_user_event(&mut self, context: &mut EngineContext<T>, event: &T) -> bool {
+    let _ = context;
+    let _ = event;
+
+    false
+  }
+}
+
+/// Queued through [`EngineContext`] and applied by the engine between frames
+pub enum LayerCommand<T: 'static = ()> {
+  /// Push a layer above the other layers but below every overlay
+  Push(Box<dyn Layer<T>>),
+  /// Push an overlay above everything
+  PushOverlay(Box<dyn Layer<T>>),
+  /// Pop the topmost layer, overlays are not touched
+  Pop,
+  /// Pop the topmost overlay
+  PopOverlay,
+  /// Pop the topmost layer then push a new one in its place
+  Replace(Box<dyn Layer<T>>),
+}
+
+/// Layers followed by overlays
+///
+/// Updated and drawn bottom to top so overlays draw last, events go top to
+/// bottom so overlays get them first
+pub struct LayerStack<T: 'static = ()> {
+  layers: Vec<Box<dyn Layer<T>>>,
+  /// Index of the first overlay
+  overlay_start: usize,
+  pending: Vec<LayerCommand<T>>,
+}
+
+impl<T: 'static> LayerStack<T> {
+  /// `layers` are attached once the engine context exists
+  pub fn new(layers: Vec<Box<dyn Layer<T>>>) -> Self {
+    Self {
+      layers: Vec::new(),
+      overlay_start: 0,
+      pending: layers.into_iter().map(LayerCommand::Push).collect(),
+    }
+  }
+
+  pub fn push_layer(&mut self, mut layer: Box<dyn Layer<T>>, context: &mut EngineContext<T>) {
+    layer.on_attach(context);
+    if !context.is_context_lost() {
+      layer.on_gpu_init(context);
+    }
+
+    self.layers.insert(self.overlay_start, layer);
+    self.overlay_start += 1;
+  }
+
+  pub fn push_overlay(&mut self, mut layer: Box<dyn Layer<T>>, context: &mut EngineContext<T>) {
+    layer.on_attach(context);
+    if !context.is_context_lost() {
+      layer.on_gpu_init(context);
+    }
+
+    self.layers.push(layer);
+  }
+
+  pub fn pop_layer(&mut self, context: &mut EngineContext<T>) -> Option<Box<dyn Layer<T>>> {
+    if self.overlay_start == 0 {
+      return None;
+    }
+
+    self.overlay_start -= 1;
+
+    let mut layer = self.layers.remove(self.overlay_start);
+    layer.on_detach(context);
+
+    Some(layer)
+  }
+
+  pub fn pop_overlay(&mut self, context: &mut EngineContext<T>) -> Option<Box<dyn Layer<T>>> {
+    if self.layers.len() == self.overlay_start {
+      return None;
+    }
+
+    let mut layer = self.layers.pop()?;
+    layer.on_detach(context);
+
+    Some(layer)
+  }
+
+  /// Apply queued commands, both from [`LayerStack::new`] and from layers
+  /// through [`EngineContext`]
+  pub(crate) fn apply_commands(&mut self, context: &mut EngineContext<T>) {
+    let mut commands = mem::take(&mut self.pending);
+    commands.append(&mut context.layer_commands);
+
+    for command in commands {
+      match command {
+        LayerCommand::Push(layer) => self.push_layer(layer, context),
+        LayerCommand::PushOverlay(layer) => self.push_overlay(layer, context),
+        LayerCommand::Pop => {
+          self.pop_layer(context);
+        }
+        LayerCommand::PopOverlay => {
+          self.pop_overlay(context);
+        }
+        LayerCommand::Replace(layer) => {
+          self.pop_layer(context);
+          self.push_layer(layer, context);
+        }
+      }
+    }
+  }
+
+  /// Input, fixed updates, updates and queued commands of one frame, the
+  /// same for a window and [`Headless`](crate::headless::Headless). Returns
+  /// whether any layer wants ime
+  ///
+  /// Starts the profiler frame, whoever presents the frame ends it
+  pub(crate) fn update_frame(&mut self, context: &mut EngineContext<T>, delta_time: f32) -> bool {
+    context.fps_stats.update(delta_time);
+    // a recording being played back brings its own delta_time and input
+    context.delta_time = context.input_replay.begin_frame(&mut context.input, delta_time);
+
+    let profiler = context.profiler.clone();
+    profiler.begin_frame();
+    let _update_scope = profiler.scope("update");
+
+    // picks up edited shader files in debug builds
+    context.poll_shaders();
+
+    context.input.update(context.delta_time);
+    context.shortcuts.update(&context.input);
+
+    let steps = context.timestep.advance(context.delta_time);
+
+    if steps > 0 {
+      let _scope = profiler.scope("fixed_update");
+
+      for _ in 0..steps {
+        for layer in &mut self.layers {
+          let _scope = profiler.scope(layer.name());
+          layer.fixed_update(context);
+        }
+      }
+    }
+
+    for layer in &mut self.layers {
+      let _scope = profiler.scope(layer.name());
+      layer.update(context);
+    }
+
+    let ime_allowed = self.layers.iter_mut().any(|layer| layer.ime_allowed());
+
+    self.apply_commands(context);
+
+    ime_allowed
+  }
+
+  /// Clear `target` and draw every layer into it, then read back the frame
+  /// for captures that leave out the egui overlay
+  pub(crate) fn draw_frame<F: Facade>(&mut self, context: &mut EngineContext<T>, target: &mut Target, facade: &F) {
+    let profiler = context.profiler.clone();
+    let _draw_scope = profiler.scope("draw");
+
+    let Color(r, g, b, a) = context.config().clear_color;
+
+    if target.get_depth_buffer_bits().is_some() {
+      target.clear_color_srgb_and_depth((r, g, b, a), 1.0);
+    } else {
+      target.clear_color_srgb(r, g, b, a);
+    }
+
+    for layer in &mut self.layers {
+      let _scope = profiler.scope(layer.name());
+      layer.draw(context, target);
+    }
+
+    context.capture.capture(target, facade, false);
+  }
+
+  /// Detach every layer, top to bottom
+  pub fn clear(&mut self, context: &mut EngineContext<T>) {
+    while let Some(mut layer) = self.layers.pop() {
+      layer.on_detach(context);
+    }
+
+    self.overlay_start = 0;
+  }
+
+  /// Bottom to top
+  pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut Box<dyn Layer<T>>> {
+    self.layers.iter_mut()
+  }
+
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.layers.len()
+  }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.layers.is_empty()
+  }
+
+  #[inline]
+  pub fn overlay_count(&self) -> usize {
+    self.layers.len() - self.overlay_start
+  }
+}
+
+impl<'a, T: 'static> IntoIterator for &'a mut LayerStack<T> {
+  type IntoIter = std::slice::IterMut<'a, Box<dyn Layer<T>>>;
+  type Item = &'a mut Box<dyn Layer<T>>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.layers.iter_mut()
+  }
 }
@@ -1,55 +1,363 @@
 use std::rc::Rc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use egui::ViewportId;
-use glium::{Display, Surface};
-use glutin::surface::WindowSurface;
+use glium::backend::Facade;
 use winit::application::ApplicationHandler;
 use winit::error::EventLoopError;
-use winit::event::WindowEvent;
-use winit::event_loop::{ActiveEventLoop, EventLoop};
-use winit::window::WindowId;
+use winit::event::{DeviceEvent, DeviceId, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy};
+use winit::window::{CursorIcon, WindowId};
 
-use crate::app::{App, AppState};
-use crate::debug::Egui;
+use crate::app::App;
+use crate::debug::profiler::Profiler;
+use crate::engine::config::{CursorMode, EngineConfig, VsyncMode, WindowCommand};
 use crate::engine::fps::FpsStats;
-use crate::engine::input::{set_key_state, update_input_state};
-use crate::engine::layer::Layer;
-use crate::math::vec2;
+use crate::engine::input::action::InputMap;
+use crate::engine::input::record::InputReplay;
+use crate::engine::input::shortcut::Shortcuts;
+use crate::engine::input::Input;
+use crate::engine::layer::{Layer, LayerCommand};
+use crate::engine::timestep::FixedTimestep;
+use crate::math::Color;
+use crate::render::assets::Assets;
+use crate::render::backend::gl::GlBackend;
+use crate::render::capture::Capture;
+use crate::render::gpu::Gpu;
 use crate::render::renderer::Renderer;
 use crate::render::renderer2d::text::font::FontBitmap;
 use crate::render::renderer2d::Renderer2d;
-use crate::window::set_viewport;
+use crate::render::shader::engine_preprocessor;
+use crate::window::Viewport;
 
 pub mod component;
+pub mod config;
 pub mod events;
 pub mod fps;
 pub mod input;
 pub mod layer;
+pub mod timestep;
 
-pub struct EngineContext {
+/// Shortest time between two checks for changed shader files
+pub const SHADER_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct EngineContext<T: 'static = ()> {
   pub delta_time: f32,
   pub start_time: Instant,
   pub last_time: Instant,
 
+  /// Drives [`Layer::fixed_update`]
+  pub timestep: FixedTimestep,
+
+  /// Whole window in physical pixels, updated on resize
+  pub viewport: Viewport,
+
   pub renderer: Renderer,
   pub renderer2d: Renderer2d,
 
+  /// Gpu resources that are uploaded again after the context is lost
+  pub assets: Assets,
+
   pub fps_stats: FpsStats,
+
+  /// Disabled by default, shared with the gl backends for gpu timings
+  pub profiler: Profiler,
+
+  /// Screenshots and recordings of this window
+  pub capture: Capture,
+
+  /// Keyboard, mouse and text input of this window
+  pub input: Input,
+
+  /// Record input to a file or play a recording back instead of live input
+  pub input_replay: InputReplay,
+
+  /// Named actions and axes, empty until a layer loads or binds some
+  pub input_map: InputMap,
+
+  /// Matched against key presses at the start of every frame
+  pub shortcuts: Shortcuts,
+
+  config: EngineConfig,
+
+  last_shader_poll: Option<Instant>,
+
+  pub(crate) event_proxy: Option<EventLoopProxy<T>>,
+
+  pub(crate) window_id: Option<WindowId>,
+  pub(crate) window_requests: Vec<(EngineConfig, Vec<Box<dyn Layer<T>>>)>,
+  pub(crate) close_requested: bool,
+
+  pub(crate) layer_commands: Vec<LayerCommand<T>>,
+  pub(crate) window_commands: Vec<WindowCommand>,
 }
 
-impl EngineContext {
-  pub fn new(renderer: Renderer, renderer2d: Renderer2d) -> Self {
+impl<T: 'static> EngineContext<T> {
+  pub fn new(config: EngineConfig, gpu: Gpu, renderer: Renderer, renderer2d: Renderer2d) -> Self {
     Self {
       delta_time: 0.0,
       last_time: Instant::now(),
       start_time: Instant::now(),
 
+      timestep: FixedTimestep::default(),
+
+      viewport: Viewport::default(),
+
       renderer,
       renderer2d,
 
+      assets: Assets::new(gpu),
+
       fps_stats: FpsStats::new(),
+
+      profiler: Profiler::new(),
+
+      capture: Capture::new(),
+
+      input: Input::new(),
+
+      input_replay: InputReplay::new(),
+
+      input_map: InputMap::new(),
+
+      shortcuts: Shortcuts::new(),
+
+      config,
+
+      last_shader_poll: None,
+
+      event_proxy: None,
+
+      window_id: None,
+      window_requests: Vec::new(),
+      close_requested: false,
+
+      layer_commands: Vec::new(),
+      window_commands: Vec::new(),
+    }
+  }
+
+  /// Gl context for creating textures, buffers and shaders
+  ///
+  /// Panics while the context is lost, see [`Layer::on_context_lost`]
+  #[inline]
+  pub fn gpu(&self) -> &Gpu {
+    self.assets.gpu()
+  }
+
+  /// True while the app is suspended
+  #[inline]
+  pub fn is_context_lost(&self) -> bool {
+    self.assets.is_lost()
+  }
+
+  /// Drop every gpu object owned by the engine
+  pub(crate) fn release_gpu(&mut self) {
+    self.profiler.release_gpu();
+    self.renderer.release();
+    self.renderer2d.release();
+    self.assets.release();
+  }
+
+  /// Rebuild the renderers and upload every asset to a new context
+  pub(crate) fn restore_gpu<F: Facade>(&mut self, facade: &F) {
+    self.renderer.restore(facade);
+    self.renderer2d.restore(facade);
+    self.assets.restore(Gpu::new(facade));
+  }
+
+  /// Recompile shaders whose files changed, only debug builds read shaders
+  /// from disk so this does nothing in release builds
+  pub fn reload_shaders(&mut self) {
+    if self.is_context_lost() {
+      return;
+    }
+
+    self.renderer.reload_shaders();
+    self.renderer2d.reload_shaders();
+    self.assets.reload_shaders();
+  }
+
+  /// [`EngineContext::reload_shaders`] at most every
+  /// [`SHADER_POLL_INTERVAL`], called every frame by the app
+  pub(crate) fn poll_shaders(&mut self) {
+    if !cfg!(debug_assertions) {
+      return;
+    }
+
+    let now = Instant::now();
+
+    if self
+      .last_shader_poll
+      .is_some_and(|last| now.duration_since(last) < SHADER_POLL_INTERVAL)
+    {
+      return;
     }
+
+    self.last_shader_poll = Some(now);
+    self.reload_shaders();
+  }
+
+  /// An egui text field has focus, key and text queries on [`Input`] report
+  /// nothing, [`Input::raw`] answers anyway
+  #[inline]
+  pub fn wants_keyboard_input(&self) -> bool {
+    self.input.egui_wants_keyboard()
+  }
+
+  /// The pointer is over egui, mouse button, scroll, click and drag queries on
+  /// [`Input`] report nothing, [`Input::raw`] answers anyway
+  #[inline]
+  pub fn wants_pointer_input(&self) -> bool {
+    self.input.egui_wants_pointer()
+  }
+
+  /// Current settings including every change made through the setters below
+  #[inline]
+  pub fn config(&self) -> &EngineConfig {
+    &self.config
+  }
+
+  pub fn set_title(&mut self, title: impl Into<String>) {
+    let title = title.into();
+    self.config.title.clone_from(&title);
+    self.window_commands.push(WindowCommand::Title(title));
+  }
+
+  /// Logical inner size
+  pub fn set_size(&mut self, width: u32, height: u32) {
+    self.config.size = Some((width, height));
+    self.window_commands.push(WindowCommand::Size((width, height)));
+  }
+
+  /// Logical outer position
+  pub fn set_position(&mut self, x: i32, y: i32) {
+    self.config.position = Some((x, y));
+    self.window_commands.push(WindowCommand::Position((x, y)));
+  }
+
+  pub fn set_resizable(&mut self, resizable: bool) {
+    self.config.resizable = resizable;
+    self.window_commands.push(WindowCommand::Resizable(resizable));
+  }
+
+  pub fn set_decorations(&mut self, decorations: bool) {
+    self.config.decorations = decorations;
+    self.window_commands.push(WindowCommand::Decorations(decorations));
+  }
+
+  /// Borderless fullscreen on the current monitor
+  pub fn set_fullscreen(&mut self, fullscreen: bool) {
+    self.config.fullscreen = fullscreen;
+    self.window_commands.push(WindowCommand::Fullscreen(fullscreen));
+  }
+
+  /// Confine or lock the cursor, it is released again while the window is
+  /// unfocused
+  pub fn set_cursor_mode(&mut self, mode: CursorMode) {
+    self.config.cursor_mode = mode;
+    self.window_commands.push(WindowCommand::CursorMode(mode));
+  }
+
+  /// Only hidden while over the window
+  pub fn set_cursor_visible(&mut self, visible: bool) {
+    self.config.cursor_visible = visible;
+    self.window_commands.push(WindowCommand::CursorVisible(visible));
+  }
+
+  pub fn set_cursor_icon(&mut self, icon: CursorIcon) {
+    self.config.cursor_icon = icon;
+    self.window_commands.push(WindowCommand::CursorIcon(icon));
+  }
+
+  /// Takes effect after the current update by creating a new gl context on
+  /// the same window, layers see
+  /// [`Layer::on_context_lost`](crate::Layer::on_context_lost) and
+  /// [`Layer::on_context_restored`](crate::Layer::on_context_restored) as on a
+  /// suspend
+  pub fn set_vsync(&mut self, vsync: VsyncMode) {
+    self.config.vsync = vsync;
+    self.window_commands.push(WindowCommand::Vsync(vsync));
+  }
+
+  /// Where the text being composed is, in physical pixels from the top left
+  /// of the window, so the ime candidate box can be placed next to it
+  pub fn set_ime_cursor_area(&mut self, x: i32, y: i32, width: u32, height: u32) {
+    self.window_commands.push(WindowCommand::ImeCursorArea {
+      position: (x, y),
+      size: (width, height),
+    });
+  }
+
+  /// Used from the next frame on
+  #[inline]
+  pub fn set_clear_color(&mut self, color: Color) {
+    self.config.clear_color = color;
+  }
+
+  /// Window this context belongs to, `None` while suspended or headless
+  #[inline]
+  pub fn window_id(&self) -> Option<WindowId> {
+    self.window_id
+  }
+
+  /// Open another window with its own context and layers, applied after the
+  /// current frame's update or event
+  pub fn open_window(&mut self, config: EngineConfig, layers: Vec<Box<dyn Layer<T>>>) {
+    self.window_requests.push((config, layers));
+  }
+
+  /// Close the window this context belongs to, closing the primary window
+  /// exits the engine
+  #[inline]
+  pub fn close_window(&mut self) {
+    self.close_requested = true;
+  }
+
+  /// Push a layer above the other layers but below every overlay, applied
+  /// after the current frame's update or event
+  pub fn push_layer(&mut self, layer: impl Layer<T> + 'static) {
+    self.layer_commands.push(LayerCommand::Push(Box::new(layer)));
+  }
+
+  /// Push an overlay above everything, applied after the current frame's
+  /// update or event
+  pub fn push_overlay(&mut self, layer: impl Layer<T> + 'static) {
+    self.layer_commands.push(LayerCommand::PushOverlay(Box::new(layer)));
+  }
+
+  /// Pop the topmost layer, overlays are not touched
+  pub fn pop_layer(&mut self) {
+    self.layer_commands.push(LayerCommand::Pop);
+  }
+
+  /// Pop the topmost overlay
+  pub fn pop_overlay(&mut self) {
+    self.layer_commands.push(LayerCommand::PopOverlay);
+  }
+
+  /// Replace the topmost layer
+  pub fn replace_layer(&mut self, layer: impl Layer<T> + 'static) {
+    self.layer_commands.push(LayerCommand::Replace(Box::new(layer)));
+  }
+
+  /// Seconds simulated by each [`Layer::fixed_update`]
+  #[inline]
+  pub fn fixed_delta_time(&self) -> f32 {
+    self.timestep.step
+  }
+
+  /// Interpolation factor between the previous and current fixed update, for
+  /// use in [`Layer::draw`]
+  #[inline]
+  pub fn alpha(&self) -> f32 {
+    self.timestep.alpha()
+  }
+
+  /// Wakes the event loop with a user event from any thread, `None` when
+  /// running headless
+  #[inline]
+  pub fn event_proxy(&self) -> Option<&EventLoopProxy<T>> {
+    self.event_proxy.as_ref()
   }
 }
 
@@ -65,37 +373,38 @@ impl From<EventLoopError> for Error {
   }
 }
 
-pub struct Engine {
-  pub layers: Vec<Box<dyn Layer>>,
-  pub egui: Option<Egui>,
-  pub context: Option<EngineContext>,
-  pub app: App,
+pub struct Engine<T: 'static = ()> {
+  /// The first window is the primary one, closing it exits
+  pub windows: Vec<App<T>>,
+
+  event_proxy: EventLoopProxy<T>,
 }
 
 impl Engine {
-  pub fn new(window_title: &'static str, layers: Vec<Box<dyn Layer>>) -> (Self, EventLoop<()>) {
-    let event_loop = EventLoop::builder().build().unwrap();
+  pub fn new(config: EngineConfig, layers: Vec<Box<dyn Layer>>) -> (Self, EventLoop<()>) {
+    Self::with_user_event(config, layers)
+  }
+}
 
-    let app = App {
-      window_title,
-      state: None,
-    };
+impl<T: 'static> Engine<T> {
+  /// Engine whose event loop carries user events of type `T`, see
+  /// [`EngineContext::event_proxy`] and [`Layer::handle_user_event`]
+  pub fn with_user_event(config: EngineConfig, layers: Vec<Box<dyn Layer<T>>>) -> (Self, EventLoop<T>) {
+    let event_loop = EventLoop::with_user_event().build().unwrap();
+    let event_proxy = event_loop.create_proxy();
 
-    let context = None;
-    let egui = None;
+    let windows = vec![App::new(config, layers)];
 
-    (
-      Self {
-        layers,
-        egui,
-        context,
-        app,
-      },
-      event_loop,
-    )
+    (Self { windows, event_proxy }, event_loop)
   }
 
-  pub fn run(mut self, event_loop: EventLoop<()>) -> Result<(), Error> {
+  /// Open another window with its own layers once the event loop starts, see
+  /// [`EngineContext::open_window`] to open one at runtime
+  pub fn add_window(&mut self, config: EngineConfig, layers: Vec<Box<dyn Layer<T>>>) {
+    self.windows.push(App::new(config, layers));
+  }
+
+  pub fn run(mut self, event_loop: EventLoop<T>) -> Result<(), Error> {
     info!("starting event loop");
 
     event_loop.run_app(&mut self)?;
@@ -103,152 +412,140 @@ impl Engine {
     Ok(())
   }
 
-  /// # Safety
-  ///
-  /// `self.context` must be `Some` before calling this function
-  fn update(&mut self) {
-    let context = self.context.as_mut().unwrap();
-    let now = Instant::now();
-    context.delta_time = now.duration_since(context.last_time).as_secs_f32();
-    context.last_time = now;
-    context.fps_stats.update((1.0 / context.delta_time) as u32);
-
-    update_input_state();
+  /// Open and close windows requested through [`EngineContext`]
+  fn apply_window_requests(&mut self, event_loop: &ActiveEventLoop) {
+    let mut opened = Vec::new();
 
-    for layer in &mut self.layers {
-      layer.update(context);
+    for app in &mut self.windows {
+      if let Some(context) = app.context.as_mut() {
+        for (config, layers) in context.window_requests.drain(..) {
+          opened.push(App::new(config, layers));
+        }
+      }
     }
-  }
-
-  /// # Safety
-  ///
-  /// `self.context`, `self.state` and `self.egui` must be `Some` before calling
-  /// this function
-  fn draw(&mut self) {
-    let state = self.app.state.as_ref().unwrap();
-
-    let mut frame = state.display.draw();
-
-    frame.clear_color_srgb(0.0, 0.0, 0.0, 0.0);
 
-    let context = self.context.as_mut().unwrap();
-
-    for layer in &mut self.layers {
-      layer.draw(context, &mut frame);
+    for mut app in opened {
+      app.resume(event_loop, &self.event_proxy);
+      self.windows.push(app);
     }
 
-    let egui = self.egui.as_mut().unwrap();
+    if self.windows.first().is_some_and(App::close_requested) {
+      event_loop.exit();
+      return;
+    }
 
-    egui.run(&state.window, |ctx| {
-      for layer in &mut self.layers {
-        layer.egui(context, ctx.clone());
+    self.windows.retain_mut(|app| {
+      if app.close_requested() {
+        app.close();
+        false
+      } else {
+        true
       }
     });
-
-    egui.paint(&state.display, &mut frame);
-
-    frame.finish().unwrap();
   }
+}
 
-  /// # Safety
-  ///
-  /// `self.context`, `self.state` and `self.egui` must be `Some` before calling
-  /// this function
-  fn handle_window_event(&mut self, event: WindowEvent) {
-    let state = self.app.state.as_ref().unwrap();
-
-    // send events to egui first
-    if self.egui.as_mut().unwrap().handle_window_event(&event, &state.window) {
-      return;
+impl<T: 'static> ApplicationHandler<T> for Engine<T> {
+  fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+    for app in &mut self.windows {
+      app.resume(event_loop, &self.event_proxy);
     }
 
-    match &event {
-      WindowEvent::CursorMoved { position, .. } => {
-        let (width, height): (f32, f32) = state.window.inner_size().into();
-        let (x, y) = (position.x as f32, position.y as f32);
+    self.apply_window_requests(event_loop);
+  }
 
-        let x = x / width * 2.0 - 1.0;
-        let y = y / height * 2.0 - 1.0;
+  fn suspended(&mut self, _: &ActiveEventLoop) {
+    for app in &mut self.windows {
+      app.suspend();
+    }
+  }
 
-        input::set_mouse_position(vec2(x, y));
-      }
-      WindowEvent::MouseInput { state, button, .. } => {
-        input::set_mouse_state(*button, *state);
-      }
-      WindowEvent::KeyboardInput { event, .. } => {
-        set_key_state(event.physical_key.into(), event.state);
-      }
-      _ => (),
+  // every window in order, stops at the first layer that consumes it
+  fn user_event(&mut self, event_loop: &ActiveEventLoop, event: T) {
+    if self.windows.iter().all(|app| app.context.is_none()) {
+      warn!("dropping user event received before the engine started");
+      return;
     }
 
-    for i in 0..self.layers.len() {
-      if self.layers[i].handle_window_event(self.context.as_mut().unwrap(), &event, &state.window) {
-        break; // event was consumed
+    for app in &mut self.windows {
+      if app.handle_user_event(&event) {
+        break;
       }
     }
-  }
-}
 
-impl ApplicationHandler for Engine {
-  fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-    self.app.state = Some(AppState::new(event_loop, self.app.window_title, true));
-    let state = self.app.state.as_ref().unwrap();
-    self.context = Some(create_context(&state.display));
-    self.egui = Some(Egui::new(ViewportId::ROOT, &state.display, &state.window, &event_loop));
+    self.apply_window_requests(event_loop);
   }
 
-  // surely not an issue to just destroy everything
-  fn suspended(&mut self, _: &ActiveEventLoop) {
-    self.app.state = None;
-    self.context = None;
-    self.egui = None;
+  // raw input isn't tied to a window, it goes to the focused one
+  fn device_event(&mut self, _: &ActiveEventLoop, _: DeviceId, event: DeviceEvent) {
+    for app in &mut self.windows {
+      app.handle_device_event(&event);
+    }
   }
 
   // Request redraw
   fn about_to_wait(&mut self, _: &ActiveEventLoop) {
-    if let Some(state) = self.app.state.as_ref() {
-      state.window.request_redraw();
+    for app in &self.windows {
+      if let Some(state) = app.state.as_ref() {
+        state.window.request_redraw();
+      }
     }
   }
 
-  fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+  fn window_event(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
+    let Some(app) = self.windows.iter_mut().find(|app| app.id() == Some(window_id)) else {
+      return;
+    };
+
     match event {
       WindowEvent::RedrawRequested => {
-        if self.app.state.is_some() {
-          self.update();
-          self.draw();
-        }
+        app.update();
+        app.draw();
       }
       WindowEvent::Resized(new_size) => {
-        if let Some(state) = self.app.state.as_ref() {
-          state.display.resize(new_size.into());
-
-          set_viewport(new_size.into());
-
-          self.handle_window_event(event)
-        }
+        app.resize(new_size);
+        app.handle_window_event(event);
       }
-      WindowEvent::CloseRequested => event_loop.exit(),
+      WindowEvent::CloseRequested => app.context.as_mut().unwrap().close_window(),
       // send every other event to engine / layers
-      event => {
-        if self.app.state.is_some() {
-          self.handle_window_event(event)
-        }
-      }
+      event => app.handle_window_event(event),
     }
+
+    self.apply_window_requests(event_loop);
   }
 }
 
-fn create_context(display: &Display<WindowSurface>) -> EngineContext {
-  let font = FontBitmap::from_bytes(display, include_bytes!("../../fonts/terminus/ter-u32n.bdf"));
+pub(crate) fn create_context<T: 'static, F: Facade>(facade: &F, config: EngineConfig) -> EngineContext<T> {
+  let preprocessor = Rc::new(engine_preprocessor());
+
+  let mesh_shader = shader_source!("../../shaders/mesh.vert", "../../shaders/mesh.frag")
+    .with_preprocessor(preprocessor.clone())
+    .with_engine_files("mesh.vert", "mesh.frag");
+  let quad_shader = shader_source!("../../shaders/quad.vert", "../../shaders/quad.frag")
+    .with_preprocessor(preprocessor.clone())
+    .with_engine_files("quad.vert", "quad.frag");
+  let text_shader = shader_source!("../../shaders/text.vert", "../../shaders/text.frag")
+    .with_preprocessor(preprocessor)
+    .with_engine_files("text.vert", "text.frag");
+
+  let profiler = Profiler::new();
+
+  let backend = GlBackend::new(facade)
+    .with_mesh_shader(mesh_shader)
+    .with_profiler(profiler.clone(), "renderer");
+  let backend2d = GlBackend::new(facade)
+    .with_quad_shader(quad_shader)
+    .with_text_shader(text_shader)
+    .with_profiler(profiler.clone(), "renderer2d");
+
+  let font = FontBitmap::from_bytes(include_bytes!("../../fonts/terminus/ter-u32n.bdf"));
   let font = Rc::new(font);
 
-  let mesh_shader = shader!(display, "../../shaders/mesh.vert", "../../shaders/mesh.frag");
-  let quad_shader = shader!(display, "../../shaders/quad.vert", "../../shaders/quad.frag");
-  let text_shader = shader!(display, "../../shaders/text.vert", "../../shaders/text.frag");
+  let renderer = Renderer::new(backend);
+  let renderer2d = Renderer2d::new(backend2d, font);
 
-  let renderer = Renderer::new(display, mesh_shader);
-  let renderer2d = Renderer2d::new(display, quad_shader, text_shader, font);
+  let mut context = EngineContext::new(config, Gpu::new(facade), renderer, renderer2d);
+  context.profiler = profiler;
 
-  EngineContext::new(renderer, renderer2d)
+  context
 }
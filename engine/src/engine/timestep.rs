@@ -0,0 +1,169 @@
+pub const DEFAULT_RATE: f32 = 60.0;
+pub const DEFAULT_MAX_STEPS: u32 = 5;
+
+/// Accumulator for running simulation at a fixed rate independent of the
+/// frame rate
+pub struct FixedTimestep {
+  /// Seconds simulated by each fixed update
+  pub step: f32,
+
+  /// Most fixed updates run in a single frame, time past that is dropped so a
+  /// slow frame can't snowball into an even slower one
+  pub max_steps: u32,
+
+  accumulator: f32,
+  alpha: f32,
+  /// Seconds dropped since the steps started hitting `max_steps`, `None`
+  /// while keeping up
+  dropped: Option<f32>,
+}
+
+impl FixedTimestep {
+  pub fn new(step: f32) -> Self {
+    debug_assert!(step > 0.0, "fixed step should be positive");
+
+    Self {
+      step,
+      max_steps: DEFAULT_MAX_STEPS,
+      accumulator: 0.0,
+      alpha: 0.0,
+      dropped: None,
+    }
+  }
+
+  /// Fixed updates per second
+  pub fn from_rate(rate: f32) -> Self {
+    Self::new(1.0 / rate)
+  }
+
+  pub fn max_steps(mut self, max_steps: u32) -> Self {
+    self.max_steps = max_steps;
+    self
+  }
+
+  /// Add a frame's worth of time and return how many fixed updates to run
+  pub fn advance(&mut self, delta_time: f32) -> u32 {
+    self.accumulator += delta_time;
+
+    let mut steps = 0;
+
+    while self.accumulator >= self.step && steps < self.max_steps {
+      self.accumulator -= self.step;
+      steps += 1;
+    }
+
+    // spiral of death, drop the backlog but keep the partial step. Logged
+    // when it starts and ends instead of every frame it lasts
+    if self.accumulator >= self.step {
+      let backlog = self.accumulator - self.accumulator % self.step;
+
+      match &mut self.dropped {
+        Some(dropped) => *dropped += backlog,
+        None => {
+          warn!("fixed update fell behind by {backlog:.3}s, skipping");
+          self.dropped = Some(backlog);
+        }
+      }
+
+      self.accumulator %= self.step;
+    } else if let Some(dropped) = self.dropped.take() {
+      info!("fixed update caught up, {dropped:.3}s skipped in total");
+    }
+
+    self.alpha = self.accumulator / self.step;
+
+    steps
+  }
+
+  /// How far between the last and next fixed update the current frame is
+  ///
+  /// `0.0 .. 1.0`, for interpolating between previous and current simulation
+  /// state when drawing
+  #[inline]
+  pub fn alpha(&self) -> f32 {
+    self.alpha
+  }
+
+  pub fn reset(&mut self) {
+    self.accumulator = 0.0;
+    self.alpha = 0.0;
+    self.dropped = None;
+  }
+}
+
+impl Default for FixedTimestep {
+  fn default() -> Self {
+    Self::from_rate(DEFAULT_RATE)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // a quarter second is exact in binary so the accumulator doesn't drift
+  const STEP: f32 = 0.25;
+
+  #[test]
+  fn uneven_deltas_carry_over() {
+    let mut timestep = FixedTimestep::new(STEP);
+
+    assert_eq!(timestep.advance(0.125), 0);
+    assert_eq!(timestep.advance(0.125), 1);
+    assert_eq!(timestep.advance(0.5625), 2);
+    assert_eq!(timestep.advance(0.1875), 1);
+    assert_eq!(timestep.advance(0.0), 0);
+  }
+
+  #[test]
+  fn max_steps_drops_the_backlog() {
+    let mut timestep = FixedTimestep::new(STEP).max_steps(2);
+
+    // five steps and a half behind, two run and the partial step is kept
+    assert_eq!(timestep.advance(1.375), 2);
+    assert_eq!(timestep.alpha(), 0.5);
+
+    assert_eq!(timestep.advance(0.125), 1);
+    assert_eq!(timestep.alpha(), 0.0);
+  }
+
+  #[test]
+  fn dropped_time_adds_up_until_caught_up() {
+    let mut timestep = FixedTimestep::new(STEP).max_steps(1);
+
+    assert_eq!(timestep.advance(0.75), 1);
+    assert_eq!(timestep.dropped, Some(0.5));
+
+    assert_eq!(timestep.advance(0.625), 1);
+    assert_eq!(timestep.dropped, Some(0.75));
+
+    // only the partial step is left over
+    assert_eq!(timestep.alpha(), 0.5);
+    assert_eq!(timestep.advance(0.125), 1);
+    assert_eq!(timestep.dropped, None);
+  }
+
+  #[test]
+  fn alpha_is_the_partial_step() {
+    let mut timestep = FixedTimestep::new(STEP);
+
+    timestep.advance(0.0625);
+    assert_eq!(timestep.alpha(), 0.25);
+
+    timestep.advance(0.375);
+    assert_eq!(timestep.alpha(), 0.75);
+
+    timestep.reset();
+    assert_eq!(timestep.alpha(), 0.0);
+    assert_eq!(timestep.advance(0.125), 0);
+    assert_eq!(timestep.alpha(), 0.5);
+  }
+
+  #[test]
+  fn from_rate() {
+    let timestep = FixedTimestep::from_rate(4.0);
+
+    assert_eq!(timestep.step, STEP);
+    assert_eq!(timestep.max_steps, DEFAULT_MAX_STEPS);
+  }
+}
@@ -0,0 +1,253 @@
+// windowless rendering for golden image tests
+// uses a surfaceless egl context on the first egl device so it works with a
+// software driver like mesa llvmpipe and no display server
+
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+use glium::framebuffer::{DepthRenderBuffer, RenderBufferCreationError, SimpleFrameBuffer, ValidationError};
+use glium::texture::{DepthFormat, MipmapsOption, RawImage2d, TextureCreationError, UncompressedFloatFormat};
+use glium::{HeadlessRenderer, IncompatibleOpenGl, Texture2d};
+use glutin::api::egl::device::Device;
+use glutin::api::egl::display::Display as EglDisplay;
+use glutin::config::{Api, ConfigSurfaceTypes, ConfigTemplateBuilder};
+use glutin::context::{ContextApi, ContextAttributesBuilder, PossiblyCurrentContext, Version};
+use glutin::prelude::*;
+use image::{ImageError, RgbaImage};
+
+use crate::engine::config::EngineConfig;
+use crate::engine::input::record::InputEvent;
+use crate::engine::layer::LayerStack;
+use crate::engine::{create_context, EngineContext};
+use crate::render::target::Target;
+use crate::window::Viewport;
+use crate::Layer;
+
+/// Set to overwrite reference images with the current output instead of
+/// comparing against them
+pub const BLESS_ENV: &str = "ALGURULGAR_BLESS";
+
+/// Set to let gpu tests pass without drawing on machines without an egl
+/// device, a missing device is an error otherwise
+pub const SKIP_GPU_ENV: &str = "ALGURULGAR_SKIP_GPU";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+  #[error("no egl device available")]
+  NoDevice,
+  #[error("no matching gl config")]
+  NoConfig,
+  #[error(transparent)]
+  Glutin(#[from] glutin::error::Error),
+  #[error(transparent)]
+  IncompatibleOpenGl(#[from] IncompatibleOpenGl),
+  #[error(transparent)]
+  TextureCreation(#[from] TextureCreationError),
+  #[error(transparent)]
+  RenderBufferCreation(#[from] RenderBufferCreationError),
+  #[error(transparent)]
+  Validation(#[from] ValidationError),
+  #[error(transparent)]
+  Image(#[from] ImageError),
+  #[error(transparent)]
+  Io(#[from] io::Error),
+  #[error("missing reference image {0}, run with {BLESS_ENV}=1 to create it")]
+  MissingReference(PathBuf),
+  #[error("image is {actual:?} but reference {path} is {expected:?}")]
+  SizeMismatch {
+    path: PathBuf,
+    expected: (u32, u32),
+    actual: (u32, u32),
+  },
+  #[error("{differing} pixels differ from reference {path}")]
+  Mismatch { path: PathBuf, differing: usize },
+}
+
+/// Runs layers without a window, drawing into an offscreen framebuffer
+pub struct Headless<T: 'static = ()> {
+  pub layers: LayerStack<T>,
+  pub context: EngineContext<T>,
+
+  color: Texture2d,
+  depth: DepthRenderBuffer,
+  renderer: HeadlessRenderer,
+}
+
+impl Headless {
+  /// Only the size, gl version and clear color of `config` are used
+  pub fn new(config: EngineConfig, layers: Vec<Box<dyn Layer>>) -> Result<Self, Error> {
+    Self::with_user_event(config, layers)
+  }
+}
+
+impl<T: 'static> Headless<T> {
+  /// Headless runner for layers handling user events of type `T`, there is no
+  /// event loop so they are sent with [`Headless::send_user_event`]
+  pub fn with_user_event(config: EngineConfig, layers: Vec<Box<dyn Layer<T>>>) -> Result<Self, Error> {
+    let renderer = create_renderer(config.gl_version)?;
+
+    let size = config.size.unwrap_or((800, 600));
+    let (width, height) = size;
+
+    let color = Texture2d::empty_with_format(
+      &renderer,
+      UncompressedFloatFormat::U8U8U8U8,
+      MipmapsOption::NoMipmap,
+      width,
+      height,
+    )?;
+    let depth = DepthRenderBuffer::new(&renderer, DepthFormat::I24, width, height)?;
+
+    let mut context = create_context(&renderer, config);
+    context.viewport = Viewport::from(size);
+
+    let mut layers = LayerStack::new(layers);
+    layers.apply_commands(&mut context);
+
+    Ok(Self {
+      layers,
+      context,
+      color,
+      depth,
+      renderer,
+    })
+  }
+
+  /// Update and draw every layer once with a fixed `delta_time` and read the
+  /// result back
+  ///
+  /// While [`EngineContext::input_replay`] is playing the recorded
+  /// `delta_time` and input are used instead
+  pub fn frame(&mut self, delta_time: f32) -> Result<RgbaImage, Error> {
+    self.layers.update_frame(&mut self.context, delta_time);
+    // no window to apply them to
+    self.context.window_commands.clear();
+
+    let profiler = self.context.profiler.clone();
+
+    let framebuffer = SimpleFrameBuffer::with_depth_buffer(&self.renderer, &self.color, &self.depth)?;
+    let mut target = Target::Offscreen(framebuffer);
+
+    self.layers.draw_frame(&mut self.context, &mut target, &self.renderer);
+
+    // no egui overlay, captures with it see the same frame
+    self.context.capture.capture(&target, &self.renderer, true);
+
+    drop(target);
+
+    let image = {
+      let _scope = profiler.scope("read_pixels");
+      self.read_pixels()
+    };
+
+    profiler.end_frame();
+
+    Ok(image)
+  }
+
+  /// Input as if it came from a window, recorded while
+  /// [`EngineContext::input_replay`] records and ignored while it plays
+  pub fn send_input(&mut self, event: InputEvent) {
+    self.context.input_replay.live_event(&mut self.context.input, event);
+  }
+
+  /// Dispatch a user event right away, topmost layer first
+  pub fn send_user_event(&mut self, event: T) {
+    for layer in self.layers.iter_mut().rev() {
+      if layer.handle_user_event(&mut self.context, &event) {
+        break; // event was consumed
+      }
+    }
+
+    self.layers.apply_commands(&mut self.context);
+  }
+
+  /// Read the offscreen color buffer, top row first
+  pub fn read_pixels(&self) -> RgbaImage {
+    let raw: RawImage2d<u8> = self.color.read();
+
+    let mut image = RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned()).unwrap();
+
+    // gl reads bottom row first
+    image::imageops::flip_vertical_in_place(&mut image);
+
+    image
+  }
+
+  #[inline]
+  pub fn size(&self) -> (u32, u32) {
+    self.color.dimensions()
+  }
+}
+
+fn create_renderer((major, minor): (u8, u8)) -> Result<HeadlessRenderer, Error> {
+  let device = Device::query_devices()?.next().ok_or(Error::NoDevice)?;
+  let display = unsafe { EglDisplay::with_device(&device, None)? };
+
+  let template = ConfigTemplateBuilder::new()
+    .with_api(Api::OPENGL)
+    .with_surface_type(ConfigSurfaceTypes::empty())
+    .build();
+
+  let config = unsafe { display.find_configs(template)? }
+    .next()
+    .ok_or(Error::NoConfig)?;
+
+  let context_attributes = ContextAttributesBuilder::new()
+    .with_context_api(ContextApi::OpenGl(Some(Version::new(major, minor))))
+    .build(None);
+
+  let context = unsafe { display.create_context(&config, &context_attributes)? }.make_current_surfaceless()?;
+
+  Ok(HeadlessRenderer::new(PossiblyCurrentContext::Egl(context))?)
+}
+
+/// Compare `image` against the reference png at `path`
+///
+/// Channels within `tolerance` of the reference count as equal. On mismatch
+/// the image is written next to the reference as `<name>.actual.png`
+pub fn compare_golden(image: &RgbaImage, path: impl AsRef<Path>, tolerance: u8) -> Result<(), Error> {
+  let path = path.as_ref();
+
+  if env::var_os(BLESS_ENV).is_some() {
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+
+    image.save(path)?;
+    info!("wrote reference image {}", path.display());
+
+    return Ok(());
+  }
+
+  if !path.exists() {
+    return Err(Error::MissingReference(path.to_path_buf()));
+  }
+
+  let reference = image::open(path)?.into_rgba8();
+
+  if reference.dimensions() != image.dimensions() {
+    return Err(Error::SizeMismatch {
+      path: path.to_path_buf(),
+      expected: reference.dimensions(),
+      actual: image.dimensions(),
+    });
+  }
+
+  let differing = image
+    .pixels()
+    .zip(reference.pixels())
+    .filter(|(a, b)| a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > tolerance))
+    .count();
+
+  if differing > 0 {
+    image.save(path.with_extension("actual.png"))?;
+
+    return Err(Error::Mismatch {
+      path: path.to_path_buf(),
+      differing,
+    });
+  }
+
+  Ok(())
+}
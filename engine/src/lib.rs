@@ -7,12 +7,15 @@ extern crate log;
 use std::env;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+pub use engine::config::{CursorMode, EngineConfig, VsyncMode};
 pub use engine::layer::Layer;
 pub use engine::{Engine, EngineContext};
 pub use log::{debug, error, info, trace, warn};
 pub use math::*;
 use pretty_env_logger::init;
 pub use render::camera::ortho::{OrthoCamera, OrthoCameraController};
+pub use render::gpu::Gpu;
+pub use debug::profiler::Profiler;
 pub use {glium, glutin, nalgebra, winit, egui};
 
 #[allow(unused_imports)]
@@ -24,14 +27,13 @@ pub mod render;
 #[macro_use]
 pub mod macros;
 pub mod engine;
+pub mod headless;
 pub mod update;
 pub mod window;
 
 pub mod mesh;
 pub mod debug;
 
-pub const VSYNC: bool = true;
-
 pub fn init_logger() {
   if env::var("RUST_LOG").is_err() {
     env::set_var("RUST_LOG", "info");
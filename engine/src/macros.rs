@@ -4,3 +4,10 @@ macro_rules! shader {
     $crate::render::shader::Shader::new($display, include_str!($vert), include_str!($frag))
   };
 }
+
+#[macro_export]
+macro_rules! shader_source {
+  ($vert:expr, $frag:expr) => {
+    $crate::render::shader::ShaderSource::new(include_str!($vert), include_str!($frag))
+  };
+}
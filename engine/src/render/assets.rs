@@ -0,0 +1,326 @@
+// gpu resources that outlive the gl context
+// sources stay on the cpu so everything can be uploaded again after the
+// context is lost on suspend
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use glium::index::PrimitiveType;
+use glium::texture::RawImage2d;
+use glium::{IndexBuffer, Texture2d, VertexBuffer};
+
+use crate::render::gpu::Gpu;
+use crate::render::renderer::mesh::{Mesh, Vertex};
+use crate::render::shader::preprocess::Defines;
+use crate::render::shader::variants::ShaderVariants;
+use crate::render::shader::{self, Shader, ShaderSource};
+
+/// Index into [`Assets`], only valid for the registry that returned it
+pub struct Handle<T> {
+  index: usize,
+  marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+  fn new(index: usize) -> Self {
+    Self {
+      index,
+      marker: PhantomData,
+    }
+  }
+}
+
+impl<T> Clone for Handle<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.index == other.index
+  }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.index.hash(state);
+  }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("Handle").field(&self.index).finish()
+  }
+}
+
+/// Rgba pixels for [`Assets::add_texture`]
+#[derive(Debug, Clone)]
+pub struct TextureSource {
+  pub rgba: Vec<u8>,
+  pub size: (u32, u32),
+}
+
+/// Uploaded [`Mesh`]
+pub struct GpuMesh {
+  pub vertex_buffer: VertexBuffer<Vertex>,
+  pub index_buffer: IndexBuffer<u16>,
+}
+
+trait Upload: Sized {
+  type Source;
+
+  fn upload(source: &Self::Source, gpu: &Gpu) -> Self;
+}
+
+impl Upload for Texture2d {
+  type Source = TextureSource;
+
+  fn upload(source: &TextureSource, gpu: &Gpu) -> Self {
+    let image = RawImage2d::from_raw_rgba(source.rgba.clone(), source.size);
+
+    Texture2d::new(gpu, image).unwrap()
+  }
+}
+
+impl Upload for GpuMesh {
+  type Source = Mesh;
+
+  fn upload(mesh: &Mesh, gpu: &Gpu) -> Self {
+    Self {
+      vertex_buffer: VertexBuffer::new(gpu, &mesh.vertices).unwrap(),
+      index_buffer: IndexBuffer::new(gpu, PrimitiveType::TrianglesList, &mesh.indices).unwrap(),
+    }
+  }
+}
+
+impl Upload for Shader {
+  type Source = ShaderSource;
+
+  fn upload(source: &ShaderSource, gpu: &Gpu) -> Self {
+    source.compile(gpu)
+  }
+}
+
+// variants compile lazily, a new context starts with none of them
+impl Upload for ShaderVariants {
+  type Source = ShaderSource;
+
+  fn upload(source: &ShaderSource, _gpu: &Gpu) -> Self {
+    ShaderVariants::new(source.clone())
+  }
+}
+
+struct Entry<T: Upload> {
+  source: T::Source,
+  /// `None` while the context is lost
+  uploaded: Option<T>,
+}
+
+struct Storage<T: Upload> {
+  entries: Vec<Entry<T>>,
+}
+
+impl<T: Upload> Storage<T> {
+  fn new() -> Self {
+    Self { entries: Vec::new() }
+  }
+
+  fn add(&mut self, source: T::Source, gpu: Option<&Gpu>) -> Handle<T> {
+    let uploaded = gpu.map(|gpu| T::upload(&source, gpu));
+
+    self.entries.push(Entry { source, uploaded });
+
+    Handle::new(self.entries.len() - 1)
+  }
+
+  fn get(&self, handle: Handle<T>) -> &T {
+    self.entries[handle.index]
+      .uploaded
+      .as_ref()
+      .expect("gl context is lost")
+  }
+
+  fn get_mut(&mut self, handle: Handle<T>) -> &mut T {
+    self.entries[handle.index]
+      .uploaded
+      .as_mut()
+      .expect("gl context is lost")
+  }
+
+  fn source(&self, handle: Handle<T>) -> &T::Source {
+    &self.entries[handle.index].source
+  }
+
+  fn release(&mut self) {
+    for entry in &mut self.entries {
+      entry.uploaded = None;
+    }
+  }
+
+  fn restore(&mut self, gpu: &Gpu) {
+    for entry in &mut self.entries {
+      entry.uploaded = Some(T::upload(&entry.source, gpu));
+    }
+  }
+}
+
+impl Storage<Shader> {
+  fn reload(&mut self, gpu: &Gpu) {
+    for entry in &mut self.entries {
+      if let Some(shader) = &mut entry.uploaded {
+        entry.source.reload(gpu, shader);
+      }
+    }
+  }
+}
+
+impl Storage<ShaderVariants> {
+  // the source is kept in sync so a restore compiles the reloaded one
+  fn reload(&mut self, gpu: &Gpu) {
+    for entry in &mut self.entries {
+      if let Some(variants) = &mut entry.uploaded {
+        if variants.reload(gpu) {
+          entry.source = variants.source().clone();
+        }
+      }
+    }
+  }
+}
+
+/// Textures, meshes and shaders that are uploaded again when the gl context
+/// is restored, handles stay valid across the whole cycle
+///
+/// Adding while the context is lost is fine, the upload happens on restore
+pub struct Assets {
+  gpu: Option<Gpu>,
+  textures: Storage<Texture2d>,
+  meshes: Storage<GpuMesh>,
+  shaders: Storage<Shader>,
+  shader_variants: Storage<ShaderVariants>,
+}
+
+impl Assets {
+  pub fn new(gpu: Gpu) -> Self {
+    Self {
+      gpu: Some(gpu),
+      textures: Storage::new(),
+      meshes: Storage::new(),
+      shaders: Storage::new(),
+      shader_variants: Storage::new(),
+    }
+  }
+
+  pub fn add_texture(&mut self, rgba: Vec<u8>, size: (u32, u32)) -> Handle<Texture2d> {
+    self.textures.add(TextureSource { rgba, size }, self.gpu.as_ref())
+  }
+
+  pub fn add_mesh(&mut self, mesh: Mesh) -> Handle<GpuMesh> {
+    self.meshes.add(mesh, self.gpu.as_ref())
+  }
+
+  pub fn add_shader(&mut self, source: ShaderSource) -> Handle<Shader> {
+    self.shaders.add(source, self.gpu.as_ref())
+  }
+
+  /// Permutations of `source`, see [`Assets::shader_variant`]
+  pub fn add_shader_variants(&mut self, source: ShaderSource) -> Handle<ShaderVariants> {
+    self.shader_variants.add(source, self.gpu.as_ref())
+  }
+
+  /// Panics while the context is lost
+  #[inline]
+  pub fn texture(&self, handle: Handle<Texture2d>) -> &Texture2d {
+    self.textures.get(handle)
+  }
+
+  /// Panics while the context is lost
+  #[inline]
+  pub fn mesh(&self, handle: Handle<GpuMesh>) -> &GpuMesh {
+    self.meshes.get(handle)
+  }
+
+  /// Panics while the context is lost
+  #[inline]
+  pub fn shader(&self, handle: Handle<Shader>) -> &Shader {
+    self.shaders.get(handle)
+  }
+
+  /// The variant of `handle` with `defines` added, compiled on first use
+  /// and again after the context is restored. Panics while the context is
+  /// lost
+  pub fn shader_variant(
+    &mut self,
+    handle: Handle<ShaderVariants>,
+    defines: &Defines,
+  ) -> Result<&Shader, shader::Error> {
+    let gpu = self.gpu.as_ref().expect("gl context is lost");
+
+    self.shader_variants.get_mut(handle).get(gpu, defines)
+  }
+
+  #[inline]
+  pub fn texture_source(&self, handle: Handle<Texture2d>) -> &TextureSource {
+    self.textures.source(handle)
+  }
+
+  #[inline]
+  pub fn mesh_source(&self, handle: Handle<GpuMesh>) -> &Mesh {
+    self.meshes.source(handle)
+  }
+
+  #[inline]
+  pub fn shader_source(&self, handle: Handle<Shader>) -> &ShaderSource {
+    self.shaders.source(handle)
+  }
+
+  #[inline]
+  pub fn shader_variants_source(&self, handle: Handle<ShaderVariants>) -> &ShaderSource {
+    self.shader_variants.source(handle)
+  }
+
+  /// Panics while the context is lost
+  #[inline]
+  pub fn gpu(&self) -> &Gpu {
+    self.gpu.as_ref().expect("gl context is lost")
+  }
+
+  #[inline]
+  pub fn is_lost(&self) -> bool {
+    self.gpu.is_none()
+  }
+
+  /// Recompile every shader whose files changed, see
+  /// [`ShaderSource::with_files`]
+  pub fn reload_shaders(&mut self) {
+    if let Some(gpu) = &self.gpu {
+      self.shaders.reload(gpu);
+      self.shader_variants.reload(gpu);
+    }
+  }
+
+  /// Drop every uploaded object and the gpu handle
+  pub(crate) fn release(&mut self) {
+    self.textures.release();
+    self.meshes.release();
+    self.shaders.release();
+    self.shader_variants.release();
+
+    self.gpu = None;
+  }
+
+  /// Upload everything to a new context
+  pub(crate) fn restore(&mut self, gpu: Gpu) {
+    self.textures.restore(&gpu);
+    self.meshes.restore(&gpu);
+    self.shaders.restore(&gpu);
+    self.shader_variants.restore(&gpu);
+
+    self.gpu = Some(gpu);
+  }
+}
@@ -0,0 +1,334 @@
+use std::ops::Deref;
+use std::rc::Rc;
+
+use glium::backend::{Context, Facade};
+use glium::draw_parameters::TimeElapsedQuery;
+use glium::index::PrimitiveType;
+use glium::texture::RawImage2d;
+use glium::uniforms::{MagnifySamplerFilter, Uniforms};
+use glium::{uniform, DrawParameters, IndexBuffer, Surface, Texture2d, VertexBuffer};
+use nalgebra::Matrix4;
+
+use crate::debug::profiler::Profiler;
+use crate::math::ViewProjection;
+use crate::render::backend::Backend;
+use crate::render::renderer::mesh::{self, Vertex, TRI_INDEX_ARRAY};
+use crate::render::renderer2d::quad::QuadVertex;
+use crate::render::renderer2d::text::TextVertex;
+use crate::render::renderer2d::{self, QUAD_INDEX_ARRAY};
+use crate::render::shader::{Shader, ShaderSource, DRAW_PARAMETERS};
+use crate::render::target::Target;
+use crate::window::Viewport;
+
+struct Pipeline<V: Copy + glium::Vertex> {
+  vertex_buffer: VertexBuffer<V>,
+  index_buffer: IndexBuffer<u16>,
+  shader: Shader,
+}
+
+impl<V: Copy + glium::Vertex> Pipeline<V> {
+  fn new<F: Facade>(facade: &F, shader: Shader, max_vertices: usize, indices: &[u16]) -> Self {
+    let vertex_buffer = VertexBuffer::empty_dynamic(facade, max_vertices).unwrap();
+    let index_buffer = IndexBuffer::new(facade, PrimitiveType::TrianglesList, indices).unwrap();
+
+    Self {
+      vertex_buffer,
+      index_buffer,
+      shader,
+    }
+  }
+}
+
+/// Everything needed to build a [`Pipeline`] again after the context is lost
+struct Slot<V: Copy + glium::Vertex> {
+  source: ShaderSource,
+  max_vertices: usize,
+  indices: &'static [u16],
+  pipeline: Option<Pipeline<V>>,
+}
+
+impl<V: Copy + glium::Vertex> Slot<V> {
+  fn new(context: &Rc<Context>, source: ShaderSource, max_vertices: usize, indices: &'static [u16]) -> Self {
+    let mut slot = Self {
+      source,
+      max_vertices,
+      indices,
+      pipeline: None,
+    };
+    slot.restore(context);
+
+    slot
+  }
+
+  fn restore(&mut self, context: &Rc<Context>) {
+    let shader = self.source.load(context);
+
+    self.pipeline = Some(Pipeline::new(context, shader, self.max_vertices, self.indices));
+  }
+
+  fn reload(&mut self, context: &Rc<Context>) {
+    if let Some(pipeline) = &mut self.pipeline {
+      self.source.reload(context, &mut pipeline.shader);
+    }
+  }
+
+  fn pipeline(&mut self) -> &mut Pipeline<V> {
+    self.pipeline.as_mut().expect("gl context is lost")
+  }
+}
+
+/// OpenGL backend through glium
+///
+/// Pipelines are added with the `with_*_shader` methods, drawing with a
+/// pipeline that was never added panics. Shader sources are kept so
+/// [`GlBackend::restore`] can rebuild everything after [`GlBackend::release`]
+pub struct GlBackend {
+  context: Option<Rc<Context>>,
+  viewport: Option<Viewport>,
+  /// Draws are timed under this name while the profiler is enabled
+  profiler: Option<(Profiler, &'static str)>,
+  quad: Option<Slot<QuadVertex>>,
+  text: Option<Slot<TextVertex>>,
+  mesh: Option<Slot<Vertex>>,
+}
+
+impl GlBackend {
+  pub fn new<F: Facade>(facade: &F) -> Self {
+    Self {
+      context: Some(facade.get_context().clone()),
+      viewport: None,
+      profiler: None,
+      quad: None,
+      text: None,
+      mesh: None,
+    }
+  }
+
+  pub fn with_quad_shader(mut self, source: ShaderSource) -> Self {
+    self.quad = Some(Slot::new(
+      self.context(),
+      source,
+      renderer2d::MAX_VERTICES,
+      &QUAD_INDEX_ARRAY,
+    ));
+    self
+  }
+
+  pub fn with_text_shader(mut self, source: ShaderSource) -> Self {
+    self.text = Some(Slot::new(
+      self.context(),
+      source,
+      renderer2d::MAX_VERTICES,
+      &QUAD_INDEX_ARRAY,
+    ));
+    self
+  }
+
+  pub fn with_mesh_shader(mut self, source: ShaderSource) -> Self {
+    self.mesh = Some(Slot::new(self.context(), source, mesh::MAX_VERTICES, &TRI_INDEX_ARRAY));
+    self
+  }
+
+  /// Time every draw on the gpu as `name`, see [`Profiler::gpu_query`]
+  pub fn with_profiler(mut self, profiler: Profiler, name: &'static str) -> Self {
+    self.profiler = Some((profiler, name));
+    self
+  }
+
+  /// Panics while the context is lost
+  #[inline]
+  pub fn context(&self) -> &Rc<Context> {
+    self.context.as_ref().expect("gl context is lost")
+  }
+
+  #[inline]
+  pub fn is_lost(&self) -> bool {
+    self.context.is_none()
+  }
+
+  /// Drop every gl object and the context itself, drawing panics until
+  /// [`GlBackend::restore`]
+  pub fn release(&mut self) {
+    if let Some(quad) = &mut self.quad {
+      quad.pipeline = None;
+    }
+    if let Some(text) = &mut self.text {
+      text.pipeline = None;
+    }
+    if let Some(mesh) = &mut self.mesh {
+      mesh.pipeline = None;
+    }
+
+    self.context = None;
+  }
+
+  /// Rebuild every pipeline on a new context
+  pub fn restore<F: Facade>(&mut self, facade: &F) {
+    let context = facade.get_context().clone();
+
+    if let Some(quad) = &mut self.quad {
+      quad.restore(&context);
+    }
+    if let Some(text) = &mut self.text {
+      text.restore(&context);
+    }
+    if let Some(mesh) = &mut self.mesh {
+      mesh.restore(&context);
+    }
+
+    self.context = Some(context);
+  }
+
+  /// Recompile every pipeline whose shader files changed, see
+  /// [`ShaderSource::with_files`]
+  pub fn reload_shaders(&mut self) {
+    let Some(context) = self.context.clone() else {
+      return;
+    };
+
+    if let Some(quad) = &mut self.quad {
+      quad.reload(&context);
+    }
+    if let Some(text) = &mut self.text {
+      text.reload(&context);
+    }
+    if let Some(mesh) = &mut self.mesh {
+      mesh.reload(&context);
+    }
+  }
+
+  fn gpu_query(&self) -> Option<Rc<TimeElapsedQuery>> {
+    let (profiler, name) = self.profiler.as_ref()?;
+
+    profiler.gpu_query(self.context(), *name)
+  }
+}
+
+impl Backend for GlBackend {
+  type Target<'t> = Target<'t>;
+  type Texture = Texture2d;
+
+  fn create_texture(&self, rgba: Vec<u8>, size: (u32, u32)) -> Texture2d {
+    let image = RawImage2d::from_raw_rgba(rgba, size);
+
+    Texture2d::new(self.context(), image).unwrap()
+  }
+
+  #[inline]
+  fn set_viewport(&mut self, viewport: Option<Viewport>) {
+    self.viewport = viewport;
+  }
+
+  fn draw_quads(&mut self, target: &mut Target<'_>, vertices: &[QuadVertex], view_projection: &ViewProjection) {
+    let query = self.gpu_query();
+    let pipeline = self.quad.as_mut().expect("gl backend has no quad shader").pipeline();
+
+    let uniforms = uniform! {
+      u_view_projection: *view_projection.as_ref(),
+    };
+
+    copy_and_draw(
+      pipeline,
+      vertices,
+      vertices.len() / 4 * 6,
+      self.viewport,
+      query.as_deref(),
+      target,
+      &uniforms,
+    );
+  }
+
+  fn draw_text(
+    &mut self,
+    target: &mut Target<'_>,
+    vertices: &[TextVertex],
+    atlas: &Texture2d,
+    view_projection: &ViewProjection,
+  ) {
+    let query = self.gpu_query();
+    let pipeline = self.text.as_mut().expect("gl backend has no text shader").pipeline();
+
+    let uniforms = uniform! {
+      u_font_atlas: atlas.sampled().magnify_filter(MagnifySamplerFilter::Nearest),
+      u_view_projection: *view_projection.as_ref(),
+    };
+
+    copy_and_draw(
+      pipeline,
+      vertices,
+      vertices.len() / 4 * 6,
+      self.viewport,
+      query.as_deref(),
+      target,
+      &uniforms,
+    );
+  }
+
+  fn draw_mesh(
+    &mut self,
+    target: &mut Target<'_>,
+    vertices: &[Vertex],
+    indices: &[u16],
+    view_projection: &ViewProjection,
+    model: &Matrix4<f32>,
+  ) {
+    let query = self.gpu_query();
+    let pipeline = self.mesh.as_mut().expect("gl backend has no mesh shader").pipeline();
+
+    pipeline.index_buffer.slice(0..indices.len()).unwrap().write(indices);
+
+    let uniforms = uniform! {
+      u_view_projection: *view_projection.as_ref(),
+      u_model: *model.as_ref(),
+    };
+
+    copy_and_draw(
+      pipeline,
+      vertices,
+      indices.len(),
+      self.viewport,
+      query.as_deref(),
+      target,
+      &uniforms,
+    );
+  }
+}
+
+fn copy_and_draw<V: Copy + glium::Vertex, U: Uniforms>(
+  pipeline: &mut Pipeline<V>,
+  vertices: &[V],
+  index_count: usize,
+  viewport: Option<Viewport>,
+  time_elapsed_query: Option<&TimeElapsedQuery>,
+  target: &mut Target<'_>,
+  uniforms: &U,
+) {
+  let vertex_buffer = &mut pipeline.vertex_buffer;
+
+  if vertices.len() == vertex_buffer.len() {
+    vertex_buffer.write(vertices);
+  } else {
+    vertex_buffer.invalidate();
+
+    unsafe {
+      vertex_buffer
+        .slice_mut(0..vertices.len())
+        .unwrap_unchecked()
+        .write(vertices);
+    }
+  }
+
+  target
+    .draw(
+      vertex_buffer.deref(),
+      pipeline.index_buffer.slice(0..index_count).unwrap(),
+      pipeline.shader.program(),
+      uniforms,
+      &DrawParameters {
+        viewport: viewport.map(|viewport| viewport.rect()),
+        time_elapsed_query,
+        ..DRAW_PARAMETERS
+      },
+    )
+    .unwrap();
+}
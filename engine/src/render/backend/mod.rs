@@ -0,0 +1,49 @@
+// renderers batch vertices on the cpu and hand them to a backend to draw
+// gl goes through glium, software rasterizes on the cpu without a gl context
+
+use nalgebra::Matrix4;
+
+use crate::math::ViewProjection;
+use crate::render::renderer::mesh::Vertex;
+use crate::render::renderer2d::quad::QuadVertex;
+use crate::render::renderer2d::text::TextVertex;
+use crate::window::Viewport;
+
+pub mod gl;
+pub mod software;
+
+pub trait Backend {
+  /// Surface the backend draws into
+  type Target<'t>;
+
+  /// Texture sampled by the text pipeline
+  type Texture;
+
+  /// Create a texture from tightly packed rgba rows, first row is `v = 0`
+  fn create_texture(&self, rgba: Vec<u8>, size: (u32, u32)) -> Self::Texture;
+
+  /// Restrict the following draws to `viewport`, `None` for the whole target
+  fn set_viewport(&mut self, viewport: Option<Viewport>);
+
+  /// Draw `vertices` as quads, 4 vertices per quad
+  fn draw_quads(&mut self, target: &mut Self::Target<'_>, vertices: &[QuadVertex], view_projection: &ViewProjection);
+
+  /// Draw `vertices` as glyph quads, 4 vertices per quad, sampling `atlas`
+  fn draw_text(
+    &mut self,
+    target: &mut Self::Target<'_>,
+    vertices: &[TextVertex],
+    atlas: &Self::Texture,
+    view_projection: &ViewProjection,
+  );
+
+  /// Draw an indexed triangle list
+  fn draw_mesh(
+    &mut self,
+    target: &mut Self::Target<'_>,
+    vertices: &[Vertex],
+    indices: &[u16],
+    view_projection: &ViewProjection,
+    model: &Matrix4<f32>,
+  );
+}
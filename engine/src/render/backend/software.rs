@@ -0,0 +1,698 @@
+// cpu rasterizer, deterministic output for tests without a gl context
+// mirrors the gl pipelines: alpha blending, no depth test, no culling, nearest
+// texture sampling
+
+use image::RgbaImage;
+use nalgebra::Matrix4;
+
+use crate::math::{vec2, vec3, vec4, Color, Vec2, Vec3, Vec4, ViewProjection};
+use crate::render::backend::Backend;
+use crate::render::renderer::mesh::Vertex;
+use crate::render::renderer2d::quad::QuadVertex;
+use crate::render::renderer2d::text::TextVertex;
+use crate::render::renderer2d::QUAD_INDICES;
+use crate::window::Viewport;
+
+/// Color buffer the [`SoftwareBackend`] draws into, top row first
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+  width: u32,
+  height: u32,
+  pixels: Vec<[u8; 4]>,
+}
+
+impl Framebuffer {
+  pub fn new(width: u32, height: u32) -> Self {
+    Self {
+      width,
+      height,
+      pixels: vec![[0; 4]; (width * height) as usize],
+    }
+  }
+
+  pub fn clear(&mut self, color: Color) {
+    self.pixels.fill(color.into());
+  }
+
+  #[inline]
+  pub fn dimensions(&self) -> (u32, u32) {
+    (self.width, self.height)
+  }
+
+  #[inline]
+  pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+    self.pixels[(y * self.width + x) as usize]
+  }
+
+  #[inline]
+  pub fn pixels(&self) -> &[[u8; 4]] {
+    &self.pixels
+  }
+
+  pub fn to_image(&self) -> RgbaImage {
+    let data = self.pixels.iter().flatten().copied().collect();
+
+    RgbaImage::from_raw(self.width, self.height, data).unwrap()
+  }
+
+  /// `SourceAlpha, OneMinusSourceAlpha` for color and alpha like
+  /// [`DRAW_PARAMETERS`](crate::render::shader::DRAW_PARAMETERS)
+  fn blend(&mut self, x: u32, y: u32, source: Vec4) {
+    let pixel = &mut self.pixels[(y * self.width + x) as usize];
+
+    let alpha = source.w.clamp(0.0, 1.0);
+
+    for i in 0..4 {
+      let destination = pixel[i] as f32 / 255.0;
+      let value = source[i].clamp(0.0, 1.0) * alpha + destination * (1.0 - alpha);
+
+      pixel[i] = (value * 255.0).round() as u8;
+    }
+  }
+}
+
+/// Rgba texture sampled by the [`SoftwareBackend`]
+#[derive(Debug, Clone)]
+pub struct SoftwareTexture {
+  width: u32,
+  height: u32,
+  pixels: Vec<u8>,
+}
+
+impl SoftwareTexture {
+  /// Nearest sample with clamped coordinates
+  pub fn sample(&self, tex_coords: Vec2) -> Vec4 {
+    let x = ((tex_coords.x * self.width as f32) as i64).clamp(0, self.width as i64 - 1) as usize;
+    let y = ((tex_coords.y * self.height as f32) as i64).clamp(0, self.height as i64 - 1) as usize;
+
+    let i = (y * self.width as usize + x) * 4;
+    let [r, g, b, a] = [
+      self.pixels[i],
+      self.pixels[i + 1],
+      self.pixels[i + 2],
+      self.pixels[i + 3],
+    ];
+
+    vec4(r as f32, g as f32, b as f32, a as f32) / 255.0
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct SoftwareBackend {
+  viewport: Option<Viewport>,
+}
+
+impl SoftwareBackend {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl Backend for SoftwareBackend {
+  type Target<'t> = Framebuffer;
+  type Texture = SoftwareTexture;
+
+  fn create_texture(&self, rgba: Vec<u8>, (width, height): (u32, u32)) -> SoftwareTexture {
+    debug_assert_eq!(rgba.len(), (width * height * 4) as usize);
+
+    SoftwareTexture {
+      width,
+      height,
+      pixels: rgba,
+    }
+  }
+
+  #[inline]
+  fn set_viewport(&mut self, viewport: Option<Viewport>) {
+    self.viewport = viewport;
+  }
+
+  fn draw_quads(&mut self, target: &mut Framebuffer, vertices: &[QuadVertex], view_projection: &ViewProjection) {
+    let vertices = vertices
+      .iter()
+      .map(|v| ClipVertex {
+        // quad.vert uses z = 1
+        position: view_projection * vec4(v.position[0], v.position[1], 1.0, 1.0),
+        color: v.color.into(),
+        tex_coords: v.tex_coords.into(),
+      })
+      .collect::<Vec<_>>();
+
+    let region = Region::new(target, self.viewport);
+
+    draw_quad_list(target, region, &vertices, |color, _| color);
+  }
+
+  fn draw_text(
+    &mut self,
+    target: &mut Framebuffer,
+    vertices: &[TextVertex],
+    atlas: &SoftwareTexture,
+    view_projection: &ViewProjection,
+  ) {
+    let vertices = vertices
+      .iter()
+      .map(|v| ClipVertex {
+        position: view_projection * vec4(v.position[0], v.position[1], 0.0, 1.0),
+        color: v.color.into(),
+        tex_coords: v.tex_coords.into(),
+      })
+      .collect::<Vec<_>>();
+
+    let region = Region::new(target, self.viewport);
+
+    draw_quad_list(target, region, &vertices, |color, tex_coords| {
+      color.component_mul(&atlas.sample(tex_coords))
+    });
+  }
+
+  fn draw_mesh(
+    &mut self,
+    target: &mut Framebuffer,
+    vertices: &[Vertex],
+    indices: &[u16],
+    view_projection: &ViewProjection,
+    model: &Matrix4<f32>,
+  ) {
+    let transform = view_projection * model;
+
+    let vertices = vertices
+      .iter()
+      .map(|v| ClipVertex {
+        position: transform * vec4(v.position[0], v.position[1], v.position[2], 1.0),
+        color: v.color.into(),
+        tex_coords: Vec2::zeros(),
+      })
+      .collect::<Vec<_>>();
+
+    let region = Region::new(target, self.viewport);
+
+    for triangle in indices.array_chunks::<3>() {
+      // gl doesn't define what an out of range index draws, leave the
+      // triangle out instead of panicking
+      let [Some(a), Some(b), Some(c)] = triangle.map(|i| vertices.get(i as usize).copied()) else {
+        continue;
+      };
+
+      rasterize(target, region, [a, b, c], |color, _| color);
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ClipVertex {
+  position: Vec4,
+  color: Vec4,
+  tex_coords: Vec2,
+}
+
+/// Pixels covered by the viewport, top left origin
+#[derive(Debug, Clone, Copy)]
+struct Region {
+  left: f32,
+  top: f32,
+  width: f32,
+  height: f32,
+}
+
+impl Region {
+  fn new(target: &Framebuffer, viewport: Option<Viewport>) -> Self {
+    let viewport = viewport.unwrap_or(Viewport::new(target.width, target.height));
+
+    Self {
+      left: viewport.x as f32,
+      // viewports count from the bottom like gl
+      top: target.height as f32 - (viewport.y + viewport.height) as f32,
+      width: viewport.width as f32,
+      height: viewport.height as f32,
+    }
+  }
+}
+
+fn draw_quad_list(
+  target: &mut Framebuffer,
+  region: Region,
+  vertices: &[ClipVertex],
+  fragment: impl Fn(Vec4, Vec2) -> Vec4,
+) {
+  for quad in vertices.array_chunks::<4>() {
+    for [a, b, c] in QUAD_INDICES.array_chunks::<3>() {
+      let triangle = [quad[*a as usize], quad[*b as usize], quad[*c as usize]];
+
+      rasterize(target, region, triangle, &fragment);
+    }
+  }
+}
+
+fn rasterize(
+  target: &mut Framebuffer,
+  region: Region,
+  mut triangle: [ClipVertex; 3],
+  fragment: impl Fn(Vec4, Vec2) -> Vec4,
+) {
+  // nothing we draw crosses the camera plane so there is no clipping, just
+  // drop anything behind it
+  if triangle.iter().any(|v| v.position.w <= 0.0) {
+    return;
+  }
+
+  let Region {
+    left,
+    top,
+    width,
+    height,
+  } = region;
+
+  // screen space with the origin at the top left, z holds 1 / w for
+  // perspective correct interpolation
+  let mut screen = triangle.map(|v| {
+    let inv_w = 1.0 / v.position.w;
+
+    vec3(
+      left + (v.position.x * inv_w + 1.0) * 0.5 * width,
+      top + (1.0 - v.position.y * inv_w) * 0.5 * height,
+      inv_w,
+    )
+  });
+
+  let mut area = edge(screen[0], screen[1], screen[2]);
+
+  if area == 0.0 {
+    return;
+  }
+
+  // no culling, flip to a consistent winding instead
+  if area < 0.0 {
+    screen.swap(1, 2);
+    triangle.swap(1, 2);
+    area = -area;
+  }
+
+  let min_x = screen
+    .iter()
+    .map(|v| v.x)
+    .fold(f32::INFINITY, f32::min)
+    .floor()
+    .max(left)
+    .max(0.0) as u32;
+  let min_y = screen
+    .iter()
+    .map(|v| v.y)
+    .fold(f32::INFINITY, f32::min)
+    .floor()
+    .max(top)
+    .max(0.0) as u32;
+  let max_x = screen
+    .iter()
+    .map(|v| v.x)
+    .fold(f32::NEG_INFINITY, f32::max)
+    .ceil()
+    .min(left + width)
+    .min(target.width as f32) as u32;
+  let max_y = screen
+    .iter()
+    .map(|v| v.y)
+    .fold(f32::NEG_INFINITY, f32::max)
+    .ceil()
+    .min(top + height)
+    .min(target.height as f32) as u32;
+
+  let edges = [(1, 2), (2, 0), (0, 1)];
+  let top_left = edges.map(|(a, b)| is_top_left(screen[a], screen[b]));
+
+  for y in min_y..max_y {
+    for x in min_x..max_x {
+      let point = vec3(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+
+      let weights = edges.map(|(a, b)| edge(screen[a], screen[b], point));
+
+      // shared edges belong to exactly one triangle so blended quads don't
+      // double up along their diagonal
+      let inside = weights
+        .iter()
+        .zip(top_left.iter())
+        .all(|(w, top_left)| *w > 0.0 || (*w == 0.0 && *top_left));
+
+      if !inside {
+        continue;
+      }
+
+      let weights = weights.map(|w| w / area);
+      let inv_w = weights[0] * screen[0].z + weights[1] * screen[1].z + weights[2] * screen[2].z;
+      let weights = [0, 1, 2].map(|i| weights[i] * screen[i].z / inv_w);
+
+      let color = triangle[0].color * weights[0] + triangle[1].color * weights[1] + triangle[2].color * weights[2];
+      let tex_coords =
+        triangle[0].tex_coords * weights[0] + triangle[1].tex_coords * weights[1] + triangle[2].tex_coords * weights[2];
+
+      target.blend(x, y, fragment(color, tex_coords));
+    }
+  }
+}
+
+#[inline]
+fn edge(a: Vec3, b: Vec3, point: Vec3) -> f32 {
+  (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x)
+}
+
+// with y pointing down and positive winding, top edges point right and left
+// edges point up
+#[inline]
+fn is_top_left(a: Vec3, b: Vec3) -> bool {
+  let d = vec2(b.x - a.x, b.y - a.y);
+
+  (d.y == 0.0 && d.x > 0.0) || d.y < 0.0
+}
+
+#[cfg(test)]
+mod tests {
+  use std::rc::Rc;
+
+  use super::*;
+  use crate::render::camera::ortho::OrthoCameraController;
+  use crate::render::renderer::mesh::Mesh;
+  use crate::render::renderer::Renderer;
+  use crate::render::renderer2d::text::font::FontBitmap;
+  use crate::render::renderer2d::text::TextParams;
+  use crate::render::renderer2d::Renderer2d;
+
+  const SIZE: u32 = 8;
+  const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+  // pixel coordinates on the 8x8 target to clip space with an identity camera
+  fn ndc(x: f32, y: f32) -> [f32; 2] {
+    [x / (SIZE as f32 / 2.0) - 1.0, 1.0 - y / (SIZE as f32 / 2.0)]
+  }
+
+  fn quad(left: f32, top: f32, right: f32, bottom: f32, color: [f32; 4]) -> [QuadVertex; 4] {
+    [
+      (ndc(left, top), [0.0, 0.0]),
+      (ndc(right, top), [1.0, 0.0]),
+      (ndc(right, bottom), [1.0, 1.0]),
+      (ndc(left, bottom), [0.0, 1.0]),
+    ]
+    .map(|(position, tex_coords)| QuadVertex {
+      position,
+      tex_coords,
+      color,
+    })
+  }
+
+  fn target(clear: Color) -> Framebuffer {
+    let mut target = Framebuffer::new(SIZE, SIZE);
+    target.clear(clear);
+    target
+  }
+
+  fn covered(target: &Framebuffer, color: [u8; 4]) -> Vec<(u32, u32)> {
+    (0..SIZE)
+      .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+      .filter(|&(x, y)| target.pixel(x, y) == color)
+      .collect()
+  }
+
+  fn square(from: u32, to: u32) -> Vec<(u32, u32)> {
+    (from..to).flat_map(|y| (from..to).map(move |x| (x, y))).collect()
+  }
+
+  #[test]
+  fn quad_covers_pixel_centers_inside_its_edges() {
+    let mut target = target(Color::BLACK);
+
+    SoftwareBackend::new().draw_quads(&mut target, &quad(2.0, 2.0, 6.0, 6.0, RED), &ViewProjection::identity());
+
+    assert_eq!(covered(&target, [255, 0, 0, 255]), square(2, 6));
+  }
+
+  #[test]
+  fn shared_diagonal_is_blended_once() {
+    let mut target = target(Color::BLACK);
+
+    SoftwareBackend::new().draw_quads(
+      &mut target,
+      &quad(0.0, 0.0, 8.0, 8.0, [1.0, 1.0, 1.0, 0.5]),
+      &ViewProjection::identity(),
+    );
+
+    // 0.5 * 1.0 + 0.5 * 0.0 for color, 0.5 * 0.5 + 0.5 * 1.0 for alpha
+    assert!(target.pixels().iter().all(|pixel| *pixel == [128, 128, 128, 191]));
+  }
+
+  #[test]
+  fn adjacent_quads_dont_overlap() {
+    let mut target = target(Color::BLACK);
+    let color = [1.0, 1.0, 1.0, 0.5];
+
+    let mut vertices = quad(0.0, 0.0, 4.0, 8.0, color).to_vec();
+    vertices.extend(quad(4.0, 0.0, 8.0, 8.0, color));
+
+    SoftwareBackend::new().draw_quads(&mut target, &vertices, &ViewProjection::identity());
+
+    assert!(target.pixels().iter().all(|pixel| *pixel == [128, 128, 128, 191]));
+  }
+
+  #[test]
+  fn blends_source_over_destination() {
+    let mut target = target(Color(0.0, 0.0, 1.0, 1.0));
+
+    SoftwareBackend::new().draw_quads(
+      &mut target,
+      &quad(0.0, 0.0, 8.0, 8.0, [1.0, 0.0, 0.0, 0.25]),
+      &ViewProjection::identity(),
+    );
+
+    // red 0.25, blue 0.75, alpha 0.25 * 0.25 + 0.75
+    assert_eq!(target.pixel(3, 3), [64, 0, 191, 207]);
+  }
+
+  #[test]
+  fn viewport_clips_and_offsets() {
+    let mut target = target(Color::BLACK);
+    let mut backend = SoftwareBackend::new();
+
+    // bottom left quarter, gl counts y from the bottom
+    backend.set_viewport(Some(Viewport {
+      x: 0,
+      y: 0,
+      width: SIZE / 2,
+      height: SIZE / 2,
+    }));
+    backend.draw_quads(&mut target, &quad(0.0, 0.0, 8.0, 8.0, RED), &ViewProjection::identity());
+
+    let expected: Vec<_> = (4..8).flat_map(|y| (0..4).map(move |x| (x, y))).collect();
+
+    assert_eq!(covered(&target, [255, 0, 0, 255]), expected);
+  }
+
+  #[test]
+  fn text_samples_the_atlas_and_tints_it() {
+    let mut target = target(Color::BLACK);
+    let mut backend = SoftwareBackend::new();
+
+    // opaque white on the left, transparent on the right
+    let atlas = backend.create_texture(vec![255, 255, 255, 255, 0, 0, 0, 0], (2, 1));
+
+    let vertices = quad(0.0, 0.0, 8.0, 8.0, [0.0, 1.0, 0.0, 1.0]).map(|v| TextVertex {
+      position: v.position,
+      tex_coords: v.tex_coords,
+      color: v.color,
+    });
+
+    backend.draw_text(&mut target, &vertices, &atlas, &ViewProjection::identity());
+
+    let expected: Vec<_> = (0..SIZE).flat_map(|y| (0..4).map(move |x| (x, y))).collect();
+
+    assert_eq!(covered(&target, [0, 255, 0, 255]), expected);
+    assert_eq!(covered(&target, [0, 0, 0, 255]).len(), (SIZE * SIZE / 2) as usize);
+  }
+
+  #[test]
+  fn mesh_applies_the_model_matrix_and_interpolates_color() {
+    let mut target = target(Color::BLACK);
+
+    let vertices = [(2.0, 2.0), (4.0, 2.0), (4.0, 6.0), (2.0, 6.0)].map(|(x, y)| {
+      let [x, y] = ndc(x, y);
+
+      Vertex {
+        position: [x, y, 0.0],
+        color: RED,
+      }
+    });
+
+    // two pixels to the right
+    let model = Matrix4::new_translation(&vec3(0.5, 0.0, 0.0));
+
+    SoftwareBackend::new().draw_mesh(
+      &mut target,
+      &vertices,
+      &QUAD_INDICES,
+      &ViewProjection::identity(),
+      &model,
+    );
+
+    let expected: Vec<_> = (2..6).flat_map(|y| (4..6).map(move |x| (x, y))).collect();
+
+    assert_eq!(covered(&target, [255, 0, 0, 255]), expected);
+  }
+
+  #[test]
+  fn mesh_behind_the_camera_is_dropped() {
+    let mut target = target(Color::BLACK);
+
+    let vertices = [(0.0, 0.0), (8.0, 0.0), (8.0, 8.0)].map(|(x, y)| {
+      let [x, y] = ndc(x, y);
+
+      Vertex {
+        position: [x, y, 0.0],
+        color: RED,
+      }
+    });
+
+    // w = -1
+    let flip = Matrix4::from_diagonal(&vec4(1.0, 1.0, 1.0, -1.0));
+
+    SoftwareBackend::new().draw_mesh(&mut target, &vertices, &[0, 1, 2], &flip, &Matrix4::identity());
+
+    assert!(target.pixels().iter().all(|pixel| *pixel == [0, 0, 0, 255]));
+  }
+
+  // the renderers on top of the backend, with a camera that spans [-1, 1] on
+  // both axes of a square target
+
+  fn font() -> Rc<FontBitmap> {
+    Rc::new(FontBitmap::from_bytes(include_bytes!(
+      "../../../fonts/terminus/ter-u32n.bdf"
+    )))
+  }
+
+  fn camera(size: u32) -> OrthoCameraController {
+    OrthoCameraController::new(Viewport::new(size, size))
+  }
+
+  fn square_mesh(half: f32, color: [f32; 4]) -> Mesh {
+    Mesh::new(
+      [[-half, -half], [half, -half], [half, half], [-half, half]]
+        .map(|[x, y]| Vertex {
+          position: [x, y, 0.0],
+          color,
+        })
+        .to_vec(),
+      QUAD_INDICES.to_vec(),
+    )
+  }
+
+  #[test]
+  fn renderer2d_quads() {
+    let mut target = target(Color::BLACK);
+    let mut renderer2d = Renderer2d::new(SoftwareBackend::new(), font());
+
+    let mut context = renderer2d.begin(&camera(SIZE), &mut target);
+    context.draw_quad(vec2(0.0, 0.0), vec2(0.5, 0.5));
+    context.draw_quad(vec2(-0.5, 0.5), vec2(0.5, 0.5));
+    context.finish();
+
+    // y points up in the world and down on the target
+    let mut expected: Vec<_> = (1..3).flat_map(|y| (1..3).map(move |x| (x, y))).collect();
+    expected.extend(square(3, 5));
+
+    assert_eq!(covered(&target, [255, 255, 255, 255]), expected);
+    assert_eq!(
+      covered(&target, [0, 0, 0, 255]).len(),
+      (SIZE * SIZE) as usize - expected.len()
+    );
+  }
+
+  #[test]
+  fn renderer2d_text() {
+    const TEXT_SIZE: u32 = 128;
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+
+    let font = font();
+    let (cell_width, cell_height) = (font.cell_size.x as u32, font.cell_size.y as u32);
+
+    let mut target = Framebuffer::new(TEXT_SIZE, TEXT_SIZE);
+    target.clear(Color::BLACK);
+
+    let mut renderer2d = Renderer2d::new(SoftwareBackend::new(), font);
+
+    // the scale applies to the glyph and the position, one atlas texel per
+    // pixel
+    let params = TextParams::new().scale(0.125).color(Color(0.0, 1.0, 0.0, 1.0));
+
+    let mut context = renderer2d.begin(&camera(TEXT_SIZE), &mut target);
+    context.draw_text("Hi", vec2(-0.5, -0.25), &params);
+    context.finish();
+
+    let text: Vec<_> = (0..TEXT_SIZE)
+      .flat_map(|y| (0..TEXT_SIZE).map(move |x| (x, y)))
+      .filter(|&(x, y)| target.pixel(x, y) != [0, 0, 0, 255])
+      .collect();
+
+    // the atlas is either opaque white or transparent, tinted it's exactly
+    // the text color
+    assert!(text.iter().all(|&(x, y)| target.pixel(x, y) == GREEN));
+
+    // two cells starting at the position, glyphs sit on top of it
+    let (left, bottom) = (32, 80);
+    assert!(text
+      .iter()
+      .all(|&(x, y)| (left..left + 2 * cell_width).contains(&x) && (bottom - cell_height..bottom).contains(&y)));
+
+    // both glyphs are drawn
+    assert!(text.iter().any(|&(x, _)| x < left + cell_width));
+    assert!(text.iter().any(|&(x, _)| x >= left + cell_width));
+  }
+
+  #[test]
+  fn renderer_meshes() {
+    const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+
+    let mut target = target(Color::BLACK);
+    let mut renderer = Renderer::new(SoftwareBackend::new());
+
+    let mut context = renderer.begin(&camera(SIZE), &mut target);
+    context.draw(
+      &square_mesh(0.25, GREEN),
+      &vec3(0.75, 0.75, 0.0),
+      &Vec3::zeros(),
+      &vec3(1.0, 1.0, 1.0),
+    );
+    context.draw(
+      &square_mesh(0.25, RED),
+      &Vec3::zeros(),
+      &Vec3::zeros(),
+      &vec3(2.0, 2.0, 1.0),
+    );
+    context.finish();
+
+    assert_eq!(
+      covered(&target, [0, 255, 0, 255]),
+      (0..2).flat_map(|y| (6..8).map(move |x| (x, y))).collect::<Vec<_>>()
+    );
+    assert_eq!(covered(&target, [255, 0, 0, 255]), square(2, 6));
+  }
+
+  #[test]
+  fn out_of_range_indices_skip_the_triangle() {
+    let mut target = target(Color::BLACK);
+    let mut mesh = square_mesh(0.5, RED);
+
+    // the second triangle points past the vertices
+    mesh.indices[4] = 4;
+
+    let mut renderer = Renderer::new(SoftwareBackend::new());
+
+    let mut context = renderer.begin(&camera(SIZE), &mut target);
+    context.draw_transform(&mesh, &Matrix4::identity());
+    context.finish();
+
+    // only the bottom right half of the square, the diagonal goes from its
+    // bottom left to its top right corner
+    let expected: Vec<_> = (2..6)
+      .flat_map(|y| (2..6).map(move |x| (x, y)))
+      .filter(|&(x, y)| x + y >= 7)
+      .collect();
+
+    assert_eq!(covered(&target, [255, 0, 0, 255]), expected);
+  }
+}
@@ -1,13 +1,14 @@
 use nalgebra::Matrix4;
-use winit::event::{MouseScrollDelta, WindowEvent};
-use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::event::WindowEvent;
+use winit::keyboard::KeyCode;
 use winit::window::Window;
 
 use crate::engine::events::EventHandler;
-use crate::engine::input::key_pressed;
+use crate::engine::input::action::{AxisBinding, Binding, InputMap};
+use crate::engine::input::Input;
 use crate::math::{Position, Projection, View, ViewProjection};
 use crate::update::UpdateHandler;
-use crate::window::aspect_ratio;
+use crate::window::Viewport;
 use crate::{vec2, vec3, vec4};
 
 pub struct OrthoCamera {
@@ -75,30 +76,61 @@ impl OrthoCamera {
     // normalize by world.w to get the actual position
     vec2(world.x / world.w, world.y / world.w)
   }
+
+  /// Where the cursor points at in the world
+  pub fn mouse_world_position(&self, input: &Input) -> Position {
+    self.screen_to_world(input.mouse_position())
+  }
 }
 
+/// Axes read by [`OrthoCameraController`]
+pub const CAMERA_X: &str = "camera_x";
+pub const CAMERA_Y: &str = "camera_y";
+pub const CAMERA_ZOOM: &str = "camera_zoom";
+/// Zoomed by the value itself instead of per second, for the scroll wheel
+pub const CAMERA_ZOOM_STEP: &str = "camera_zoom_step";
+
 pub struct OrthoCameraController {
   camera: OrthoCamera,
   zoom: f32,
+  aspect_ratio: f32,
   pub speed: f32,
+  /// [`CAMERA_X`], [`CAMERA_Y`], [`CAMERA_ZOOM`] and [`CAMERA_ZOOM_STEP`],
+  /// WASD, QE and the scroll wheel by default
+  pub bindings: InputMap,
+  /// Zoom with touch and trackpad pinches, off by default
+  pub pinch_zoom: bool,
 }
 
 impl OrthoCameraController {
-  pub fn new() -> Self {
+  /// Window resizes are picked up in [`EventHandler::handle_event`], sub
+  /// viewports are set with [`OrthoCameraController::set_viewport`]
+  pub fn new(viewport: Viewport) -> Self {
     let zoom = 1.0;
-    let aspect = aspect_ratio();
-
-    debug_assert_ne!(aspect, 0.0, "aspect ratio should never be zero");
+    let aspect = viewport.aspect_ratio();
 
     let camera = OrthoCamera::new(-aspect * zoom, aspect * zoom, -zoom, zoom);
 
     Self {
       camera,
       zoom,
+      aspect_ratio: aspect,
       speed: 1.0,
+      bindings: default_bindings(),
+      pinch_zoom: false,
     }
   }
 
+  pub fn set_viewport(&mut self, viewport: Viewport) {
+    self.aspect_ratio = viewport.aspect_ratio();
+    self.update_projection();
+  }
+
+  #[inline]
+  pub const fn aspect_ratio(&self) -> f32 {
+    self.aspect_ratio
+  }
+
   #[inline]
   pub const fn camera(&self) -> &OrthoCamera {
     &self.camera
@@ -115,7 +147,7 @@ impl OrthoCameraController {
   }
 
   fn update_projection(&mut self) {
-    let aspect = aspect_ratio();
+    let aspect = self.aspect_ratio;
 
     let left = -aspect * self.zoom;
     let mut right = aspect * self.zoom;
@@ -135,56 +167,55 @@ impl OrthoCameraController {
 }
 
 impl Default for OrthoCameraController {
+  /// Square aspect ratio until a viewport is set
   fn default() -> Self {
-    Self::new()
+    Self::new(Viewport::default())
   }
 }
 
 impl UpdateHandler for OrthoCameraController {
-  fn update(&mut self, delta_time: f32) {
-    if key_pressed(PhysicalKey::Code(KeyCode::KeyA)) {
-      self.camera.position.x -= self.speed * delta_time;
-      self.camera.recalculate_view_matrix();
-    } else if key_pressed(PhysicalKey::Code(KeyCode::KeyD)) {
-      self.camera.position.x += self.speed * delta_time;
+  fn update(&mut self, input: &Input, delta_time: f32) {
+    let x = self.bindings.axis(input, CAMERA_X);
+    let y = self.bindings.axis(input, CAMERA_Y);
+
+    if x != 0.0 || y != 0.0 {
+      self.camera.position.x += x * self.speed * delta_time;
+      self.camera.position.y += y * self.speed * delta_time;
       self.camera.recalculate_view_matrix();
     }
 
-    if key_pressed(PhysicalKey::Code(KeyCode::KeyW)) {
-      self.camera.position.y += self.speed * delta_time;
-      self.camera.recalculate_view_matrix();
-    } else if key_pressed(PhysicalKey::Code(KeyCode::KeyS)) {
-      self.camera.position.y -= self.speed * delta_time;
-      self.camera.recalculate_view_matrix();
+    let zoom = self.bindings.axis(input, CAMERA_ZOOM) * delta_time + self.bindings.axis(input, CAMERA_ZOOM_STEP);
+
+    if zoom != 0.0 {
+      self.set_zoom(self.zoom + zoom * self.speed);
     }
 
-    if key_pressed(PhysicalKey::Code(KeyCode::KeyQ)) {
-      self.set_zoom(self.zoom + self.speed * delta_time);
-      self.update_projection();
-    } else if key_pressed(PhysicalKey::Code(KeyCode::KeyE)) {
-      self.set_zoom(self.zoom - self.speed * delta_time);
-      self.update_projection();
+    // fingers moving apart zoom in
+    let scale = input.pinch_scale();
+
+    if self.pinch_zoom && scale != 1.0 {
+      self.set_zoom(self.zoom / scale);
     }
   }
 }
 
+fn default_bindings() -> InputMap {
+  let buttons = |negative: KeyCode, positive: KeyCode| AxisBinding::Buttons {
+    negative: Binding::Key(negative),
+    positive: Binding::Key(positive),
+  };
+
+  InputMap::new()
+    .with_axis(CAMERA_X, [buttons(KeyCode::KeyA, KeyCode::KeyD)])
+    .with_axis(CAMERA_Y, [buttons(KeyCode::KeyS, KeyCode::KeyW)])
+    .with_axis(CAMERA_ZOOM, [buttons(KeyCode::KeyE, KeyCode::KeyQ)])
+    .with_axis(CAMERA_ZOOM_STEP, [AxisBinding::ScrollY])
+}
+
 impl EventHandler for OrthoCameraController {
   fn handle_event(&mut self, event: &WindowEvent, _window: &Window) {
-    match event {
-      WindowEvent::MouseWheel { delta, .. } => match delta {
-        MouseScrollDelta::LineDelta(_, y) => {
-          self.set_zoom(self.zoom + y * self.speed);
-          self.update_projection();
-        }
-        MouseScrollDelta::PixelDelta(delta) => {
-          self.set_zoom(self.zoom + delta.y as f32 * self.speed);
-          self.update_projection();
-        }
-      },
-      WindowEvent::Resized(_) => {
-        self.update_projection();
-      }
-      _ => (),
+    if let WindowEvent::Resized(size) = event {
+      self.set_viewport(Viewport::from(*size));
     }
   }
 }
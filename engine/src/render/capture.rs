@@ -0,0 +1,422 @@
+// screenshots and frame sequence recording
+// frames are read back right after the layers draw or after egui is painted,
+// encoding and writing happens on a worker thread
+
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use glium::backend::Facade;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, ImageError, RgbaImage};
+use winit::keyboard::KeyCode;
+
+use crate::render::target::Target;
+
+/// Frames waiting for the recording worker
+const RECORDING_BUFFER: usize = 4;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+  #[error(transparent)]
+  Image(#[from] ImageError),
+  #[error(transparent)]
+  Io(#[from] io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceFormat {
+  /// Numbered pngs in a directory
+  Png,
+  /// One looping animated gif
+  Gif,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordingConfig {
+  /// Directory for [`SequenceFormat::Png`], file for [`SequenceFormat::Gif`]
+  pub path: PathBuf,
+  pub format: SequenceFormat,
+  /// Frames captured per second of real time, independent of the frame rate
+  pub fps: u32,
+  /// Include the egui overlay
+  pub egui: bool,
+  /// Stop on its own after this many frames
+  pub max_frames: Option<u32>,
+}
+
+impl RecordingConfig {
+  /// Write `frame_00000.png`, `frame_00001.png` and so on into `directory`
+  pub fn png(directory: impl Into<PathBuf>) -> Self {
+    Self::new(directory.into(), SequenceFormat::Png)
+  }
+
+  /// Write an animated gif to `path`
+  pub fn gif(path: impl Into<PathBuf>) -> Self {
+    Self::new(path.into(), SequenceFormat::Gif)
+  }
+
+  fn new(path: PathBuf, format: SequenceFormat) -> Self {
+    Self {
+      path,
+      format,
+      fps: 30,
+      egui: false,
+      max_frames: None,
+    }
+  }
+
+  pub fn fps(mut self, fps: u32) -> Self {
+    self.fps = fps.max(1);
+    self
+  }
+
+  pub fn egui(mut self, egui: bool) -> Self {
+    self.egui = egui;
+    self
+  }
+
+  pub fn max_frames(mut self, frames: u32) -> Self {
+    self.max_frames = Some(frames);
+    self
+  }
+}
+
+/// Keys handled by the engine before any layer sees them, none are bound by
+/// default
+#[derive(Debug, Clone)]
+pub struct CaptureHotkeys {
+  /// Save a png of the next frame into `directory`
+  pub screenshot: Option<KeyCode>,
+  /// Start or stop recording a gif into `directory`
+  pub record: Option<KeyCode>,
+  pub directory: PathBuf,
+  /// Include the egui overlay
+  pub egui: bool,
+}
+
+impl CaptureHotkeys {
+  /// F12 for a screenshot and F9 to toggle recording, into `captures`
+  pub fn function_keys() -> Self {
+    Self {
+      screenshot: Some(KeyCode::F12),
+      record: Some(KeyCode::F9),
+      ..Self::default()
+    }
+  }
+}
+
+impl Default for CaptureHotkeys {
+  fn default() -> Self {
+    Self {
+      screenshot: None,
+      record: None,
+      directory: PathBuf::from("captures"),
+      egui: false,
+    }
+  }
+}
+
+/// Image of a requested frame, filled in once it is presented
+#[derive(Clone, Default)]
+pub struct Screenshot {
+  image: Rc<RefCell<Option<RgbaImage>>>,
+}
+
+impl Screenshot {
+  #[inline]
+  pub fn is_ready(&self) -> bool {
+    self.image.borrow().is_some()
+  }
+
+  /// `None` until the frame was drawn
+  pub fn take(&self) -> Option<RgbaImage> {
+    self.image.borrow_mut().take()
+  }
+}
+
+enum Destination {
+  Screenshot(Screenshot),
+  File(PathBuf),
+}
+
+struct Request {
+  egui: bool,
+  destination: Destination,
+}
+
+struct Recording {
+  config: RecordingConfig,
+  interval: Duration,
+  next: Instant,
+  frames: u32,
+  sender: SyncSender<RgbaImage>,
+}
+
+/// Pending screenshots and the current recording of one window
+pub struct Capture {
+  pub hotkeys: CaptureHotkeys,
+
+  requests: Vec<Request>,
+  recording: Option<Recording>,
+  workers: Vec<JoinHandle<()>>,
+}
+
+impl Capture {
+  pub fn new() -> Self {
+    Self {
+      hotkeys: CaptureHotkeys::default(),
+      requests: Vec::new(),
+      recording: None,
+      workers: Vec::new(),
+    }
+  }
+
+  /// Capture the next presented frame, with or without the egui overlay
+  pub fn screenshot(&mut self, egui: bool) -> Screenshot {
+    let screenshot = Screenshot::default();
+
+    self.requests.push(Request {
+      egui,
+      destination: Destination::Screenshot(screenshot.clone()),
+    });
+
+    screenshot
+  }
+
+  /// Save the next presented frame as a png, missing directories are created
+  pub fn save_screenshot(&mut self, path: impl Into<PathBuf>, egui: bool) {
+    self.requests.push(Request {
+      egui,
+      destination: Destination::File(path.into()),
+    });
+  }
+
+  /// Replaces the current recording
+  pub fn start_recording(&mut self, config: RecordingConfig) {
+    self.stop_recording();
+
+    info!("recording {:?} to {}", config.format, config.path.display());
+
+    // frames are dropped once the encoder is this far behind instead of
+    // piling up in memory
+    let (sender, receiver) = mpsc::sync_channel::<RgbaImage>(RECORDING_BUFFER);
+    let worker_config = config.clone();
+
+    self.spawn(move || {
+      let config = worker_config;
+
+      let result = match config.format {
+        SequenceFormat::Png => write_pngs(&config.path, receiver.iter()),
+        SequenceFormat::Gif => write_gif(&config.path, config.fps, receiver.iter()),
+      };
+
+      match result {
+        Ok(frames) => info!("wrote {frames} frames to {}", config.path.display()),
+        Err(e) => error!("failed to write recording {}: {e}", config.path.display()),
+      }
+    });
+
+    self.recording = Some(Recording {
+      interval: Duration::from_secs_f64(1.0 / config.fps as f64),
+      next: Instant::now(),
+      frames: 0,
+      config,
+      sender,
+    });
+  }
+
+  /// Finish the current recording, it is written out in the background
+  pub fn stop_recording(&mut self) {
+    // dropping the sender ends the worker
+    self.recording = None;
+    self.workers.retain(|worker| !worker.is_finished());
+  }
+
+  #[inline]
+  pub fn is_recording(&self) -> bool {
+    self.recording.is_some()
+  }
+
+  /// Screenshot or toggle recording if `key` is one of the hotkeys, returns
+  /// true if it was
+  pub(crate) fn handle_hotkey(&mut self, key: KeyCode) -> bool {
+    let CaptureHotkeys {
+      screenshot,
+      record,
+      directory,
+      egui,
+    } = self.hotkeys.clone();
+
+    if screenshot == Some(key) {
+      let path = directory.join(format!("{}.png", timestamp()));
+      self.save_screenshot(path, egui);
+
+      return true;
+    }
+
+    if record == Some(key) {
+      if self.is_recording() {
+        self.stop_recording();
+      } else {
+        let config = RecordingConfig::gif(directory.join(format!("{}.gif", timestamp()))).egui(egui);
+        self.start_recording(config);
+      }
+
+      return true;
+    }
+
+    false
+  }
+
+  /// Read `target` back if anything wants the frame at this point, `egui`
+  /// says whether the overlay was painted yet
+  pub(crate) fn capture<F: Facade>(&mut self, target: &Target, facade: &F, egui: bool) {
+    let now = Instant::now();
+
+    let record = self
+      .recording
+      .as_ref()
+      .is_some_and(|recording| recording.config.egui == egui && now >= recording.next);
+
+    if !record && !self.requests.iter().any(|request| request.egui == egui) {
+      return;
+    }
+
+    let mut image = target.read_pixels(facade);
+
+    // the framebuffer alpha is whatever was blended last, captures are
+    // opaque like the window
+    image.pixels_mut().for_each(|pixel| pixel[3] = u8::MAX);
+
+    if record {
+      self.record(image.clone(), now);
+    }
+
+    let (requests, pending): (Vec<_>, Vec<_>) = self.requests.drain(..).partition(|request| request.egui == egui);
+    self.requests = pending;
+
+    for request in requests {
+      match request.destination {
+        Destination::Screenshot(screenshot) => *screenshot.image.borrow_mut() = Some(image.clone()),
+        Destination::File(path) => {
+          let image = image.clone();
+
+          self.spawn(move || match save_png(&path, &image) {
+            Ok(()) => info!("saved screenshot {}", path.display()),
+            Err(e) => error!("failed to save screenshot {}: {e}", path.display()),
+          });
+        }
+      }
+    }
+  }
+
+  fn record(&mut self, image: RgbaImage, now: Instant) {
+    let Some(recording) = self.recording.as_mut() else {
+      return;
+    };
+
+    recording.next += recording.interval;
+    // drop frames instead of catching up after a stall
+    if recording.next < now {
+      recording.next = now + recording.interval;
+    }
+
+    let disconnected = match recording.sender.try_send(image) {
+      Ok(()) => {
+        recording.frames += 1;
+        false
+      }
+      Err(TrySendError::Full(_)) => {
+        warn!("recording fell behind, dropped a frame");
+        false
+      }
+      Err(TrySendError::Disconnected(_)) => true,
+    };
+
+    let done = disconnected
+      || recording
+        .config
+        .max_frames
+        .is_some_and(|max_frames| recording.frames >= max_frames);
+
+    if done {
+      self.stop_recording();
+    }
+  }
+
+  fn spawn(&mut self, f: impl FnOnce() + Send + 'static) {
+    self.workers.push(thread::spawn(f));
+  }
+}
+
+impl Default for Capture {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// finish writing everything before the process exits
+impl Drop for Capture {
+  fn drop(&mut self) {
+    self.recording = None;
+
+    for worker in self.workers.drain(..) {
+      let _ = worker.join();
+    }
+  }
+}
+
+fn save_png(path: &Path, image: &RgbaImage) -> Result<(), Error> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+
+  image.save(path)?;
+
+  Ok(())
+}
+
+fn write_pngs(directory: &Path, frames: impl Iterator<Item = RgbaImage>) -> Result<u32, Error> {
+  fs::create_dir_all(directory)?;
+
+  let mut count = 0;
+
+  for image in frames {
+    image.save(directory.join(format!("frame_{count:05}.png")))?;
+    count += 1;
+  }
+
+  Ok(count)
+}
+
+fn write_gif(path: &Path, fps: u32, frames: impl Iterator<Item = RgbaImage>) -> Result<u32, Error> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+
+  let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), 10);
+  encoder.set_repeat(Repeat::Infinite)?;
+
+  let delay = Delay::from_numer_denom_ms(1000, fps);
+  let mut count = 0;
+
+  for image in frames {
+    encoder.encode_frame(image::Frame::from_parts(image, 0, 0, delay))?;
+    count += 1;
+  }
+
+  Ok(count)
+}
+
+// unique enough file name for captures
+fn timestamp() -> String {
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+  format!("capture-{}-{:03}", now.as_secs(), now.subsec_millis())
+}
@@ -0,0 +1,27 @@
+use std::rc::Rc;
+
+use glium::backend::{Context, Facade};
+
+/// Cheap handle to the gl context, usable anywhere glium wants a [`Facade`]
+/// to create textures, buffers and shaders
+///
+/// Only valid while the context it was taken from is alive
+#[derive(Clone)]
+pub struct Gpu {
+  context: Rc<Context>,
+}
+
+impl Gpu {
+  pub fn new<F: Facade + ?Sized>(facade: &F) -> Self {
+    Self {
+      context: facade.get_context().clone(),
+    }
+  }
+}
+
+impl Facade for Gpu {
+  #[inline]
+  fn get_context(&self) -> &Rc<Context> {
+    &self.context
+  }
+}
@@ -1,4 +1,9 @@
+pub mod assets;
+pub mod backend;
 pub mod camera;
+pub mod capture;
+pub mod gpu;
 pub mod renderer2d;
 pub mod shader;
 pub mod renderer;
+pub mod target;
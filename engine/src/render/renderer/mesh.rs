@@ -1,13 +1,8 @@
-use std::ops::Deref;
-
-use glium::index::PrimitiveType;
-use glium::uniforms::Uniforms;
-use glium::{implement_vertex, uniform, Display, Frame, IndexBuffer, Surface, VertexBuffer};
-use glutin::surface::WindowSurface;
+use glium::implement_vertex;
 use nalgebra::Matrix4;
 use tobj::LoadOptions;
 
-use crate::render::shader::{Shader, DRAW_PARAMETERS};
+use crate::render::backend::Backend;
 use crate::ViewProjection;
 
 pub const MAX_TRIS: usize = 20000;
@@ -16,7 +11,7 @@ pub const MAX_INDICES: usize = MAX_TRIS * 3;
 
 // calculate triangle indices once
 const TRI_INDICES: [u16; 3] = [0, 1, 2];
-static TRI_INDEX_ARRAY: [u16; MAX_INDICES] = {
+pub(crate) static TRI_INDEX_ARRAY: [u16; MAX_INDICES] = {
   let mut indices = [0; MAX_INDICES];
 
   let mut i = 0;
@@ -84,29 +79,19 @@ impl Mesh {
 
 pub struct MeshRenderer {
   vertex_array: Vec<Vertex>,
-  vertex_buffer: VertexBuffer<Vertex>,
-  index_buffer: IndexBuffer<u16>,
-  shader: Shader,
 }
 
 impl MeshRenderer {
-  pub fn new(display: &Display<WindowSurface>, shader: Shader) -> Self {
+  pub fn new() -> Self {
     let vertex_array = Vec::with_capacity(MAX_VERTICES);
-    let vertex_buffer = VertexBuffer::empty_dynamic(display, MAX_VERTICES).unwrap();
-
-    let index_buffer = IndexBuffer::new(display, PrimitiveType::TrianglesList, &TRI_INDEX_ARRAY).unwrap();
 
-    Self {
-      vertex_buffer,
-      vertex_array,
-      index_buffer,
-      shader,
-    }
+    Self { vertex_array }
   }
 
-  pub fn draw_mesh(
+  pub fn draw_mesh<B: Backend>(
     &mut self,
-    frame: &mut glium::Frame,
+    backend: &mut B,
+    target: &mut B::Target<'_>,
     view_projection: &ViewProjection,
     transform: Matrix4<f32>,
     mesh: &Mesh,
@@ -114,42 +99,16 @@ impl MeshRenderer {
     self.vertex_array.clear();
     self.vertex_array.extend_from_slice(&mesh.vertices);
 
-    self
-      .index_buffer
-      .slice(0..mesh.indices.len())
-      .unwrap()
-      .write(&mesh.indices);
-
-    let uniforms = uniform! {
-      u_view_projection: *view_projection.as_ref(),
-      u_model: *transform.as_ref(),
-    };
-
-    copy_and_draw(
-      &mut self.vertex_buffer,
-      &mut self.vertex_array,
-      &self.index_buffer,
-      &self.shader,
-      frame,
-      &uniforms,
-    );
+    backend.draw_mesh(target, &self.vertex_array, &mesh.indices, view_projection, &transform);
   }
 
-  pub fn flush(&mut self, _frame: &mut Frame, _view_projection: &ViewProjection) {
-    // if !self.vertex_array.is_empty() {
-    //   let uniforms = uniform! {
-    //     u_view_projection: *view_projection.as_ref(),
-    //   };
-
-    //   copy_and_draw(
-    //     &mut self.vertex_buffer,
-    //     &mut self.vertex_array,
-    //     &self.index_buffer,
-    //     &self.shader,
-    //     frame,
-    //     &uniforms,
-    //   );
-    // }
+  pub fn flush<B: Backend>(
+    &mut self,
+    _backend: &mut B,
+    _target: &mut B::Target<'_>,
+    _view_projection: &ViewProjection,
+  ) {
+    // meshes are drawn as they're added, nothing is batched yet
   }
 
   pub fn clear(&mut self) {
@@ -159,34 +118,8 @@ impl MeshRenderer {
   }
 }
 
-pub(crate) fn copy_and_draw<V: Copy, U: Uniforms>(
-  vertex_buffer: &mut VertexBuffer<V>,
-  vertex_array: &mut Vec<V>,
-  index_buffer: &IndexBuffer<u16>,
-  shader: &Shader,
-  frame: &mut Frame,
-  uniforms: &U,
-) {
-  if vertex_array.len() == vertex_buffer.len() {
-    vertex_buffer.write(vertex_array.as_slice());
-  } else {
-    vertex_buffer.invalidate();
-
-    unsafe {
-      vertex_buffer
-        .slice_mut(0..vertex_array.len())
-        .unwrap_unchecked()
-        .write(vertex_array.as_slice());
-    }
+impl Default for MeshRenderer {
+  fn default() -> Self {
+    Self::new()
   }
-
-  frame
-    .draw(
-      vertex_buffer.deref(),
-      index_buffer,
-      shader.program(),
-      uniforms,
-      &DRAW_PARAMETERS,
-    )
-    .unwrap();
 }
@@ -1,71 +1,123 @@
 // 3d renderer
-// todo: maybe make this a trait and have vulkan and opengl implementations
 
-use glium::{Display, Frame};
-use glutin::surface::WindowSurface;
+use glium::backend::Facade;
 use mesh::{Mesh, MeshRenderer};
 use nalgebra::Matrix4;
 
-use super::shader::Shader;
+use crate::render::backend::gl::GlBackend;
+use crate::render::backend::Backend;
+use crate::window::Viewport;
 use crate::{OrthoCameraController, Vec3, ViewProjection};
 
 pub mod mesh;
 
-pub struct Renderer {
+pub struct Renderer<B: Backend = GlBackend> {
+  backend: B,
   mesh_renderer: MeshRenderer,
 }
 
-impl Renderer {
-  pub fn new(display: &Display<WindowSurface>, shader: Shader) -> Self {
+impl<B: Backend> Renderer<B> {
+  /// `backend` needs the mesh pipeline
+  pub fn new(backend: B) -> Self {
     Self {
-      mesh_renderer: MeshRenderer::new(display, shader),
+      backend,
+      mesh_renderer: MeshRenderer::new(),
     }
   }
 
   /// Initialize the renderer context to begin drawing
-  pub fn begin<'a>(&'a mut self, camera: &OrthoCameraController, frame: &'a mut Frame) -> RendererContext<'a> {
+  pub fn begin<'a, 't>(
+    &'a mut self,
+    camera: &OrthoCameraController,
+    target: &'a mut B::Target<'t>,
+  ) -> RendererContext<'a, 't, B> {
+    self.begin_viewport(camera, target, None)
+  }
+
+  /// Like [`Self::begin`] but only draws into `viewport`, for split screen or
+  /// part of a render target
+  pub fn begin_viewport<'a, 't>(
+    &'a mut self,
+    camera: &OrthoCameraController,
+    target: &'a mut B::Target<'t>,
+    viewport: Option<Viewport>,
+  ) -> RendererContext<'a, 't, B> {
+    self.backend.set_viewport(viewport);
+
     let view_projection = *camera.view_projection();
 
     RendererContext {
       renderer: self,
-      frame,
+      target,
       view_projection,
     }
   }
+
+  #[inline]
+  pub fn backend(&self) -> &B {
+    &self.backend
+  }
+
+  #[inline]
+  pub fn backend_mut(&mut self) -> &mut B {
+    &mut self.backend
+  }
 }
 
-pub struct RendererContext<'a> {
-  pub renderer: &'a mut Renderer,
+impl Renderer<GlBackend> {
+  /// Drop every gl object, drawing panics until [`Renderer::restore`]
+  pub fn release(&mut self) {
+    self.backend.release();
+  }
 
-  /// The frame that is currently being drawn to
-  pub frame: &'a mut Frame,
+  pub fn restore<F: Facade>(&mut self, facade: &F) {
+    self.backend.restore(facade);
+  }
+
+  /// See [`GlBackend::reload_shaders`]
+  pub fn reload_shaders(&mut self) {
+    self.backend.reload_shaders();
+  }
+}
+
+pub struct RendererContext<'a, 't, B: Backend = GlBackend> {
+  pub renderer: &'a mut Renderer<B>,
+
+  /// The target that is currently being drawn to
+  pub target: &'a mut B::Target<'t>,
 
   /// The view projection matrix that is currently being used
   pub view_projection: ViewProjection,
 }
 
-impl<'a> RendererContext<'a> {
+impl<'a, 't, B: Backend> RendererContext<'a, 't, B> {
   /// Draw vertex buffer
   pub fn draw(&mut self, mesh: &Mesh, position: &Vec3, rotation: &Vec3, scale: &Vec3) {
     let mut transform = Matrix4::new_rotation(*rotation);
     transform.append_translation_mut(position);
     transform.append_nonuniform_scaling_mut(scale);
 
-    self
-      .renderer
-      .mesh_renderer
-      .draw_mesh(self.frame, &self.view_projection, transform, mesh)
+    self.draw_transform(mesh, &transform)
   }
 
   pub fn draw_transform(&mut self, mesh: &Mesh, transform: &Matrix4<f32>) {
-    self
-      .renderer
-      .mesh_renderer
-      .draw_mesh(self.frame, &self.view_projection, *transform, mesh)
+    let renderer = &mut *self.renderer;
+
+    renderer.mesh_renderer.draw_mesh(
+      &mut renderer.backend,
+      self.target,
+      &self.view_projection,
+      *transform,
+      mesh,
+    )
   }
 
   pub fn flush(&mut self) {
-    self.renderer.mesh_renderer.flush(self.frame, &self.view_projection);
+    let renderer = &mut *self.renderer;
+
+    renderer
+      .mesh_renderer
+      .flush(&mut renderer.backend, self.target, &self.view_projection);
   }
 
   /// Finish drawing and flush the renderer
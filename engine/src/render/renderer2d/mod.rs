@@ -1,19 +1,18 @@
 pub mod quad;
 pub mod text;
 
-use std::ops::Deref;
 use std::rc::Rc;
 
-use glium::uniforms::Uniforms;
-use glium::{Display, Frame, IndexBuffer, Surface, VertexBuffer};
-use glutin::surface::WindowSurface;
+use glium::backend::Facade;
 
 use self::quad::QuadRenderer;
 use self::text::{TextParams, TextRenderer};
+use super::backend::gl::GlBackend;
+use super::backend::Backend;
 use super::camera::ortho::OrthoCameraController;
-use super::shader::{Shader, DRAW_PARAMETERS};
-use crate::render::renderer2d::text::font::FontBitmap;
 use crate::math::{Position, Size, ViewProjection};
+use crate::render::renderer2d::text::font::FontBitmap;
+use crate::window::Viewport;
 
 pub const MAX_QUADS: usize = 20000;
 pub const MAX_VERTICES: usize = MAX_QUADS * 4;
@@ -21,8 +20,8 @@ pub const MAX_INDICES: usize = MAX_QUADS * 6;
 pub const MAX_TEXTURE_SLOTS: usize = 32;
 
 // calculate quad indices once
-const QUAD_INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
-static QUAD_INDEX_ARRAY: [u16; MAX_INDICES] = {
+pub(crate) const QUAD_INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
+pub(crate) static QUAD_INDEX_ARRAY: [u16; MAX_INDICES] = {
   let mut indices = [0; MAX_INDICES];
 
   let mut i = 0;
@@ -41,23 +40,43 @@ static QUAD_INDEX_ARRAY: [u16; MAX_INDICES] = {
   indices
 };
 
-pub struct Renderer2d {
+pub struct Renderer2d<B: Backend = GlBackend> {
+  backend: B,
   quad_renderer: QuadRenderer,
-  text_renderer: TextRenderer,
+  text_renderer: TextRenderer<B>,
 }
 
-impl Renderer2d {
-  pub fn new(display: &Display<WindowSurface>, quad_shader: Shader, text_shader: Shader, font: Rc<FontBitmap>) -> Self {
-    let quad_renderer = QuadRenderer::new(display, quad_shader);
-    let text_renderer = TextRenderer::new(display, text_shader, font);
+impl<B: Backend> Renderer2d<B> {
+  /// `backend` needs the quad and text pipelines
+  pub fn new(backend: B, font: Rc<FontBitmap>) -> Self {
+    let quad_renderer = QuadRenderer::new();
+    let text_renderer = TextRenderer::new(&backend, font);
 
     Self {
+      backend,
       quad_renderer,
       text_renderer,
     }
   }
 
-  pub fn begin<'a>(&'a mut self, camera: &OrthoCameraController, frame: &'a mut Frame) -> RendererContext<'a> {
+  pub fn begin<'a, 't>(
+    &'a mut self,
+    camera: &OrthoCameraController,
+    target: &'a mut B::Target<'t>,
+  ) -> RendererContext<'a, 't, B> {
+    self.begin_viewport(camera, target, None)
+  }
+
+  /// Like [`Self::begin`] but only draws into `viewport`, for split screen or
+  /// part of a render target
+  pub fn begin_viewport<'a, 't>(
+    &'a mut self,
+    camera: &OrthoCameraController,
+    target: &'a mut B::Target<'t>,
+    viewport: Option<Viewport>,
+  ) -> RendererContext<'a, 't, B> {
+    self.backend.set_viewport(viewport);
+
     let view_projection = *camera.camera().view_projection();
 
     self.quad_renderer.clear();
@@ -65,79 +84,86 @@ impl Renderer2d {
 
     RendererContext {
       renderer: self,
-      frame,
+      target,
       view_projection,
     }
   }
+
+  #[inline]
+  pub fn backend(&self) -> &B {
+    &self.backend
+  }
+
+  #[inline]
+  pub fn backend_mut(&mut self) -> &mut B {
+    &mut self.backend
+  }
+}
+
+impl Renderer2d<GlBackend> {
+  /// Drop every gl object, drawing panics until [`Renderer2d::restore`]
+  pub fn release(&mut self) {
+    self.text_renderer.release();
+    self.backend.release();
+  }
+
+  pub fn restore<F: Facade>(&mut self, facade: &F) {
+    self.backend.restore(facade);
+    self.text_renderer.restore(&self.backend);
+  }
+
+  /// See [`GlBackend::reload_shaders`]
+  pub fn reload_shaders(&mut self) {
+    self.backend.reload_shaders();
+  }
 }
 
-pub struct RendererContext<'a> {
-  pub renderer: &'a mut Renderer2d,
-  pub frame: &'a mut Frame,
+pub struct RendererContext<'a, 't, B: Backend = GlBackend> {
+  pub renderer: &'a mut Renderer2d<B>,
+  pub target: &'a mut B::Target<'t>,
   pub view_projection: ViewProjection,
 }
 
-impl<'a> RendererContext<'a> {
+impl<'a, 't, B: Backend> RendererContext<'a, 't, B> {
   #[inline]
   pub fn draw_quad(&mut self, position: Position, size: Size) {
-    self
-      .renderer
-      .quad_renderer
-      .draw_quad(self.frame, &self.view_projection, position, size);
+    let renderer = &mut *self.renderer;
+
+    renderer.quad_renderer.draw_quad(
+      &mut renderer.backend,
+      self.target,
+      &self.view_projection,
+      position,
+      size,
+    );
   }
 
   #[inline]
   pub fn draw_text(&mut self, text: &str, position: Position, params: &TextParams) {
-    self
-      .renderer
-      .text_renderer
-      .draw_text(self.frame, &self.view_projection, position, params, text);
+    let renderer = &mut *self.renderer;
+
+    renderer.text_renderer.draw_text(
+      &mut renderer.backend,
+      self.target,
+      &self.view_projection,
+      position,
+      params,
+      text,
+    );
   }
 
   fn flush(&mut self) {
-    self
-      .renderer
+    let renderer = &mut *self.renderer;
+
+    renderer
       .quad_renderer
-      .next_batch(self.frame, &self.view_projection);
-    self
-      .renderer
+      .next_batch(&mut renderer.backend, self.target, &self.view_projection);
+    renderer
       .text_renderer
-      .next_batch(self.frame, &self.view_projection);
+      .next_batch(&mut renderer.backend, self.target, &self.view_projection);
   }
 
   pub fn finish(mut self) {
     self.flush();
   }
 }
-
-pub(crate) fn copy_and_draw<V: Copy, U: Uniforms>(
-  vertex_buffer: &mut VertexBuffer<V>,
-  vertex_array: &mut Vec<V>,
-  index_buffer: &IndexBuffer<u16>,
-  shader: &Shader,
-  frame: &mut Frame,
-  uniforms: &U,
-) {
-  if vertex_array.len() == vertex_buffer.len() {
-    vertex_buffer.write(vertex_array.as_slice());
-  } else {
-    vertex_buffer.invalidate();
-
-    unsafe {
-      vertex_buffer
-        .slice_mut(0..vertex_array.len())
-        .unwrap_unchecked()
-        .write(vertex_array.as_slice());
-    }
-  }
-
-  frame
-    .draw(
-      vertex_buffer.deref(),
-      index_buffer,
-      shader.program(),
-      uniforms,
-      &DRAW_PARAMETERS,
-    )
-    .unwrap();
-}
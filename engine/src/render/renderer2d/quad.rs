@@ -1,55 +1,55 @@
-use glium::index::PrimitiveType;
-use glium::{implement_vertex, uniform, Display, Frame, IndexBuffer, VertexBuffer};
-use glutin::surface::WindowSurface;
+use glium::implement_vertex;
 use nalgebra::{Matrix4, Vector3, Vector4};
 
 use crate::math::{Position, ViewProjection};
-use crate::render::renderer2d::{copy_and_draw, MAX_VERTICES, QUAD_INDEX_ARRAY};
-use crate::render::shader::Shader;
+use crate::render::backend::Backend;
+use crate::render::renderer2d::MAX_VERTICES;
 use crate::{vec4, Vec2};
 
 #[derive(Debug, Default, Copy, Clone)]
 pub struct QuadVertex {
-  position: [f32; 2],
-  tex_coords: [f32; 2],
-  color: [f32; 4],
+  pub position: [f32; 2],
+  pub tex_coords: [f32; 2],
+  pub color: [f32; 4],
 }
 
 implement_vertex!(QuadVertex, position, tex_coords, color);
 
 pub struct QuadRenderer {
   vertex_array: Vec<QuadVertex>,
-  vertex_buffer: VertexBuffer<QuadVertex>,
-  index_buffer: IndexBuffer<u16>,
-  shader: Shader,
 }
 
 impl QuadRenderer {
-  pub fn new(display: &Display<WindowSurface>, shader: Shader) -> Self {
+  pub fn new() -> Self {
     let vertex_array = Vec::with_capacity(MAX_VERTICES);
-    let vertex_buffer = VertexBuffer::empty_dynamic(display, MAX_VERTICES).unwrap();
 
-    let index_buffer = IndexBuffer::new(display, PrimitiveType::TrianglesList, &QUAD_INDEX_ARRAY).unwrap();
-
-    Self {
-      vertex_buffer,
-      vertex_array,
-      index_buffer,
-      shader,
-    }
+    Self { vertex_array }
   }
 
-  pub fn draw_quad(&mut self, frame: &mut Frame, view_projection: &ViewProjection, position: Position, size: Vec2) {
+  pub fn draw_quad<B: Backend>(
+    &mut self,
+    backend: &mut B,
+    target: &mut B::Target<'_>,
+    view_projection: &ViewProjection,
+    position: Position,
+    size: Vec2,
+  ) {
     let position = Vector3::new(position.x, position.y, 0.0);
     let size = Vector3::new(size.x, size.y, 1.0);
     let translation = Matrix4::identity().prepend_translation(&position);
     let scaling = Matrix4::identity().prepend_nonuniform_scaling(&size);
     let transform = translation * scaling;
 
-    self.draw_quad_transform(frame, view_projection, transform);
+    self.draw_quad_transform(backend, target, view_projection, transform);
   }
 
-  fn draw_quad_transform(&mut self, frame: &mut Frame, view_projection: &ViewProjection, transform: Matrix4<f32>) {
+  fn draw_quad_transform<B: Backend>(
+    &mut self,
+    backend: &mut B,
+    target: &mut B::Target<'_>,
+    view_projection: &ViewProjection,
+    transform: Matrix4<f32>,
+  ) {
     const QUAD_VERTEX_COUNT: usize = 4;
     const TEX_COORDS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
     const QUAD_VERTEX_POSITIONS: [Vector4<f32>; 4] = [
@@ -60,7 +60,7 @@ impl QuadRenderer {
     ];
 
     if self.vertex_array.len() + QUAD_VERTEX_COUNT >= MAX_VERTICES {
-      self.next_batch(frame, view_projection);
+      self.next_batch(backend, target, view_projection);
     }
 
     for i in 0..QUAD_VERTEX_COUNT {
@@ -78,25 +78,19 @@ impl QuadRenderer {
     self.clear();
   }
 
-  pub fn next_batch(&mut self, frame: &mut Frame, view_projection: &ViewProjection) {
-    self.flush(frame, view_projection);
+  pub fn next_batch<B: Backend>(
+    &mut self,
+    backend: &mut B,
+    target: &mut B::Target<'_>,
+    view_projection: &ViewProjection,
+  ) {
+    self.flush(backend, target, view_projection);
     self.start_batch();
   }
 
-  pub fn flush(&mut self, frame: &mut Frame, view_projection: &ViewProjection) {
+  pub fn flush<B: Backend>(&mut self, backend: &mut B, target: &mut B::Target<'_>, view_projection: &ViewProjection) {
     if !self.vertex_array.is_empty() {
-      let uniforms = uniform! {
-        u_view_projection: *view_projection.as_ref(),
-      };
-
-      copy_and_draw(
-        &mut self.vertex_buffer,
-        &mut self.vertex_array,
-        &self.index_buffer,
-        &self.shader,
-        frame,
-        &uniforms,
-      );
+      backend.draw_quads(target, &self.vertex_array, view_projection);
     }
   }
 
@@ -106,3 +100,9 @@ impl QuadRenderer {
     }
   }
 }
+
+impl Default for QuadRenderer {
+  fn default() -> Self {
+    Self::new()
+  }
+}
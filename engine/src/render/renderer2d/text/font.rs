@@ -3,9 +3,6 @@ use std::collections::HashMap;
 use std::fs;
 
 use bdf::Font;
-use glium::texture::{RawImage2d, Texture2d};
-use glium::Display;
-use glutin::surface::WindowSurface;
 use nalgebra::Vector2;
 
 use crate::math::u_sqrt;
@@ -196,22 +193,24 @@ fn generate_font_atlas(font: &Font) -> FontAtlas {
   }
 }
 
+/// Cpu side glyph atlas, uploaded by the [`TextRenderer`](super::TextRenderer)
 pub struct FontBitmap {
   pub font: Font,
-  pub texture: Texture2d,
+  /// Rgba pixels
+  pub atlas: Vec<u8>,
   pub atlas_size: Vector2<usize>,
   pub cell_size: Vector2<usize>,
   pub char_info: HashMap<char, CharInfo>,
 }
 
 impl FontBitmap {
-  pub fn from_bytes(display: &Display<WindowSurface>, bytes: &[u8]) -> Self {
+  pub fn from_bytes(bytes: &[u8]) -> Self {
     let font = bdf::read(bytes).unwrap();
 
-    Self::new(display, font)
+    Self::new(font)
   }
 
-  pub fn new(display: &Display<WindowSurface>, font: Font) -> Self {
+  pub fn new(font: Font) -> Self {
     let FontAtlas {
       atlas,
       cell_size,
@@ -224,18 +223,20 @@ impl FontBitmap {
     #[cfg(debug_assertions)]
     fs::write("atlas.bin", &atlas).unwrap();
 
-    let image = RawImage2d::from_raw_rgba(atlas, (atlas_size.x as u32, atlas_size.y as u32));
-    let texture = Texture2d::new(display, image).unwrap();
-
     Self {
       font,
-      texture,
+      atlas,
       atlas_size,
       cell_size,
       char_info,
     }
   }
 
+  #[inline]
+  pub fn atlas_dimensions(&self) -> (u32, u32) {
+    (self.atlas_size.x as u32, self.atlas_size.y as u32)
+  }
+
   pub fn get_info(&self, c: char) -> CharInfo {
     self.char_info.get(&c).cloned().unwrap_or_default()
   }
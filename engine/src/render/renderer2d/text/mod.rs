@@ -1,16 +1,14 @@
 use std::marker::Copy;
 use std::rc::Rc;
 
-use glium::index::PrimitiveType;
-use glium::uniforms::MagnifySamplerFilter;
-use glium::{implement_vertex, uniform, Display, Frame, IndexBuffer, VertexBuffer};
-use glutin::surface::WindowSurface;
+use glium::implement_vertex;
 use nalgebra::Matrix4;
 
 use crate::math::{Position, Size, ViewProjection};
+use crate::render::backend::gl::GlBackend;
+use crate::render::backend::Backend;
 use crate::render::renderer2d::text::font::FontBitmap;
-use crate::render::renderer2d::{copy_and_draw, MAX_VERTICES, QUAD_INDEX_ARRAY};
-use crate::render::shader::Shader;
+use crate::render::renderer2d::MAX_VERTICES;
 use crate::{vec2, vec3, vec4, Color};
 
 pub mod font;
@@ -19,40 +17,46 @@ const TAB_SIZE: f32 = 4.0;
 
 #[derive(Default, Copy, Clone)]
 pub struct TextVertex {
-  position: [f32; 2],
-  tex_coords: [f32; 2],
-  color: [f32; 4],
+  pub position: [f32; 2],
+  pub tex_coords: [f32; 2],
+  pub color: [f32; 4],
 }
 
 implement_vertex!(TextVertex, position, tex_coords, color);
 
-pub struct TextRenderer {
+pub struct TextRenderer<B: Backend = GlBackend> {
   vertex_array: Vec<TextVertex>,
-  vertex_buffer: VertexBuffer<TextVertex>,
-  index_buffer: IndexBuffer<u16>,
-  shader: Shader,
   font: Rc<FontBitmap>,
+  /// `None` while the gl context is lost
+  atlas: Option<B::Texture>,
 }
 
-impl TextRenderer {
-  pub fn new(display: &Display<WindowSurface>, shader: Shader, font: Rc<FontBitmap>) -> Self {
+impl<B: Backend> TextRenderer<B> {
+  pub fn new(backend: &B, font: Rc<FontBitmap>) -> Self {
     let vertex_array = Vec::with_capacity(MAX_VERTICES);
-    let vertex_buffer = VertexBuffer::empty_dynamic(display, MAX_VERTICES).unwrap();
-
-    let index_buffer = IndexBuffer::new(display, PrimitiveType::TrianglesList, &QUAD_INDEX_ARRAY).unwrap();
+    let atlas = Some(backend.create_texture(font.atlas.clone(), font.atlas_dimensions()));
 
     Self {
-      vertex_buffer,
       vertex_array,
-      index_buffer,
-      shader,
       font,
+      atlas,
     }
   }
 
+  /// Drop the atlas texture
+  pub fn release(&mut self) {
+    self.atlas = None;
+  }
+
+  /// Upload the atlas texture again
+  pub fn restore(&mut self, backend: &B) {
+    self.atlas = Some(backend.create_texture(self.font.atlas.clone(), self.font.atlas_dimensions()));
+  }
+
   pub fn draw_text(
     &mut self,
-    frame: &mut Frame,
+    backend: &mut B,
+    target: &mut B::Target<'_>,
     view_projection: &ViewProjection,
     position: Position,
     params: &TextParams,
@@ -64,12 +68,13 @@ impl TextRenderer {
     let scaling = Matrix4::identity().prepend_nonuniform_scaling(&size);
     let transform = translation * scaling;
 
-    self.draw_text_transform(frame, view_projection, transform, params, text);
+    self.draw_text_transform(backend, target, view_projection, transform, params, text);
   }
 
   fn draw_text_transform(
     &mut self,
-    frame: &mut Frame,
+    backend: &mut B,
+    target: &mut B::Target<'_>,
     view_projection: &ViewProjection,
     transform: Matrix4<f32>,
     params: &TextParams,
@@ -80,7 +85,7 @@ impl TextRenderer {
     }
 
     if self.vertex_array.len() + text.len() * 4 >= MAX_VERTICES {
-      self.next_batch(frame, view_projection);
+      self.next_batch(backend, target, view_projection);
     }
 
     let mut x = 0.0;
@@ -153,26 +158,16 @@ impl TextRenderer {
     self.clear();
   }
 
-  pub fn next_batch(&mut self, frame: &mut Frame, view_projection: &ViewProjection) {
-    self.flush(frame, view_projection);
+  pub fn next_batch(&mut self, backend: &mut B, target: &mut B::Target<'_>, view_projection: &ViewProjection) {
+    self.flush(backend, target, view_projection);
     self.start_batch();
   }
 
-  pub fn flush(&mut self, frame: &mut Frame, view_projection: &ViewProjection) {
+  pub fn flush(&mut self, backend: &mut B, target: &mut B::Target<'_>, view_projection: &ViewProjection) {
     if !self.vertex_array.is_empty() {
-      let uniforms = uniform! {
-        u_font_atlas: self.font.texture.sampled().magnify_filter(MagnifySamplerFilter::Nearest),
-        u_view_projection: *view_projection.as_ref(),
-      };
-
-      copy_and_draw(
-        &mut self.vertex_buffer,
-        &mut self.vertex_array,
-        &self.index_buffer,
-        &self.shader,
-        frame,
-        &uniforms,
-      );
+      let atlas = self.atlas.as_ref().expect("gl context is lost");
+
+      backend.draw_text(target, &self.vertex_array, atlas, view_projection);
     }
   }
 
@@ -1,21 +1,77 @@
+// shader programs and their sources
+// debug builds read the engine's shaders from disk and compile them again
+// when the files change, release builds only use the embedded sources
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use glium::backend::Facade;
 use glium::draw_parameters::{
   ClipControlDepth, ClipControlOrigin, DepthClamp, PolygonOffset, ProvokingVertex, Stencil,
 };
+use glium::program::ShaderType;
 use glium::{
-  BackfaceCullingMode, Blend, BlendingFunction, Depth, DepthTest, Display, DrawParameters, LinearBlendingFactor,
-  PolygonMode, Program, StencilOperation, StencilTest,
+  BackfaceCullingMode, Blend, BlendingFunction, Depth, DepthTest, DrawParameters, LinearBlendingFactor, PolygonMode,
+  Program, ProgramCreationError, StencilOperation, StencilTest,
 };
-use glutin::surface::WindowSurface;
 
+use crate::render::shader::preprocess::{Defines, Preprocessed, Preprocessor};
+
+pub mod preprocess;
+pub mod variants;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+  #[error(transparent)]
+  Preprocess(#[from] preprocess::Error),
+  /// Locations in `log` point at the original files
+  #[error("failed to compile {stage} shader:\n{log}")]
+  Compile { stage: &'static str, log: String },
+  #[error(transparent)]
+  Program(ProgramCreationError),
+}
+
+/// Where the engine's own shaders are read from in debug builds
+pub const SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders");
+
+/// Resolves the includes shared by the engine's shaders, embedded so they
+/// also work for the embedded sources of release builds
+pub fn engine_preprocessor() -> Preprocessor {
+  Preprocessor::new()
+    .with_source("include/ibl.glsl", include_str!("../../../shaders/include/ibl.glsl"))
+    .with_source(
+      "include/shadow.glsl",
+      include_str!("../../../shaders/include/shadow.glsl"),
+    )
+    .with_source(
+      "include/text_vertex.glsl",
+      include_str!("../../../shaders/include/text_vertex.glsl"),
+    )
+}
+
+/// Glium program used by the
+/// [`GlBackend`](crate::render::backend::gl::GlBackend)
 pub struct Shader {
   program: Program,
 }
 
 impl Shader {
-  pub fn new(display: &Display<WindowSurface>, vertex_shader: &str, fragment_shader: &str) -> Self {
-    let program = Program::from_source(display, vertex_shader, fragment_shader, None).unwrap();
+  /// Panics if the program doesn't compile or link
+  pub fn new<F: Facade>(facade: &F, vertex_shader: &str, fragment_shader: &str) -> Self {
+    Self::try_new(facade, vertex_shader, fragment_shader).unwrap()
+  }
+
+  pub fn try_new<F: Facade>(
+    facade: &F,
+    vertex_shader: &str,
+    fragment_shader: &str,
+  ) -> Result<Self, ProgramCreationError> {
+    let program = Program::from_source(facade, vertex_shader, fragment_shader, None)?;
 
-    Self { program }
+    Ok(Self { program })
   }
 
   #[inline]
@@ -24,6 +80,243 @@ impl Shader {
   }
 }
 
+/// Vertex and fragment source, kept so the program can be compiled again
+/// after the gl context is lost
+///
+/// Both stages go through a [`Preprocessor`] with the source's [`Defines`]
+/// before they are compiled
+#[derive(Debug, Clone)]
+pub struct ShaderSource {
+  pub vertex: Cow<'static, str>,
+  pub fragment: Cow<'static, str>,
+  files: Option<ShaderFiles>,
+  preprocessor: Rc<Preprocessor>,
+  defines: Defines,
+}
+
+/// Files a [`ShaderSource`] is reloaded from
+#[derive(Debug, Clone)]
+struct ShaderFiles {
+  vertex: PathBuf,
+  fragment: PathBuf,
+  /// Files included by either stage the last time it was read
+  includes: Vec<PathBuf>,
+  modified: Option<SystemTime>,
+}
+
+impl ShaderFiles {
+  // the newest of every file, `None` if any can't be read
+  fn modified(&self) -> Option<SystemTime> {
+    [&self.vertex, &self.fragment]
+      .into_iter()
+      .chain(&self.includes)
+      .map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+      .try_fold(SystemTime::UNIX_EPOCH, |newest, modified| Some(newest.max(modified?)))
+  }
+}
+
+impl ShaderSource {
+  pub fn new(vertex: impl Into<Cow<'static, str>>, fragment: impl Into<Cow<'static, str>>) -> Self {
+    Self {
+      vertex: vertex.into(),
+      fragment: fragment.into(),
+      files: None,
+      preprocessor: Rc::default(),
+      defines: Defines::new(),
+    }
+  }
+
+  /// Resolve includes with `preprocessor`, shared so every variant of a
+  /// shader doesn't need its own copy. Set it before
+  /// [`ShaderSource::with_files`] so includes are watched from the start
+  pub fn with_preprocessor(mut self, preprocessor: Rc<Preprocessor>) -> Self {
+    self.preprocessor = preprocessor;
+    self
+  }
+
+  /// Add `defines` to both stages, replacing values of ones already set
+  pub fn with_defines(mut self, defines: &Defines) -> Self {
+    self.defines.extend(defines);
+    self
+  }
+
+  #[inline]
+  pub fn defines(&self) -> &Defines {
+    &self.defines
+  }
+
+  /// Read the source from `vertex` and `fragment` instead and reload it
+  /// whenever they or a file they include change, see
+  /// [`ShaderSource::load`] and [`ShaderSource::reloaded`]
+  ///
+  /// Only in debug builds, release builds keep the current source. The
+  /// current source is also kept while the files are missing or don't
+  /// compile
+  pub fn with_files(mut self, vertex: impl Into<PathBuf>, fragment: impl Into<PathBuf>) -> Self {
+    if cfg!(debug_assertions) {
+      self.files = Some(ShaderFiles {
+        vertex: vertex.into(),
+        fragment: fragment.into(),
+        includes: Vec::new(),
+        modified: None,
+      });
+    }
+
+    self
+  }
+
+  /// `vertex` and `fragment` in [`SHADER_DIR`]
+  pub(crate) fn with_engine_files(self, vertex: &str, fragment: &str) -> Self {
+    let dir = Path::new(SHADER_DIR);
+
+    self.with_files(dir.join(vertex), dir.join(fragment))
+  }
+
+  /// Vertex and fragment file while reloading from disk
+  pub fn files(&self) -> Option<(&Path, &Path)> {
+    self
+      .files
+      .as_ref()
+      .map(|files| (files.vertex.as_path(), files.fragment.as_path()))
+  }
+
+  // for log messages
+  fn file_names(&self) -> String {
+    match self.files() {
+      Some((vertex, fragment)) => format!("{} and {}", vertex.display(), fragment.display()),
+      None => "shader".to_string(),
+    }
+  }
+
+  /// The source read from disk again if a file changed since the last call,
+  /// `self` keeps the old source so a broken edit can be rejected
+  pub fn reloaded(&mut self) -> Option<ShaderSource> {
+    let files = self.files.as_mut()?;
+    let modified = files.modified();
+
+    if modified.is_none() || modified == files.modified {
+      return None;
+    }
+
+    // only tried once per change even if it fails
+    files.modified = modified;
+
+    let read = |path: &Path| {
+      fs::read_to_string(path)
+        .inspect_err(|e| warn!("failed to read shader {}: {e}", path.display()))
+        .ok()
+    };
+
+    let vertex = read(&files.vertex)?;
+    let fragment = read(&files.fragment)?;
+
+    let mut source = Self {
+      vertex: vertex.into(),
+      fragment: fragment.into(),
+      ..self.clone()
+    };
+
+    // watch what the new source includes, the old list stays if it's broken
+    if let Ok((vertex, fragment)) = source.preprocess() {
+      let files = source.files.as_mut().unwrap();
+
+      files.includes = vertex.files()[1..]
+        .iter()
+        .chain(&fragment.files()[1..])
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .collect();
+      files.modified = files.modified();
+
+      // a fix for a broken edit can be in an include too
+      let watched = self.files.as_mut().unwrap();
+      watched.includes = files.includes.clone();
+      watched.modified = files.modified;
+    }
+
+    Some(source)
+  }
+
+  /// Both stages with includes resolved and defines injected
+  pub fn preprocess(&self) -> Result<(Preprocessed, Preprocessed), preprocess::Error> {
+    let (vertex_path, fragment_path) = self.files().unzip();
+
+    let name = |path: Option<&Path>, stage: &str| path.map_or(stage.to_string(), |path| path.display().to_string());
+
+    let vertex = self
+      .preprocessor
+      .process(&name(vertex_path, "vertex"), vertex_path, &self.vertex, &self.defines)?;
+    let fragment = self.preprocessor.process(
+      &name(fragment_path, "fragment"),
+      fragment_path,
+      &self.fragment,
+      &self.defines,
+    )?;
+
+    Ok((vertex, fragment))
+  }
+
+  /// Panics if the program doesn't preprocess, compile or link
+  pub fn compile<F: Facade>(&self, facade: &F) -> Shader {
+    self.try_compile(facade).unwrap_or_else(|e| panic!("{e}"))
+  }
+
+  pub fn try_compile<F: Facade>(&self, facade: &F) -> Result<Shader, Error> {
+    let (vertex, fragment) = self.preprocess()?;
+
+    Shader::try_new(facade, &vertex.source, &fragment.source).map_err(|e| match e {
+      ProgramCreationError::CompilationError(log, ShaderType::Vertex) => Error::Compile {
+        stage: "vertex",
+        log: vertex.map_log(&log),
+      },
+      ProgramCreationError::CompilationError(log, ShaderType::Fragment) => Error::Compile {
+        stage: "fragment",
+        log: fragment.map_log(&log),
+      },
+      e => Error::Program(e),
+    })
+  }
+
+  /// Compile the files when they are set and compile, otherwise the current
+  /// source with the error logged. Panics if that doesn't compile either
+  pub fn load<F: Facade>(&mut self, facade: &F) -> Shader {
+    if let Some(source) = self.reloaded() {
+      match source.try_compile(facade) {
+        Ok(shader) => {
+          *self = source;
+          return shader;
+        }
+        Err(e) => error!(
+          "failed to compile {}, using the embedded source: {e}",
+          source.file_names()
+        ),
+      }
+    }
+
+    self.compile(facade)
+  }
+
+  /// Swap `shader` for the reloaded program when the files changed and it
+  /// compiles, a broken edit is logged and `shader` stays
+  pub(crate) fn reload<F: Facade>(&mut self, facade: &F, shader: &mut Shader) {
+    let Some(source) = self.reloaded() else {
+      return;
+    };
+
+    let name = source.file_names();
+
+    match source.try_compile(facade) {
+      Ok(reloaded) => {
+        info!("reloaded {name}");
+
+        *shader = reloaded;
+        *self = source;
+      }
+      Err(e) => error!("failed to reload {name}: {e}"),
+    }
+  }
+}
+
 // make Default::default() const holy fuck
 pub const DRAW_PARAMETERS: DrawParameters<'static> = DrawParameters {
   blend: Blend {
@@ -0,0 +1,483 @@
+// glsl preprocessing before a program is compiled
+// resolves #include and injects #defines, every file gets its own source
+// string number through #line so compile errors can be mapped back to it
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+  #[error("failed to read {path}: {source}")]
+  Io { path: PathBuf, source: io::Error },
+  #[error("{file}:{line}: malformed #include, expected #include \"file\"")]
+  MalformedInclude { file: String, line: usize },
+  #[error("{file}:{line}: can't find include {name:?}")]
+  NotFound { file: String, line: usize, name: String },
+}
+
+/// `#define`s injected after `#version`, ordered so the same set always
+/// produces the same source and can key a cache
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Defines(BTreeMap<String, String>);
+
+impl Defines {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// `#define name`
+  pub fn with(self, name: impl Into<String>) -> Self {
+    self.with_value(name, "")
+  }
+
+  /// `#define name value`
+  pub fn with_value(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+    self.0.insert(name.into(), value.to_string());
+    self
+  }
+
+  /// Add every define in `other`, replacing values of ones already set
+  pub fn extend(&mut self, other: &Defines) {
+    self
+      .0
+      .extend(other.0.iter().map(|(name, value)| (name.clone(), value.clone())));
+  }
+
+  #[inline]
+  pub fn contains(&self, name: &str) -> bool {
+    self.0.contains_key(name)
+  }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.0.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+  }
+}
+
+/// Resolves `#include "file"` and injects [`Defines`]
+///
+/// Includes are looked up next to the including file, then in every
+/// directory of the search path in order, then in the sources added with
+/// [`Preprocessor::with_source`]. Each file is included once per shader, so
+/// shared structs need no include guards
+#[derive(Debug, Clone, Default)]
+pub struct Preprocessor {
+  search_path: Vec<PathBuf>,
+  sources: HashMap<String, Cow<'static, str>>,
+}
+
+/// Output of [`Preprocessor::process`]
+#[derive(Debug, Clone)]
+pub struct Preprocessed {
+  pub source: String,
+  /// Indexed by the source string number given to `#line`
+  files: Vec<String>,
+}
+
+struct Resolved<'a> {
+  name: String,
+  dir: Option<PathBuf>,
+  source: Cow<'a, str>,
+}
+
+struct State {
+  output: String,
+  files: Vec<String>,
+  included: HashSet<String>,
+  // written after the root's #version, or before everything without one
+  defines: Option<String>,
+}
+
+impl Preprocessor {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Look for includes in `dir` after the including file's own directory
+  pub fn with_search_path(mut self, dir: impl Into<PathBuf>) -> Self {
+    self.search_path.push(dir.into());
+    self
+  }
+
+  /// Source for `#include "name"` when no file on the search path matches,
+  /// e.g. embedded with `include_str!` for release builds
+  pub fn with_source(mut self, name: impl Into<String>, source: impl Into<Cow<'static, str>>) -> Self {
+    self.sources.insert(name.into(), source.into());
+    self
+  }
+
+  /// Preprocess `source` called `name` in error messages, includes are
+  /// looked up next to `path` first when it came from a file
+  pub fn process(
+    &self,
+    name: &str,
+    path: Option<&Path>,
+    source: &str,
+    defines: &Defines,
+  ) -> Result<Preprocessed, Error> {
+    let mut injected = String::new();
+
+    for (name, value) in defines.iter() {
+      let _ = writeln!(injected, "#define {name} {value}");
+    }
+
+    let mut state = State {
+      output: String::with_capacity(source.len()),
+      files: vec![name.to_string()],
+      included: HashSet::from([name.to_string()]),
+      defines: Some(injected),
+    };
+
+    let has_version = source.lines().any(|line| line.trim_start().starts_with("#version"));
+
+    if !has_version {
+      let defines = state.defines.take().unwrap_or_default();
+
+      state.output.push_str(&defines);
+      state.output.push_str("#line 1 0\n");
+    }
+
+    let dir = path.and_then(Path::parent);
+    self.append(&mut state, 0, dir, source)?;
+
+    Ok(Preprocessed {
+      source: state.output,
+      files: state.files,
+    })
+  }
+
+  fn append(&self, state: &mut State, index: usize, dir: Option<&Path>, source: &str) -> Result<(), Error> {
+    for (number, line) in source.lines().enumerate() {
+      let number = number + 1;
+      let trimmed = line.trim_start();
+
+      if trimmed.starts_with("#version") {
+        if index == 0 {
+          state.output.push_str(line);
+          state.output.push('\n');
+
+          if let Some(defines) = state.defines.take() {
+            state.output.push_str(&defines);
+            let _ = writeln!(state.output, "#line {} 0", number + 1);
+          }
+        } else {
+          // only the root picks the version
+          state.output.push('\n');
+        }
+
+        continue;
+      }
+
+      let Some(rest) = trimmed.strip_prefix("#include") else {
+        state.output.push_str(line);
+        state.output.push('\n');
+        continue;
+      };
+
+      let malformed = || Error::MalformedInclude {
+        file: state.files[index].clone(),
+        line: number,
+      };
+      let name = parse_include(rest).ok_or_else(malformed)?;
+
+      let resolved = self.resolve(name, dir)?.ok_or_else(|| Error::NotFound {
+        file: state.files[index].clone(),
+        line: number,
+        name: name.to_string(),
+      })?;
+
+      if !state.included.insert(resolved.name.clone()) {
+        state.output.push('\n');
+        continue;
+      }
+
+      let child = state.files.len();
+      state.files.push(resolved.name);
+
+      let _ = writeln!(state.output, "#line 1 {child}");
+      self.append(state, child, resolved.dir.as_deref(), &resolved.source)?;
+      let _ = writeln!(state.output, "#line {} {index}", number + 1);
+    }
+
+    Ok(())
+  }
+
+  fn resolve(&self, name: &str, dir: Option<&Path>) -> Result<Option<Resolved<'_>>, Error> {
+    let dirs = dir.into_iter().chain(self.search_path.iter().map(PathBuf::as_path));
+
+    for path in dirs.map(|dir| dir.join(name)) {
+      if !path.is_file() {
+        continue;
+      }
+
+      let source = fs::read_to_string(&path).map_err(|source| Error::Io {
+        path: path.clone(),
+        source,
+      })?;
+
+      return Ok(Some(Resolved {
+        name: path.display().to_string(),
+        dir: path.parent().map(Path::to_path_buf),
+        source: source.into(),
+      }));
+    }
+
+    Ok(self.sources.get(name).map(|source| Resolved {
+      name: name.to_string(),
+      dir: None,
+      source: Cow::Borrowed(source),
+    }))
+  }
+}
+
+// `"file"` or `<file>` after #include
+fn parse_include(rest: &str) -> Option<&str> {
+  let rest = rest.trim();
+
+  let name = match rest.chars().next()? {
+    '"' => rest.strip_prefix('"')?.strip_suffix('"')?,
+    '<' => rest.strip_prefix('<')?.strip_suffix('>')?,
+    _ => return None,
+  };
+
+  (!name.is_empty()).then_some(name)
+}
+
+impl Preprocessed {
+  /// Every file that went into the source, the root first
+  #[inline]
+  pub fn files(&self) -> &[String] {
+    &self.files
+  }
+
+  /// Replace the source string numbers in a driver's info log with file
+  /// names, drivers write locations as `0:12`, `0(12)` or `0:12(5)`
+  pub fn map_log(&self, log: &str) -> String {
+    let bytes = log.as_bytes();
+    let mut mapped = String::with_capacity(log.len());
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+      let starts_number = bytes[i].is_ascii_digit() && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric());
+
+      if !starts_number {
+        i += 1;
+        continue;
+      }
+
+      let file_end = digits_end(bytes, i);
+      let location = log[i..file_end]
+        .parse::<usize>()
+        .ok()
+        .and_then(|file| Some((self.files.get(file)?, line_after(log, file_end)?)));
+
+      match location {
+        Some((file, (line, end))) => {
+          mapped.push_str(&log[copied..i]);
+          let _ = write!(mapped, "{file}:{line}");
+
+          copied = end;
+          i = end;
+        }
+        None => i = file_end,
+      }
+    }
+
+    mapped.push_str(&log[copied..]);
+    mapped
+  }
+}
+
+fn digits_end(bytes: &[u8], start: usize) -> usize {
+  start + bytes[start..].iter().take_while(|byte| byte.is_ascii_digit()).count()
+}
+
+// `:12` or `(12)` right after the source string number, with the end of it
+fn line_after(log: &str, start: usize) -> Option<(&str, usize)> {
+  let bytes = log.as_bytes();
+  let open = *bytes.get(start)?;
+
+  if open != b':' && open != b'(' {
+    return None;
+  }
+
+  let end = digits_end(bytes, start + 1);
+
+  if end == start + 1 {
+    return None;
+  }
+
+  let line = &log[start + 1..end];
+
+  match open {
+    b'(' if bytes.get(end) == Some(&b')') => Some((line, end + 1)),
+    b':' => Some((line, end)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::env;
+
+  use super::*;
+
+  fn preprocessor() -> Preprocessor {
+    Preprocessor::new()
+      .with_source("common.glsl", "float a;\n#include \"nested.glsl\"\n")
+      .with_source("nested.glsl", "#version 450\nfloat b;\n")
+  }
+
+  #[test]
+  fn line_numbers_follow_every_file() {
+    let source = "#version 450\nvoid f();\n#include \"common.glsl\"\nvoid main() {}\n";
+
+    let output = preprocessor()
+      .process("main.frag", None, source, &Defines::new())
+      .unwrap();
+
+    assert_eq!(
+      output.source,
+      "#version 450\n\
+       #line 2 0\n\
+       void f();\n\
+       #line 1 1\n\
+       float a;\n\
+       #line 1 2\n\
+       \n\
+       float b;\n\
+       #line 3 1\n\
+       #line 4 0\n\
+       void main() {}\n"
+    );
+    assert_eq!(output.files(), ["main.frag", "common.glsl", "nested.glsl"]);
+  }
+
+  #[test]
+  fn files_are_included_once() {
+    let source = "#include \"nested.glsl\"\n#include \"common.glsl\"\n#include <nested.glsl>\nvoid main() {}\n";
+
+    let output = preprocessor()
+      .process("main.frag", None, source, &Defines::new())
+      .unwrap();
+
+    // common.glsl and the second include of nested.glsl become blank lines
+    assert_eq!(output.source.matches("float b;").count(), 1);
+    assert_eq!(output.files(), ["main.frag", "nested.glsl", "common.glsl"]);
+    assert!(output.source.ends_with("float a;\n\n#line 3 0\n\nvoid main() {}\n"));
+  }
+
+  #[test]
+  fn defines_go_after_version() {
+    let defines = Defines::new().with("SHADOWS").with_value("LIGHTS", 4);
+
+    let output = Preprocessor::new()
+      .process(
+        "main.frag",
+        None,
+        "// comment\n#version 450 core\nvoid main() {}\n",
+        &defines,
+      )
+      .unwrap();
+
+    assert_eq!(
+      output.source,
+      "// comment\n\
+       #version 450 core\n\
+       #define LIGHTS 4\n\
+       #define SHADOWS \n\
+       #line 3 0\n\
+       void main() {}\n"
+    );
+
+    // without a version the defines come first
+    let output = Preprocessor::new()
+      .process("main.frag", None, "void main() {}\n", &defines)
+      .unwrap();
+
+    assert_eq!(
+      output.source,
+      "#define LIGHTS 4\n#define SHADOWS \n#line 1 0\nvoid main() {}\n"
+    );
+  }
+
+  #[test]
+  fn files_next_to_the_shader_come_first() {
+    let dir = env::temp_dir().join(format!("algurulgar-preprocess-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("common.glsl"), "float from_disk;\n").unwrap();
+
+    let path = dir.join("main.frag");
+    let output = preprocessor()
+      .process("main.frag", Some(&path), "#include \"common.glsl\"\n", &Defines::new())
+      .unwrap();
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.source.contains("float from_disk;"));
+    assert!(!output.source.contains("float a;"));
+    assert_eq!(output.files()[1], dir.join("common.glsl").display().to_string());
+  }
+
+  #[test]
+  fn broken_includes() {
+    let error = preprocessor()
+      .process(
+        "main.frag",
+        None,
+        "void f();\n#include \"missing.glsl\"\n",
+        &Defines::new(),
+      )
+      .unwrap_err();
+
+    assert!(matches!(
+      error,
+      Error::NotFound { ref file, line: 2, ref name } if file == "main.frag" && name == "missing.glsl"
+    ));
+
+    for include in [
+      "#include common.glsl",
+      "#include \"common.glsl",
+      "#include \"\"",
+      "#include",
+    ] {
+      let error = preprocessor()
+        .process("main.frag", None, include, &Defines::new())
+        .unwrap_err();
+
+      assert!(matches!(error, Error::MalformedInclude { line: 1, .. }), "{include}");
+    }
+  }
+
+  #[test]
+  fn map_log_formats() {
+    let output = preprocessor()
+      .process("main.frag", None, "#include \"common.glsl\"\n", &Defines::new())
+      .unwrap();
+
+    // mesa, nvidia and amd
+    assert_eq!(
+      output.map_log("0:12(5): error: `x' undeclared"),
+      "main.frag:12(5): error: `x' undeclared"
+    );
+    assert_eq!(
+      output.map_log("1(3) : error C1008: undefined variable"),
+      "common.glsl:3 : error C1008: undefined variable"
+    );
+    assert_eq!(
+      output.map_log("ERROR: 2:12: 'b' : redefinition\nERROR: 0:1: x"),
+      "ERROR: nested.glsl:12: 'b' : redefinition\nERROR: main.frag:1: x"
+    );
+
+    // unknown source strings and other numbers stay
+    assert_eq!(output.map_log("7:1: vec4:2 (3) 0:x"), "7:1: vec4:2 (3) 0:x");
+  }
+}
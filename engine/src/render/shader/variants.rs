@@ -0,0 +1,95 @@
+// permutations of one shader, e.g. with WIREFRAME or SHADOWS defined
+// each define set is compiled the first time it's used and kept after that,
+// a reload recompiles every kept set from the new source
+
+use std::collections::HashMap;
+
+use glium::backend::Facade;
+
+use crate::render::shader::preprocess::Defines;
+use crate::render::shader::{Error, Shader, ShaderSource};
+
+/// Variants of a [`ShaderSource`] cached by their [`Defines`]
+pub struct ShaderVariants {
+  source: ShaderSource,
+  variants: HashMap<Defines, Shader>,
+}
+
+impl ShaderVariants {
+  /// Defines already on `source` are part of every variant
+  pub fn new(source: ShaderSource) -> Self {
+    Self {
+      source,
+      variants: HashMap::new(),
+    }
+  }
+
+  /// The variant with `defines` added, compiled on first use. Failed
+  /// compiles aren't cached
+  pub fn get<F: Facade>(&mut self, facade: &F, defines: &Defines) -> Result<&Shader, Error> {
+    if !self.variants.contains_key(defines) {
+      let shader = self.source.clone().with_defines(defines).try_compile(facade)?;
+
+      self.variants.insert(defines.clone(), shader);
+    }
+
+    Ok(&self.variants[defines])
+  }
+
+  /// The variant without any extra defines
+  pub fn base<F: Facade>(&mut self, facade: &F) -> Result<&Shader, Error> {
+    self.get(facade, &Defines::new())
+  }
+
+  #[inline]
+  pub fn source(&self) -> &ShaderSource {
+    &self.source
+  }
+
+  /// Variants compiled so far
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.variants.len()
+  }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.variants.is_empty()
+  }
+
+  /// Drop every compiled variant, e.g. before the gl context is lost
+  pub fn clear(&mut self) {
+    self.variants.clear();
+  }
+
+  /// Recompile every compiled variant when the files changed, see
+  /// [`ShaderSource::with_files`]. If any variant fails they all stay on
+  /// the old source. True when the new source was taken
+  pub(crate) fn reload<F: Facade>(&mut self, facade: &F) -> bool {
+    let Some(source) = self.source.reloaded() else {
+      return false;
+    };
+
+    let name = source.file_names();
+    let mut variants = HashMap::with_capacity(self.variants.len());
+
+    for defines in self.variants.keys() {
+      match source.clone().with_defines(defines).try_compile(facade) {
+        Ok(shader) => {
+          variants.insert(defines.clone(), shader);
+        }
+        Err(e) => {
+          error!("failed to reload {name} with {defines:?}: {e}");
+          return false;
+        }
+      }
+    }
+
+    info!("reloaded {name}");
+
+    self.source = source;
+    self.variants = variants;
+
+    true
+  }
+}
@@ -0,0 +1,184 @@
+use glium::backend::Facade;
+use glium::framebuffer::{MultiOutputFrameBuffer, SimpleFrameBuffer};
+use glium::index::IndicesSource;
+use glium::texture::{MipmapsOption, RawImage2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, Uniforms};
+use glium::vertex::MultiVerticesSource;
+use glium::{
+  BlitMask, BlitTarget, DrawError, DrawParameters, Frame, Program, Rect, Surface, SwapBuffersError, Texture2d,
+};
+use image::RgbaImage;
+
+/// Surface layers draw into
+///
+/// Either the window's frame or an offscreen framebuffer when running
+/// [`Headless`](crate::headless::Headless)
+pub enum Target<'a> {
+  Frame(Frame),
+  Offscreen(SimpleFrameBuffer<'a>),
+}
+
+impl<'a> Target<'a> {
+  /// Swap buffers if this is a window frame, does nothing for offscreen targets
+  pub fn finish(self) -> Result<(), SwapBuffersError> {
+    match self {
+      Target::Frame(frame) => frame.finish(),
+      Target::Offscreen(_) => Ok(()),
+    }
+  }
+
+  #[inline]
+  pub fn is_offscreen(&self) -> bool {
+    matches!(self, Target::Offscreen(_))
+  }
+
+  /// Copy what has been drawn so far, top row first
+  ///
+  /// Stalls until the gpu caught up, multisampled frames are resolved
+  pub fn read_pixels<F: Facade>(&self, facade: &F) -> RgbaImage {
+    let (width, height) = self.get_dimensions();
+
+    let texture = Texture2d::empty_with_format(
+      facade,
+      UncompressedFloatFormat::U8U8U8U8,
+      MipmapsOption::NoMipmap,
+      width,
+      height,
+    )
+    .unwrap();
+
+    let rect = Rect {
+      left: 0,
+      bottom: 0,
+      width,
+      height,
+    };
+    let blit_target = BlitTarget {
+      left: 0,
+      bottom: 0,
+      width: width as i32,
+      height: height as i32,
+    };
+
+    let surface = texture.as_surface();
+    let filter = MagnifySamplerFilter::Nearest;
+
+    match self {
+      Target::Frame(_) => surface.blit_buffers_from_frame(&rect, &blit_target, filter, BlitMask::color()),
+      Target::Offscreen(fb) => {
+        surface.blit_buffers_from_simple_framebuffer(fb, &rect, &blit_target, filter, BlitMask::color())
+      }
+    }
+
+    let raw: RawImage2d<u8> = texture.read();
+    let mut image = RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned()).unwrap();
+
+    // gl reads bottom row first
+    image::imageops::flip_vertical_in_place(&mut image);
+
+    image
+  }
+}
+
+impl<'a> Surface for Target<'a> {
+  fn clear(
+    &mut self,
+    rect: Option<&Rect>,
+    color: Option<(f32, f32, f32, f32)>,
+    color_srgb: bool,
+    depth: Option<f32>,
+    stencil: Option<i32>,
+  ) {
+    match self {
+      Target::Frame(frame) => frame.clear(rect, color, color_srgb, depth, stencil),
+      Target::Offscreen(fb) => fb.clear(rect, color, color_srgb, depth, stencil),
+    }
+  }
+
+  fn get_dimensions(&self) -> (u32, u32) {
+    match self {
+      Target::Frame(frame) => frame.get_dimensions(),
+      Target::Offscreen(fb) => fb.get_dimensions(),
+    }
+  }
+
+  fn get_depth_buffer_bits(&self) -> Option<u16> {
+    match self {
+      Target::Frame(frame) => frame.get_depth_buffer_bits(),
+      Target::Offscreen(fb) => fb.get_depth_buffer_bits(),
+    }
+  }
+
+  fn get_stencil_buffer_bits(&self) -> Option<u16> {
+    match self {
+      Target::Frame(frame) => frame.get_stencil_buffer_bits(),
+      Target::Offscreen(fb) => fb.get_stencil_buffer_bits(),
+    }
+  }
+
+  fn draw<'b, 'c, V, I, U>(
+    &mut self,
+    vertices: V,
+    indices: I,
+    program: &Program,
+    uniforms: &U,
+    draw_parameters: &DrawParameters<'_>,
+  ) -> Result<(), DrawError>
+  where
+    V: MultiVerticesSource<'c>,
+    I: Into<IndicesSource<'b>>,
+    U: Uniforms,
+  {
+    match self {
+      Target::Frame(frame) => frame.draw(vertices, indices, program, uniforms, draw_parameters),
+      Target::Offscreen(fb) => fb.draw(vertices, indices, program, uniforms, draw_parameters),
+    }
+  }
+
+  fn blit_buffers_from_frame(
+    &self,
+    source_rect: &Rect,
+    target_rect: &BlitTarget,
+    filter: MagnifySamplerFilter,
+    mask: BlitMask,
+  ) {
+    match self {
+      Target::Frame(frame) => frame.blit_buffers_from_frame(source_rect, target_rect, filter, mask),
+      Target::Offscreen(fb) => fb.blit_buffers_from_frame(source_rect, target_rect, filter, mask),
+    }
+  }
+
+  fn blit_buffers_from_simple_framebuffer(
+    &self,
+    source: &SimpleFrameBuffer<'_>,
+    source_rect: &Rect,
+    target_rect: &BlitTarget,
+    filter: MagnifySamplerFilter,
+    mask: BlitMask,
+  ) {
+    match self {
+      Target::Frame(frame) => {
+        frame.blit_buffers_from_simple_framebuffer(source, source_rect, target_rect, filter, mask)
+      }
+      Target::Offscreen(fb) => fb.blit_buffers_from_simple_framebuffer(source, source_rect, target_rect, filter, mask),
+    }
+  }
+
+  fn blit_buffers_from_multioutput_framebuffer(
+    &self,
+    source: &MultiOutputFrameBuffer<'_>,
+    source_rect: &Rect,
+    target_rect: &BlitTarget,
+    filter: MagnifySamplerFilter,
+    mask: BlitMask,
+  ) {
+    match self {
+      Target::Frame(frame) => {
+        frame.blit_buffers_from_multioutput_framebuffer(source, source_rect, target_rect, filter, mask)
+      }
+      Target::Offscreen(fb) => {
+        fb.blit_buffers_from_multioutput_framebuffer(source, source_rect, target_rect, filter, mask)
+      }
+    }
+  }
+}
@@ -1,3 +1,5 @@
+use crate::engine::input::Input;
+
 pub trait UpdateHandler {
-  fn update(&mut self, delta: f32);
+  fn update(&mut self, input: &Input, delta: f32);
 }
@@ -1,39 +1,105 @@
-use std::sync::atomic::{AtomicU32, Ordering};
+use glium::Rect;
 
-use atomic_float::AtomicF32;
+/// Pixel rectangle that is drawn into, the whole window or part of it for
+/// split screen and render targets
+///
+/// `x` and `y` are measured from the bottom left like gl
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
 
-pub type Viewport = (u32, u32);
-pub type ViewportF = (f32, f32);
+impl Viewport {
+  #[inline]
+  pub const fn new(width: u32, height: u32) -> Self {
+    Self {
+      x: 0,
+      y: 0,
+      width,
+      height,
+    }
+  }
 
-static VIEWPORT: (AtomicU32, AtomicU32) = (AtomicU32::new(0), AtomicU32::new(0));
-static VIEWPORT_F: (AtomicF32, AtomicF32) = (AtomicF32::new(0.0), AtomicF32::new(0.0));
-static ASPECT_RATIO: AtomicF32 = AtomicF32::new(1.0);
+  #[inline]
+  pub const fn with_offset(mut self, x: u32, y: u32) -> Self {
+    self.x = x;
+    self.y = y;
+    self
+  }
 
-#[inline]
-pub fn viewport() -> Viewport {
-  (VIEWPORT.0.load(Ordering::Acquire), VIEWPORT.1.load(Ordering::Acquire))
-}
+  #[inline]
+  pub const fn size(&self) -> (u32, u32) {
+    (self.width, self.height)
+  }
 
-#[inline]
-pub fn viewport_f() -> ViewportF {
-  (
-    VIEWPORT_F.0.load(Ordering::Acquire),
-    VIEWPORT_F.1.load(Ordering::Acquire),
-  )
-}
+  #[inline]
+  pub fn size_f(&self) -> (f32, f32) {
+    (self.width as f32, self.height as f32)
+  }
+
+  #[inline]
+  pub const fn is_empty(&self) -> bool {
+    self.width == 0 || self.height == 0
+  }
+
+  /// Width over height, 1 while empty so cameras never get a zero aspect
+  #[inline]
+  pub fn aspect_ratio(&self) -> f32 {
+    if self.is_empty() {
+      1.0
+    } else {
+      self.width as f32 / self.height as f32
+    }
+  }
 
-#[inline]
-pub fn set_viewport((width, height): Viewport) {
-  VIEWPORT.0.store(width, Ordering::Release);
-  VIEWPORT.1.store(height, Ordering::Release);
+  /// `count` equal viewports side by side, left to right
+  pub fn split_columns(&self, count: u32) -> impl Iterator<Item = Viewport> + '_ {
+    let width = self.width / count.max(1);
 
-  VIEWPORT_F.0.store(width as f32, Ordering::Release);
-  VIEWPORT_F.1.store(height as f32, Ordering::Release);
+    (0..count).map(move |i| Viewport {
+      x: self.x + i * width,
+      y: self.y,
+      width,
+      height: self.height,
+    })
+  }
+
+  /// `count` equal viewports stacked, top to bottom
+  pub fn split_rows(&self, count: u32) -> impl Iterator<Item = Viewport> + '_ {
+    let height = self.height / count.max(1);
+
+    (0..count).map(move |i| Viewport {
+      x: self.x,
+      y: self.y + self.height - (i + 1) * height,
+      width: self.width,
+      height,
+    })
+  }
+
+  #[inline]
+  pub const fn rect(&self) -> Rect {
+    Rect {
+      left: self.x,
+      bottom: self.y,
+      width: self.width,
+      height: self.height,
+    }
+  }
+}
 
-  ASPECT_RATIO.store(width as f32 / height as f32, Ordering::Release);
+impl From<(u32, u32)> for Viewport {
+  #[inline]
+  fn from((width, height): (u32, u32)) -> Self {
+    Self::new(width, height)
+  }
 }
 
-#[inline]
-pub fn aspect_ratio() -> f32 {
-  ASPECT_RATIO.load(Ordering::Acquire)
+impl From<winit::dpi::PhysicalSize<u32>> for Viewport {
+  #[inline]
+  fn from(size: winit::dpi::PhysicalSize<u32>) -> Self {
+    Self::new(size.width, size.height)
+  }
 }
@@ -0,0 +1,26 @@
+// screenshots read back through the capture path of a headless run
+
+use algurulgar::headless::Headless;
+use algurulgar::EngineConfig;
+
+mod common;
+
+#[test]
+fn screenshots_are_opaque() {
+  // the default clear color is transparent
+  let config = EngineConfig::new("capture").size(8, 8);
+  assert_eq!(config.clear_color.3, 0.0);
+
+  let Some(mut headless) = common::or_skip("capture", Headless::new(config, Vec::new())) else {
+    return;
+  };
+
+  let screenshot = headless.context.capture.screenshot(false);
+  let image = headless.frame(1.0 / 60.0).unwrap();
+
+  assert!(image.pixels().all(|pixel| pixel[3] == 0));
+
+  let captured = screenshot.take().unwrap();
+  assert_eq!(captured.dimensions(), (8, 8));
+  assert!(captured.pixels().all(|pixel| pixel[3] == u8::MAX));
+}
@@ -0,0 +1,19 @@
+// shared by the integration tests that need a gpu
+
+use std::env;
+
+use algurulgar::headless::{Error, Headless, SKIP_GPU_ENV};
+
+/// `None` if there is no egl device and skipping was asked for through
+/// [`SKIP_GPU_ENV`], a misconfigured ci runner fails instead of passing
+/// without drawing anything
+pub fn or_skip(name: &str, headless: Result<Headless, Error>) -> Option<Headless> {
+  match headless {
+    Ok(headless) => Some(headless),
+    Err(Error::NoDevice) if env::var_os(SKIP_GPU_ENV).is_some() => {
+      eprintln!("skipping {name}, no egl device and {SKIP_GPU_ENV} is set");
+      None
+    }
+    Err(err) => panic!("{err}"),
+  }
+}
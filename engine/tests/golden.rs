@@ -0,0 +1,120 @@
+// golden image tests, each scene is drawn headless and compared against its
+// reference png in tests/golden
+// run with ALGURULGAR_BLESS=1 to write the references after a deliberate
+// change, ALGURULGAR_SKIP_GPU=1 lets them pass on machines without an egl
+// device
+
+use std::path::PathBuf;
+
+use algurulgar::headless::{compare_golden, Headless};
+use algurulgar::render::renderer::mesh::{Mesh, Vertex};
+use algurulgar::render::renderer2d::text::TextParams;
+use algurulgar::render::target::Target;
+use algurulgar::window::Viewport;
+use algurulgar::{vec2, vec3, Color, EngineConfig, EngineContext, Layer, OrthoCameraController, Vec3};
+
+mod common;
+
+// drivers may round blended channels differently
+const TOLERANCE: u8 = 1;
+
+/// Draws with a camera that spans [-1, 1] on both axes of a square target
+struct Scene<F> {
+  camera: OrthoCameraController,
+  draw: F,
+}
+
+impl<F: FnMut(&mut EngineContext, &mut Target, &OrthoCameraController)> Layer for Scene<F> {
+  fn draw(&mut self, context: &mut EngineContext, target: &mut Target) {
+    (self.draw)(context, target, &self.camera);
+  }
+}
+
+// renders one frame of `draw` and compares it to tests/golden/`name`.png
+fn golden(name: &str, size: u32, draw: impl FnMut(&mut EngineContext, &mut Target, &OrthoCameraController) + 'static) {
+  let config = EngineConfig::new(name).size(size, size).clear_color(Color::BLACK);
+
+  let scene = Scene {
+    camera: OrthoCameraController::new(Viewport::new(size, size)),
+    draw,
+  };
+
+  let Some(mut headless) = common::or_skip(name, Headless::new(config, vec![Box::new(scene)])) else {
+    return;
+  };
+
+  let image = headless.frame(1.0 / 60.0).unwrap();
+  let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("tests/golden/{name}.png"));
+
+  if let Err(err) = compare_golden(&image, &path, TOLERANCE) {
+    panic!("{err}");
+  }
+}
+
+#[test]
+fn quads() {
+  golden("quads", 64, |context, target, camera| {
+    let mut renderer2d = context.renderer2d.begin(camera, target);
+
+    renderer2d.draw_quad(vec2(0.0, 0.0), vec2(1.0, 1.0));
+    renderer2d.draw_quad(vec2(-0.75, 0.75), vec2(0.25, 0.25));
+
+    renderer2d.finish();
+  });
+}
+
+#[test]
+fn text() {
+  golden("text", 256, |context, target, camera| {
+    let mut renderer2d = context.renderer2d.begin(camera, target);
+
+    // two pixels per atlas texel, the scale applies twice
+    renderer2d.draw_text("Hi", vec2(-0.875, -0.5625), &TextParams::new().scale(0.125));
+
+    renderer2d.finish();
+  });
+}
+
+#[test]
+fn mesh() {
+  const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+  const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+
+  // the long edge is steep enough that no pixel center lies on it
+  let triangle = Mesh::new(
+    vec![
+      Vertex {
+        position: [-0.75, 0.75, 0.0],
+        color: RED,
+      },
+      Vertex {
+        position: [-0.75, -0.75, 0.0],
+        color: RED,
+      },
+      Vertex {
+        position: [0.0, -0.75, 0.0],
+        color: RED,
+      },
+    ],
+    vec![0, 1, 2],
+  );
+
+  let square = Mesh::new(
+    [[-0.125, -0.125], [0.125, -0.125], [0.125, 0.125], [-0.125, 0.125]]
+      .map(|[x, y]| Vertex {
+        position: [x, y, 0.0],
+        color: GREEN,
+      })
+      .to_vec(),
+    vec![0, 1, 2, 2, 3, 0],
+  );
+
+  golden("mesh", 64, move |context, target, camera| {
+    let mut renderer = context.renderer.begin(camera, target);
+
+    renderer.draw(&triangle, &Vec3::zeros(), &Vec3::zeros(), &vec3(1.0, 1.0, 1.0));
+    renderer.draw(&square, &vec3(0.5, 0.5, 0.0), &Vec3::zeros(), &vec3(1.0, 1.0, 1.0));
+
+    renderer.finish();
+  });
+}
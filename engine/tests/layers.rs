@@ -0,0 +1,214 @@
+// layer stack ordering and the hooks layers get as they come and go
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use algurulgar::headless::Headless;
+use algurulgar::render::target::Target;
+use algurulgar::{EngineConfig, EngineContext, Layer};
+
+mod common;
+
+type Log = Rc<RefCell<Vec<String>>>;
+
+/// Writes every hook it gets to the shared log
+struct Probe {
+  name: &'static str,
+  log: Log,
+}
+
+impl Probe {
+  fn boxed(name: &'static str, log: &Log) -> Box<dyn Layer> {
+    Box::new(Probe { name, log: log.clone() })
+  }
+
+  fn record(&self, hook: &str) {
+    self.log.borrow_mut().push(format!("{hook} {}", self.name));
+  }
+}
+
+impl Layer for Probe {
+  fn name(&self) -> &'static str {
+    self.name
+  }
+
+  fn on_attach(&mut self, _context: &mut EngineContext) {
+    self.record("attach");
+  }
+
+  fn on_gpu_init(&mut self, _context: &mut EngineContext) {
+    self.record("gpu_init");
+  }
+
+  fn on_detach(&mut self, _context: &mut EngineContext) {
+    self.record("detach");
+  }
+
+  fn update(&mut self, _context: &mut EngineContext) {
+    self.record("update");
+  }
+
+  fn draw(&mut self, _context: &mut EngineContext, _target: &mut Target) {
+    self.record("draw");
+  }
+
+  fn handle_user_event(&mut self, _context: &mut EngineContext, _event: &()) -> bool {
+    self.record("event");
+    false
+  }
+}
+
+fn headless(name: &str, layers: &[&'static str]) -> Option<(Headless, Log)> {
+  let log = Log::default();
+  let layers = layers.iter().map(|&layer| Probe::boxed(layer, &log)).collect();
+
+  let headless = common::or_skip(name, Headless::new(EngineConfig::new(name).size(8, 8), layers))?;
+
+  Some((headless, log))
+}
+
+fn names(headless: &mut Headless) -> Vec<&'static str> {
+  headless.layers.iter_mut().map(|layer| layer.name()).collect()
+}
+
+#[test]
+fn overlays_stay_on_top() {
+  let Some((mut headless, log)) = headless("overlays", &["a", "b"]) else {
+    return;
+  };
+
+  assert_eq!(log.take(), ["attach a", "gpu_init a", "attach b", "gpu_init b"]);
+
+  let Headless { layers, context, .. } = &mut headless;
+
+  layers.push_overlay(Probe::boxed("o1", &log), context);
+  layers.push_layer(Probe::boxed("c", &log), context);
+  layers.push_overlay(Probe::boxed("o2", &log), context);
+  layers.push_layer(Probe::boxed("d", &log), context);
+
+  assert_eq!(layers.len(), 6);
+  assert_eq!(layers.overlay_count(), 2);
+  assert_eq!(names(&mut headless), ["a", "b", "c", "d", "o1", "o2"]);
+
+  let Headless { layers, context, .. } = &mut headless;
+
+  assert_eq!(layers.pop_layer(context).map(|layer| layer.name()), Some("d"));
+  assert_eq!(layers.pop_overlay(context).map(|layer| layer.name()), Some("o2"));
+  assert_eq!(layers.pop_layer(context).map(|layer| layer.name()), Some("c"));
+  assert_eq!(layers.pop_layer(context).map(|layer| layer.name()), Some("b"));
+  assert_eq!(layers.pop_layer(context).map(|layer| layer.name()), Some("a"));
+
+  // popping layers never reaches into the overlays
+  assert!(layers.pop_layer(context).is_none());
+  assert_eq!(layers.overlay_count(), 1);
+
+  // and a layer pushed now still goes below them
+  layers.push_layer(Probe::boxed("e", &log), context);
+  assert_eq!(names(&mut headless), ["e", "o1"]);
+
+  let Headless { layers, context, .. } = &mut headless;
+
+  assert_eq!(layers.pop_overlay(context).map(|layer| layer.name()), Some("o1"));
+  assert!(layers.pop_overlay(context).is_none());
+  assert_eq!(layers.overlay_count(), 0);
+  assert_eq!(names(&mut headless), ["e"]);
+
+  assert_eq!(
+    log.take(),
+    [
+      "attach o1",
+      "gpu_init o1",
+      "attach c",
+      "gpu_init c",
+      "attach o2",
+      "gpu_init o2",
+      "attach d",
+      "gpu_init d",
+      "detach d",
+      "detach o2",
+      "detach c",
+      "detach b",
+      "detach a",
+      "attach e",
+      "gpu_init e",
+      "detach o1",
+    ]
+  );
+}
+
+#[test]
+fn commands_apply_after_update() {
+  let Some((mut headless, log)) = headless("commands", &["a", "b"]) else {
+    return;
+  };
+
+  log.take();
+
+  headless.context.push_overlay(Probe {
+    name: "o",
+    log: log.clone(),
+  });
+  headless.context.push_layer(Probe {
+    name: "c",
+    log: log.clone(),
+  });
+  headless.context.replace_layer(Probe {
+    name: "d",
+    log: log.clone(),
+  });
+  headless.frame(1.0 / 60.0).unwrap();
+
+  // replace lets go of the old layer before the new one comes in
+  assert_eq!(
+    log.take(),
+    [
+      "update a",
+      "update b",
+      "attach o",
+      "gpu_init o",
+      "attach c",
+      "gpu_init c",
+      "detach c",
+      "attach d",
+      "gpu_init d",
+      "draw a",
+      "draw b",
+      "draw d",
+      "draw o",
+    ]
+  );
+  assert_eq!(names(&mut headless), ["a", "b", "d", "o"]);
+  assert_eq!(headless.layers.overlay_count(), 1);
+
+  // events go top to bottom
+  headless.send_user_event(());
+  assert_eq!(log.take(), ["event o", "event d", "event b", "event a"]);
+
+  for _ in 0..4 {
+    headless.context.pop_layer();
+  }
+  headless.frame(1.0 / 60.0).unwrap();
+
+  assert_eq!(names(&mut headless), ["o"]);
+  assert_eq!(headless.layers.overlay_count(), 1);
+
+  headless.context.pop_overlay();
+  headless.context.push_layer(Probe {
+    name: "e",
+    log: log.clone(),
+  });
+  headless.frame(1.0 / 60.0).unwrap();
+
+  assert_eq!(names(&mut headless), ["e"]);
+  assert_eq!(headless.layers.overlay_count(), 0);
+
+  log.take();
+
+  let Headless { layers, context, .. } = &mut headless;
+  layers.push_overlay(Probe::boxed("p", &log), context);
+  layers.clear(context);
+
+  assert!(layers.is_empty());
+  assert_eq!(layers.overlay_count(), 0);
+  assert_eq!(log.take(), ["attach p", "gpu_init p", "detach p", "detach e"]);
+}
@@ -0,0 +1,146 @@
+// input recorded from one headless run and played back in another has to
+// produce the same frames
+
+use std::cell::RefCell;
+use std::env;
+use std::path::Path;
+use std::rc::Rc;
+
+use algurulgar::engine::input::record::InputEvent;
+use algurulgar::engine::input::InputState;
+use algurulgar::headless::Headless;
+use algurulgar::winit::event::ElementState;
+use algurulgar::winit::keyboard::KeyCode;
+use algurulgar::{EngineConfig, EngineContext, Layer};
+
+mod common;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Observed {
+  delta_time: f32,
+  w: InputState,
+  egui_wants_keyboard: bool,
+}
+
+/// Writes down what each update sees
+struct Observer(Rc<RefCell<Vec<Observed>>>);
+
+impl Layer for Observer {
+  fn update(&mut self, context: &mut EngineContext) {
+    self.0.borrow_mut().push(Observed {
+      delta_time: context.delta_time,
+      w: context.input.key_state(KeyCode::KeyW),
+      egui_wants_keyboard: context.input.egui_wants_keyboard(),
+    });
+  }
+}
+
+fn headless() -> Option<(Headless, Rc<RefCell<Vec<Observed>>>)> {
+  let observed = Rc::new(RefCell::new(Vec::new()));
+  let config = EngineConfig::new("replay").size(16, 16);
+
+  let headless = common::or_skip(
+    "replay",
+    Headless::new(config, vec![Box::new(Observer(observed.clone()))]),
+  )?;
+
+  Some((headless, observed))
+}
+
+fn key(code: KeyCode, state: ElementState) -> InputEvent {
+  InputEvent::Key {
+    key: code.into(),
+    state,
+  }
+}
+
+// the live run sends input between frames with uneven delta times
+fn record(path: &Path) -> Option<Vec<Observed>> {
+  let (mut headless, observed) = headless()?;
+
+  headless.context.input_replay.record(path).unwrap();
+
+  headless.frame(0.016).unwrap();
+  headless.send_input(key(KeyCode::KeyW, ElementState::Pressed));
+  headless.frame(0.020).unwrap();
+  headless.frame(0.012).unwrap();
+  headless.send_input(key(KeyCode::KeyW, ElementState::Released));
+  headless.frame(0.016).unwrap();
+  headless.send_input(InputEvent::EguiFocus {
+    keyboard: true,
+    pointer: false,
+  });
+  headless.frame(0.016).unwrap();
+
+  headless.context.input_replay.stop();
+
+  let observed = observed.borrow().clone();
+  Some(observed)
+}
+
+#[test]
+fn record_and_play() {
+  let path = env::temp_dir().join(format!("algurulgar-replay-{}.ron", std::process::id()));
+
+  let Some(recorded) = record(&path) else {
+    return;
+  };
+
+  let (mut headless, observed) = headless().unwrap();
+
+  headless.context.input_replay.play(&path).unwrap();
+
+  for _ in 0..recorded.len() {
+    // live input is ignored while the recording plays
+    headless.send_input(key(KeyCode::KeyW, ElementState::Pressed));
+    headless.frame(1.0).unwrap();
+  }
+
+  std::fs::remove_file(&path).unwrap();
+
+  assert_eq!(*observed.borrow(), recorded);
+  assert_eq!(recorded[1].w, InputState::PressedThisFrame);
+  assert_eq!(recorded[3].w, InputState::ReleasedThisFrame);
+  assert!(recorded[4].egui_wants_keyboard);
+}
+
+#[test]
+fn playback_starts_and_ends_with_the_recorded_state() {
+  let path = env::temp_dir().join(format!("algurulgar-replay-held-{}.ron", std::process::id()));
+
+  let Some((mut live, _)) = headless() else {
+    return;
+  };
+
+  // held before the recording starts
+  live.send_input(key(KeyCode::KeyW, ElementState::Pressed));
+  live.frame(0.016).unwrap();
+
+  live.context.input_replay.record(&path).unwrap();
+  live.frame(0.016).unwrap();
+  live.frame(0.016).unwrap();
+  live.context.input_replay.stop();
+
+  let (mut headless, _) = headless().unwrap();
+
+  headless.send_input(key(KeyCode::KeyS, ElementState::Pressed));
+  headless.frame(0.016).unwrap();
+  assert!(headless.context.input.key_pressed(KeyCode::KeyS));
+
+  headless.context.input_replay.play(&path).unwrap();
+  headless.frame(1.0).unwrap();
+
+  // the live key is let go of and the recording starts with its own
+  assert!(!headless.context.input.key_pressed(KeyCode::KeyS));
+  assert!(headless.context.input.key_pressed(KeyCode::KeyW));
+
+  headless.frame(1.0).unwrap();
+  assert!(headless.context.input.key_held(KeyCode::KeyW));
+
+  // past the end nothing the recording held stays down
+  headless.frame(1.0).unwrap();
+  assert!(!headless.context.input_replay.is_playing());
+  assert!(!headless.context.input.key_pressed(KeyCode::KeyW));
+
+  std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,101 @@
+// shader variants kept in the asset registry pick up edits to their files
+
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use algurulgar::headless::Headless;
+use algurulgar::render::shader::preprocess::Defines;
+use algurulgar::render::shader::ShaderSource;
+use algurulgar::EngineConfig;
+
+mod common;
+
+const VERTEX: &str = "#version 140
+in vec2 position;
+void main() {
+  gl_Position = vec4(position, 0.0, 1.0);
+}
+";
+
+const WHITE: &str = "#version 140
+out vec4 color;
+void main() {
+#ifdef RED
+  color = vec4(1.0, 0.0, 0.0, 1.0);
+#else
+  color = vec4(1.0);
+#endif
+}
+";
+
+const BLUE: &str = "#version 140
+out vec4 color;
+void main() {
+#ifdef RED
+  color = vec4(1.0, 0.0, 0.0, 1.0);
+#else
+  color = vec4(0.0, 0.0, 1.0, 1.0);
+#endif
+}
+";
+
+// only the red variant fails to compile
+const BROKEN: &str = "#version 140
+out vec4 color;
+void main() {
+#ifdef RED
+  color = not glsl;
+#else
+  color = vec4(0.0, 1.0, 0.0, 1.0);
+#endif
+}
+";
+
+// mtimes can be coarse, push them forward so the change is always seen
+fn write(path: &Path, source: &str, ahead: u64) {
+  fs::write(path, source).unwrap();
+
+  let modified = SystemTime::now() + Duration::from_secs(ahead);
+  File::options()
+    .write(true)
+    .open(path)
+    .unwrap()
+    .set_modified(modified)
+    .unwrap();
+}
+
+#[test]
+fn variants_reload_together() {
+  let config = EngineConfig::new("shaders").size(8, 8);
+
+  let Some(mut headless) = common::or_skip("shaders", Headless::new(config, Vec::new())) else {
+    return;
+  };
+
+  let dir = std::env::temp_dir().join(format!("algurulgar-variants-{}", std::process::id()));
+  fs::create_dir_all(&dir).unwrap();
+
+  let vertex = dir.join("variant.vert");
+  let fragment = dir.join("variant.frag");
+  write(&vertex, VERTEX, 0);
+  write(&fragment, BLUE, 0);
+
+  let assets = &mut headless.context.assets;
+  let red = Defines::new().with("RED");
+
+  let handle = assets.add_shader_variants(ShaderSource::new(VERTEX, WHITE).with_files(&vertex, &fragment));
+  assets.shader_variant(handle, &Defines::new()).unwrap();
+  assets.shader_variant(handle, &red).unwrap();
+
+  assets.reload_shaders();
+  assert_eq!(assets.shader_variants_source(handle).fragment, BLUE);
+
+  // the base variant would compile, the red one doesn't so both stay
+  write(&fragment, BROKEN, 60);
+  assets.reload_shaders();
+  assert_eq!(assets.shader_variants_source(handle).fragment, BLUE);
+  assert!(assets.shader_variant(handle, &red).is_ok());
+
+  fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,19 +1,22 @@
-use algurulgar::debug::widgets::{MatrixWidget, VectorWidget};
+use std::path::Path;
+
+use algurulgar::debug::widgets::{FrameTimeWidget, MatrixWidget, ProfilerWidget, VectorWidget};
 use algurulgar::egui::{Color32, Context, Widget};
 use algurulgar::engine::events::EventHandler;
-use algurulgar::engine::input::{key_pressed, last_key_pressed, mouse_position};
-use algurulgar::glium::Frame;
+use algurulgar::engine::input::action::{AxisBinding, Binding, InputMap};
 use algurulgar::math::Position;
 use algurulgar::nalgebra::Matrix4;
 use algurulgar::render::camera::ortho::OrthoCameraController;
+use algurulgar::render::capture::CaptureHotkeys;
 use algurulgar::render::renderer::mesh::Mesh;
 use algurulgar::render::renderer2d::text::TextParams;
+use algurulgar::render::target::Target;
 use algurulgar::update::UpdateHandler;
 use algurulgar::winit::event::WindowEvent;
 use algurulgar::winit::keyboard::KeyCode;
 use algurulgar::winit::window::Window;
-use algurulgar::{egui, init_logger, vec2, vec3, Engine, EngineContext, Layer, Vec3};
-use log::info;
+use algurulgar::{egui, init_logger, vec2, vec3, Engine, EngineConfig, EngineContext, Layer, Vec3};
+use log::{info, warn};
 
 struct SandboxLayer {
   camera: OrthoCameraController,
@@ -30,12 +33,31 @@ struct SandboxLayer {
   bunny_debug: String,
 }
 
+const BINDINGS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/bindings.ron");
+
+fn default_bindings() -> InputMap {
+  let buttons = |negative: KeyCode, positive: KeyCode| AxisBinding::Buttons {
+    negative: Binding::Key(negative),
+    positive: Binding::Key(positive),
+  };
+
+  InputMap::new()
+    .with_axis("scale", [buttons(KeyCode::KeyZ, KeyCode::KeyX)])
+    .with_axis("bunny_x", [buttons(KeyCode::ArrowLeft, KeyCode::ArrowRight)])
+    .with_axis("bunny_y", [buttons(KeyCode::ArrowDown, KeyCode::ArrowUp)])
+    .with_axis("bunny_z", [buttons(KeyCode::BracketRight, KeyCode::BracketLeft)])
+    .with_axis("bunny_rot_x", [buttons(KeyCode::KeyK, KeyCode::KeyI)])
+    .with_axis("bunny_rot_y", [buttons(KeyCode::KeyL, KeyCode::KeyJ)])
+    .with_axis("bunny_rot_z", [buttons(KeyCode::KeyO, KeyCode::KeyU)])
+    .with_axis("bunny_scale", [buttons(KeyCode::KeyM, KeyCode::KeyN)])
+}
+
 fn main() {
   init_logger();
 
   info!("hello awa");
 
-  let (engine, event_loop) = Engine::new("awa", vec![Box::new(SandboxLayer::new())]);
+  let (engine, event_loop) = Engine::new(EngineConfig::new("awa"), vec![Box::new(SandboxLayer::new())]);
 
   engine.run(event_loop).unwrap();
 }
@@ -59,70 +81,52 @@ impl SandboxLayer {
 }
 
 impl Layer for SandboxLayer {
+  fn on_attach(&mut self, context: &mut EngineContext) {
+    self.camera.set_viewport(context.viewport);
+    context.capture.hotkeys = CaptureHotkeys::function_keys();
+
+    // written from the defaults on the first run, edit the file to rebind
+    if !Path::new(BINDINGS).exists() {
+      if let Err(e) = default_bindings().save(BINDINGS) {
+        warn!("failed to write {BINDINGS}: {e}");
+      }
+    }
+
+    context.input_map = InputMap::load(BINDINGS).unwrap_or_else(|e| {
+      warn!("using default bindings, failed to load {BINDINGS}: {e}");
+      default_bindings()
+    });
+  }
+
   fn update(&mut self, context: &mut EngineContext) {
     // let time = context.start_time.elapsed().as_secs_f32();
 
-    let mouse_position = mouse_position();
-    self.pos = self.camera.camera().screen_to_world(mouse_position);
+    self.pos = self.camera.camera().mouse_world_position(&context.input);
 
-    self.camera.update(context.delta_time);
+    self.camera.update(&context.input, context.delta_time);
 
-    if key_pressed(KeyCode::KeyX) {
-      self.scale += 0.0001;
-      println!("scale: {}", self.scale);
-    } else if key_pressed(KeyCode::KeyZ) {
-      self.scale -= 0.0001;
+    let (bindings, input) = (&context.input_map, &context.input);
+
+    let scale = bindings.axis(input, "scale");
+    if scale != 0.0 {
+      self.scale += scale * 0.0001;
       println!("scale: {}", self.scale);
     }
 
     // move bunny
-    if key_pressed(KeyCode::ArrowUp) {
-      self.bunny_pos.y += 0.01;
-    } else if key_pressed(KeyCode::ArrowDown) {
-      self.bunny_pos.y -= 0.01;
-    }
-
-    if key_pressed(KeyCode::ArrowLeft) {
-      self.bunny_pos.x -= 0.01;
-    } else if key_pressed(KeyCode::ArrowRight) {
-      self.bunny_pos.x += 0.01;
-    }
-
-    if key_pressed(KeyCode::BracketLeft) {
-      self.bunny_pos.z += 0.01;
-    } else if key_pressed(KeyCode::BracketRight) {
-      self.bunny_pos.z -= 0.01;
-    }
+    self.bunny_pos.x += bindings.axis(input, "bunny_x") * 0.01;
+    self.bunny_pos.y += bindings.axis(input, "bunny_y") * 0.01;
+    self.bunny_pos.z += bindings.axis(input, "bunny_z") * 0.01;
 
     // rotate bunny
-    if key_pressed(KeyCode::KeyI) {
-      self.bunny_rot.x += 0.01;
-    } else if key_pressed(KeyCode::KeyK) {
-      self.bunny_rot.x -= 0.01;
-    }
-
-    if key_pressed(KeyCode::KeyJ) {
-      self.bunny_rot.y += 0.01;
-    } else if key_pressed(KeyCode::KeyL) {
-      self.bunny_rot.y -= 0.01;
-    }
-
-    if key_pressed(KeyCode::KeyU) {
-      self.bunny_rot.z += 0.01;
-    } else if key_pressed(KeyCode::KeyO) {
-      self.bunny_rot.z -= 0.01;
-    }
+    self.bunny_rot.x += bindings.axis(input, "bunny_rot_x") * 0.01;
+    self.bunny_rot.y += bindings.axis(input, "bunny_rot_y") * 0.01;
+    self.bunny_rot.z += bindings.axis(input, "bunny_rot_z") * 0.01;
 
     // scale bunny
-    if key_pressed(KeyCode::KeyN) {
-      self.bunny_scale.x += 0.01;
-      self.bunny_scale.y += 0.01;
-      self.bunny_scale.z += 0.01;
-    } else if key_pressed(KeyCode::KeyM) {
-      self.bunny_scale.x -= 0.01;
-      self.bunny_scale.y -= 0.01;
-      self.bunny_scale.z -= 0.01;
-    }
+    self
+      .bunny_scale
+      .add_scalar_mut(bindings.axis(input, "bunny_scale") * 0.01);
 
     self.bunny_trans = Matrix4::new_rotation(self.bunny_rot);
     self.bunny_trans.append_translation_mut(&self.bunny_pos);
@@ -134,8 +138,8 @@ impl Layer for SandboxLayer {
     );
   }
 
-  fn draw(&mut self, context: &mut EngineContext, frame: &mut Frame) {
-    let mouse = mouse_position();
+  fn draw(&mut self, context: &mut EngineContext, frame: &mut Target) {
+    let mouse = context.input.mouse_position();
 
     let text_params = TextParams::new().scale(self.scale);
 
@@ -155,7 +159,11 @@ impl Layer for SandboxLayer {
       &text_params,
     );
 
-    renderer2d.draw_text(&format!("Key: {}", last_key_pressed()), vec2(-0.95, 0.6), &text_params);
+    renderer2d.draw_text(
+      &format!("Key: {}", context.input.last_key_pressed()),
+      vec2(-0.95, 0.6),
+      &text_params,
+    );
 
     renderer2d.draw_text(
       &format!("Camera: {}", self.camera.camera().view_projection()),
@@ -176,8 +184,8 @@ impl Layer for SandboxLayer {
       // fps
       ui.monospace(context.fps_stats.text.clone());
 
-      ui.label(format!("Mouse: {:?}", mouse_position()));
-      ui.label(format!("Key: {:?}", last_key_pressed()));
+      ui.label(format!("Mouse: {:?}", context.input.mouse_position()));
+      ui.label(format!("Key: {:?}", context.input.last_key_pressed()));
 
       ui.label("Camera:");
       MatrixWidget::new(self.camera.camera().view_projection()).ui(ui);
@@ -206,6 +214,14 @@ impl Layer for SandboxLayer {
       ui.label("hello awa");
       ui.colored_label(Color32::LIGHT_GREEN, "hello awa");
     });
+
+    egui::Window::new("frame times").show(&ctx, |ui| {
+      FrameTimeWidget::new(&mut context.fps_stats).ui(ui);
+    });
+
+    egui::Window::new("profiler").show(&ctx, |ui| {
+      ProfilerWidget::new(&context.profiler).ui(ui);
+    });
   }
 
   fn handle_window_event(&mut self, _context: &mut EngineContext, event: &WindowEvent, window: &Window) -> bool {